@@ -0,0 +1,165 @@
+//! Operator commands, they are run instead of bridge when arguments are given:
+//!
+//! `bridge dead-letters list`
+//! `bridge dead-letters <retry|complete|cancel> <transfer id> <reason>`
+//! `bridge assets list`
+//! `bridge assets add <token|nft> <contract> <realis asset> <decimals>`
+//! `bridge assets <enable|disable> <contract>`
+//!
+//! Operator name is read from `BRIDGE_OPERATOR` or `USER` env and saved in audit log.
+//! Assets are loaded by bridge at start, so it must be restarted after changes.
+//! Realis asset is `native` or id of Realis asset (token) or class (NFT) which bridge account mints.
+//! Bridge pallet events have no asset id, so only one native asset of every kind can be enabled.
+
+use db::Database;
+use primitives::{
+    asset::{Asset, AssetKind, AssetRegistry},
+    db::Resolution,
+};
+use rust_lib::{config::Config, healthchecker::HealthChecker};
+use web3::types::Address;
+
+const USAGE: &str = "Usage:
+    bridge dead-letters list
+    bridge dead-letters <retry|complete|cancel> <transfer id> <reason>
+    bridge assets list
+    bridge assets add <token|nft> <contract> <realis asset> <decimals>
+    bridge assets <enable|disable> <contract>";
+
+/// # Panics
+pub fn run(args: &[String]) {
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    rt.block_on(async {
+        match args.as_slice() {
+            ["dead-letters", "list"] => list(&connect().await).await,
+            ["dead-letters", action, transfer_id, reason @ ..] if !reason.is_empty() => {
+                let resolution = action
+                    .parse::<Resolution>()
+                    .unwrap_or_else(|error| panic!("{}\n{}", error, USAGE));
+                resolve(&connect().await, transfer_id, resolution, &reason.join(" ")).await;
+            }
+            ["assets", "list"] => list_assets(&connect().await).await,
+            ["assets", "add", kind, contract, realis_asset, decimals] => {
+                let asset = Asset {
+                    contract: contract.parse().unwrap_or_else(|_| panic!("Invalid contract address\n{}", USAGE)),
+                    kind: kind.parse::<AssetKind>().unwrap_or_else(|error| panic!("{}\n{}", error, USAGE)),
+                    realis_asset: String::from(*realis_asset),
+                    decimals: decimals.parse().unwrap_or_else(|_| panic!("Invalid decimals\n{}", USAGE)),
+                    enabled: true,
+                };
+                add_asset(&connect().await, &asset).await;
+            }
+            ["assets", action @ ("enable" | "disable"), contract] => {
+                let contract = contract.parse().unwrap_or_else(|_| panic!("Invalid contract address\n{}", USAGE));
+                enable_asset(&connect().await, &contract, *action == "enable").await;
+            }
+            _ => println!("{}", USAGE),
+        }
+    });
+}
+
+async fn list(db: &Database) {
+    let dead_letters = db.get_dead_letters().await.expect("Cannot load dead letters");
+    if dead_letters.is_empty() {
+        println!("No dead letters");
+    }
+
+    for dead_letter in dead_letters {
+        println!("{} ({})", dead_letter.transfer_id, dead_letter.chain);
+        println!("    error: {}", dead_letter.error);
+        if dead_letter.attempts.is_empty() {
+            println!("    attempts: none");
+        }
+        for attempt in dead_letter.attempts {
+            let created_at = attempt.created_at.as_deref().unwrap_or("unknown time");
+            match attempt.error {
+                Some(error) => println!("    attempt {} at {}: {}", attempt.tx_hash, created_at, error),
+                None => println!("    attempt {} at {}", attempt.tx_hash, created_at),
+            }
+        }
+        println!("    payload: {}", dead_letter.payload);
+    }
+}
+
+async fn list_assets(db: &Database) {
+    let assets = db.get_assets().await.expect("Cannot load assets");
+    if assets.is_empty() {
+        println!("No assets");
+    }
+
+    for asset in assets {
+        println!(
+            "{:?} {} -> {} (decimals: {}, {})",
+            asset.contract,
+            asset.kind.as_str(),
+            asset.realis_asset,
+            asset.decimals,
+            if asset.enabled { "enabled" } else { "disabled" }
+        );
+    }
+}
+
+async fn add_asset(db: &Database, asset: &Asset) {
+    if let Err(error) = asset.check_routable() {
+        println!("{}", error);
+        return;
+    }
+    // Bridge refuses to start with registry it can't route
+    let mut assets = db.get_assets().await.expect("Cannot load assets");
+    assets.retain(|registered| registered.contract != asset.contract);
+    assets.push(asset.clone());
+    if let Err(error) = AssetRegistry::new(assets, 0) {
+        println!("{}", error);
+        return;
+    }
+
+    if db.add_asset(asset).await.expect("Cannot register asset") {
+        println!("{:?} is registered, restart bridge to listen it", asset.contract);
+    } else {
+        println!("{:?} is already registered", asset.contract);
+    }
+}
+
+async fn enable_asset(db: &Database, contract: &Address, enabled: bool) {
+    if db.set_asset_enabled(contract, enabled).await.expect("Cannot update asset") {
+        let state = if enabled { "enabled" } else { "disabled" };
+        println!("{:?} is {}, restart bridge to apply", contract, state);
+    } else {
+        println!("No asset for {:?}", contract);
+    }
+}
+
+async fn resolve(db: &Database, transfer_id: &str, resolution: Resolution, reason: &str) {
+    let operator = Config::key_from_value("BRIDGE_OPERATOR")
+        .or_else(|_| Config::key_from_value("USER"))
+        .expect("Missing env BRIDGE_OPERATOR");
+
+    if db
+        .resolve_dead_letter(transfer_id, resolution, &operator, reason)
+        .await
+        .expect("Cannot resolve dead letter")
+    {
+        println!("{} is resolved with `{}` by {}", transfer_id, resolution.as_str(), operator);
+    } else {
+        println!("No open dead letter for {}", transfer_id);
+    }
+}
+
+/// Database client reports its connection state to health checker, nobody polls it
+/// for one-shot command, so it is bound to random local port.
+/// Schema is brought to version of this binary like on bridge start,
+/// so commands never run against tables they don't know.
+async fn connect() -> Database {
+    let health_checker = HealthChecker::new("127.0.0.1:0", 10000)
+        .await
+        .expect("Healthchecker error");
+
+    let db = crate::database(health_checker).await;
+    db.migrate().await.expect("Cannot migrate database schema");
+    db
+}
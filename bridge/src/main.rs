@@ -1,13 +1,28 @@
+mod cli;
+
 use rust_lib::{async_logger, config::Config};
-use std::sync::Arc;
+use std::{fs, path::PathBuf, sync::Arc, time::Duration};
 
-use bsc_adapter::BinanceHandler;
+use bsc_adapter::{
+    gas::{GasPolicy, GasPrice},
+    key_provider::{KeyProvider, KeystoreFile, RawKeyFile, VaultKv},
+    signer::{LocalSigner, RemoteSigner, Signer},
+    BinanceHandler,
+};
+use bsc_listener::ScanMode;
 use db::Database;
 use futures::future::join_all;
 use log::{error, info, LevelFilter};
+use primitives::{
+    asset::{Asset, AssetKind, AssetRegistry, NATIVE},
+    attestation::{Attestor, Quorum},
+    retry::RetryPolicy,
+};
+use realis_adapter::signer::{PairSigner, RemoteSigner as RealisRemoteSigner, Signer as RealisSigner};
 use realis_listener::listener_builder::BlockListenerBuilder;
-use rust_lib::{blockchain::wallets::RealisWallet, healthchecker::HealthChecker};
+use rust_lib::healthchecker::HealthChecker;
 use tokio::sync::mpsc;
+use web3::types::Address;
 
 #[allow(clippy::too_many_lines)]
 fn main() {
@@ -17,6 +32,13 @@ fn main() {
         Err(_) => async_logger::init(LevelFilter::Trace.to_string()),
     };
 
+    // Operator commands are run instead of bridge
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        cli::run(&args);
+        return;
+    }
+
     // Read tokio options from env
     let workers_number = Config::key_from_value("WORKERS_NUMBER").expect("Missing env: WORKERS_NUMBER");
     let workers_number = workers_number
@@ -28,10 +50,33 @@ fn main() {
     }
 
     let binance_url = Config::key_from_value("BINANCE_URL").expect("Missing env BINANCE_URL");
-    let token_contract_address = Config::key_from_value("ADDRESS_TOKENS").expect("Missing env ADDRESS_TOKENS");
-    let nft_contract_address = Config::key_from_value("ADDRESS_NFT").expect("Missing env ADDRESS_NFT");
-    let token_topic = Config::key_from_value("TOKEN_TOPIC").expect("Missing env TOKEN_TOPIC");
-    let nft_topic = Config::key_from_value("NFT_TOPIC").expect("Missing env NFT_TOPIC");
+    // Contracts from env are registered as native assets on first start
+    let token_contract_address = Config::key_from_value("ADDRESS_TOKENS").ok();
+    let nft_contract_address = Config::key_from_value("ADDRESS_NFT").ok();
+    // Topics are computed from contract ABIs, env only overrides them
+    let token_topic = Config::key_from_value("TOKEN_TOPIC").ok();
+    let nft_topic = Config::key_from_value("NFT_TOPIC").ok();
+    let bsc_confirmations = Config::key_from_value("BSC_CONFIRMATIONS")
+        .map(|value| {
+            value
+                .parse::<u64>()
+                .expect("BSC_CONFIRMATIONS env must be decimal number")
+        })
+        .unwrap_or(15);
+    let bsc_scan_mode = Config::key_from_value("BSC_SCAN_MODE")
+        .map(|value| value.parse::<ScanMode>().expect("BSC_SCAN_MODE env must be `logs` or `receipts`"))
+        .unwrap_or(ScanMode::Logs(1000));
+    let realis_max_batch = Config::key_from_value("REALIS_MAX_BATCH")
+        .map(|value| value.parse::<usize>().expect("REALIS_MAX_BATCH env must be decimal number"))
+        .unwrap_or(1);
+    let realis_batch_wait = Config::key_from_value("REALIS_BATCH_WAIT")
+        .map(|value| value.parse::<u64>().expect("REALIS_BATCH_WAIT env must be decimal number"))
+        .unwrap_or(3);
+    let bsc_max_in_flight = Config::key_from_value("BSC_MAX_IN_FLIGHT").map(|value| {
+        value
+            .parse::<usize>()
+            .expect("BSC_MAX_IN_FLIGHT env must be decimal number")
+    });
 
     // Read healthchecker options from env file
     let healthchecker_address = Config::key_from_value("HEALTHCHECK").expect("Missing env HEALTHCHECK");
@@ -39,12 +84,6 @@ fn main() {
     // Read blockchain connection options from env file
     let url = Config::key_from_value("REALIS_URL").expect("Missing env URL");
 
-    let db_host = Config::key_from_value("DATABASE_HOST").expect("Missing env DATABASE_HOST");
-    let db_port = Config::key_from_value("DATABASE_PORT").expect("Missing env DATABASE_PORT");
-    let db_user = Config::key_from_value("DATABASE_USER").expect("Missing env DATABASE_USER");
-    let db_password = Config::key_from_value("DATABASE_PASSWORD").expect("Missing env DATABASE_PASSWORD");
-    let db_name = Config::key_from_value("DATABASE_NAME").expect("Missing env DATABASE_NAME");
-
     let rt = tokio::runtime::Builder::new_multi_thread()
         .worker_threads(workers_number)
         .enable_all()
@@ -54,127 +93,380 @@ fn main() {
         // Init some variables
         let (binance_tx, binance_rx) = mpsc::channel(1024);
         let (realis_tx, realis_rx) = mpsc::channel(1024);
-        let pair = rust_lib::blockchain::wallets::BridgeMaster::get_private();
-        // TODO get from vault
-        let binance_master_key = "98a946173492e8e5b73577341cea3c3b8e92481bfcea038b8fd7c1940d0cd42f";
+        let realis_signer = realis_signer();
+        let binance_signer = bsc_signer().await;
 
         let health_checker = HealthChecker::new(&healthchecker_address, 10000)
             .await
             .expect("Healthchecker error");
 
-        let db = Arc::new(
-            Database::new(
-                &db_host,
-                &db_port,
-                &db_user,
-                &db_password,
-                &db_name,
-                true,
-                health_checker.clone(),
-            )
-            .await
-            .unwrap(),
-        );
-        match db.import_tables_from_file("./db/res/tables.sql").await {
-            Ok(_) => info!("Creating tables was successful"),
-            Err(error) => error!("Cannot create tables: {:?}", error),
-        }
+        let db = Arc::new(database(health_checker.clone()).await);
+        db.migrate().await.expect("Cannot migrate database schema");
+        info!("Database schema is up to date");
 
         // Init listener modules
 
         let mut modules = vec![];
 
-        let binance_handler = BinanceHandler::new(
+        let mut binance_handler = BinanceHandler::new(
             binance_rx,
             realis_tx.clone(),
             health_checker.clone(),
             &binance_url,
-            token_contract_address.clone(),
-            nft_contract_address.clone(),
-            binance_master_key,
+            binance_signer,
             Arc::clone(&db),
         );
-        modules.push(tokio::spawn(binance_handler.handle()));
-
-        let realis_adapter = realis_adapter::RealisAdapter::new(
+        if let Ok(max_in_flight) = bsc_max_in_flight {
+            binance_handler = binance_handler.with_max_in_flight(max_in_flight);
+        }
+        binance_handler = binance_handler
+            .with_gas_policy(gas_policy())
+            .with_retry_policy(retry_policy());
+        let mut realis_adapter = realis_adapter::RealisAdapter::new(
             realis_rx,
             binance_tx.clone(),
             health_checker.clone(),
             &url,
-            pair,
+            realis_signer,
             Arc::clone(&db),
+        )
+        .expect("Cannot connect to Realis")
+        .with_batch(realis_max_batch, Duration::from_secs(realis_batch_wait))
+        .with_retry_policy(retry_policy());
+
+        if let Some(contract) = &token_contract_address {
+            register_asset(&db, &binance_handler, contract, AssetKind::Token).await;
+        }
+        if let Some(contract) = &nft_contract_address {
+            register_asset(&db, &binance_handler, contract, AssetKind::Nft).await;
+        }
+        // Amounts are converted between decimals of each asset and Realis balance
+        let realis_decimals = realis_adapter
+            .token_decimals()
+            .expect("Cannot read decimals of Realis balance");
+        let assets = Arc::new(
+            AssetRegistry::new(db.get_assets().await.expect("Cannot load assets"), realis_decimals)
+                .expect("Invalid asset registry"),
         );
+        for asset in assets.enabled() {
+            let decimals = assets.decimals(asset).expect("Asset decimals can't be bridged");
+            info!(
+                "Bridged {} {:?} <-> Realis {}, decimals: BSC {}, Realis {}",
+                asset.kind.as_str(),
+                asset.contract,
+                asset.realis_asset,
+                decimals.bsc(),
+                decimals.realis()
+            );
+        }
+        binance_handler = binance_handler.with_assets(Arc::clone(&assets));
+        realis_adapter = realis_adapter.with_assets(Arc::clone(&assets));
+
+        // Multi-operator mode, releases need attestations of other operators
+        let attestation = attestation(Arc::clone(&assets)).await;
+
+        let mut realis_listener =
+            BlockListenerBuilder::new(&url, binance_tx, health_checker.clone(), Arc::clone(&db)).build();
+        let mut bsc_listener = bsc_listener::BlockListener::new(
+            binance_url,
+            realis_tx,
+            health_checker.clone(),
+            Arc::clone(&db),
+            assets,
+            token_topic.as_deref(),
+            nft_topic.as_deref(),
+            bsc_confirmations,
+            bsc_scan_mode,
+        )
+        .await
+        .expect("Cannot start BSC listener");
 
+        if let Some((attestor, quorum)) = attestation {
+            info!("Attestation mode: operator {:?}, threshold {}", attestor.operator(), quorum.threshold());
+            binance_handler = binance_handler.with_quorum(quorum.clone());
+            realis_adapter = realis_adapter.with_quorum(quorum);
+            realis_listener = realis_listener.with_attestor(Arc::clone(&attestor));
+            bsc_listener = bsc_listener.with_attestor(attestor);
+        }
+
+        modules.push(tokio::spawn(binance_handler.handle()));
         modules.push(tokio::spawn({
             async move {
                 realis_adapter.handle().await;
             }
         }));
 
-        match Config::key_from_value("RESTORE").map(|value| value == *"true") {
-            Ok(true) => {
-                let last_block = db.get_last_block_realis().await.unwrap_or(0);
-                let (mut listener, tx) =
-                    BlockListenerBuilder::new(&url, binance_tx, health_checker.clone(), Arc::clone(&db)).build();
-                modules.push(tokio::spawn({
-                    async move {
-                        listener.listen_with_restore(last_block, tx).await;
-                    }
-                }));
-            }
-            Ok(false) | Err(_) => {
-                let (mut listener, _) =
-                    BlockListenerBuilder::new(&url, binance_tx, health_checker.clone(), Arc::clone(&db)).build();
-                modules.push(tokio::spawn({
-                    async move {
-                        listener.listen().await;
-                    }
-                }));
-            }
-        }
+        let restore = matches!(Config::key_from_value("RESTORE").as_deref(), Ok("true"));
 
-        match Config::key_from_value("RESTORE").map(|value| value == *"true") {
-            Ok(true) => {
-                let last_block = db.get_last_block_bsc().await.unwrap();
-                let mut bsc_listener = bsc_listener::BlockListener::new(
-                    binance_url,
-                    realis_tx,
-                    health_checker.clone(),
-                    Arc::clone(&db),
-                    &token_contract_address,
-                    &nft_contract_address,
-                    &token_topic,
-                    &nft_topic,
-                )
-                .await
-                .unwrap();
-                modules.push(tokio::spawn({
-                    async move {
-                        bsc_listener.listen_with_restore(last_block).await;
-                    }
-                }));
+        let last_block = if restore {
+            db.get_cursor_realis().await.unwrap().map(|(block, _)| block)
+        } else {
+            None
+        };
+        modules.push(tokio::spawn({
+            async move {
+                match last_block {
+                    Some(last_block) => realis_listener.listen_with_restore(last_block).await,
+                    None => realis_listener.listen().await,
+                }
             }
-            Ok(false) | Err(_) => {
-                let mut bsc_listener = bsc_listener::BlockListener::new(
-                    binance_url,
-                    realis_tx,
-                    health_checker.clone(),
-                    Arc::clone(&db),
-                    &token_contract_address,
-                    &nft_contract_address,
-                    &token_topic,
-                    &nft_topic,
-                )
-                .await
-                .unwrap();
-                modules.push(tokio::spawn({
-                    async move {
-                        bsc_listener.listen().await;
-                    }
-                }));
+        }));
+
+        let last_block = if restore {
+            db.get_cursor_bsc().await.unwrap().map(|(block, _)| block)
+        } else {
+            None
+        };
+        modules.push(tokio::spawn({
+            async move {
+                match last_block {
+                    Some(last_block) => bsc_listener.listen_with_restore(last_block).await,
+                    None => bsc_listener.listen().await,
+                }
             }
-        }
+        }));
 
         join_all(modules).await;
     });
 }
+
+/// Connect to database from `DATABASE_*` env, operator commands use the same connection options.
+async fn database(health_checker: HealthChecker) -> Database {
+    let db_host = Config::key_from_value("DATABASE_HOST").expect("Missing env DATABASE_HOST");
+    let db_port = Config::key_from_value("DATABASE_PORT").expect("Missing env DATABASE_PORT");
+    let db_user = Config::key_from_value("DATABASE_USER").expect("Missing env DATABASE_USER");
+    let db_password = Config::key_from_value("DATABASE_PASSWORD").expect("Missing env DATABASE_PASSWORD");
+    let db_name = Config::key_from_value("DATABASE_NAME").expect("Missing env DATABASE_NAME");
+
+    Database::new(&db_host, &db_port, &db_user, &db_password, &db_name, true, health_checker)
+        .await
+        .expect("Cannot connect to database")
+}
+
+/// Build signer of BSC transactions from `BSC_SIGNER` env, key is kept in bridge by default.
+async fn bsc_signer() -> Box<dyn Signer> {
+    match Config::key_from_value("BSC_SIGNER").as_deref() {
+        Ok("remote") => Box::new(RemoteSigner::new(
+            &Config::key_from_value("BSC_SIGNER_URL").expect("Missing env BSC_SIGNER_URL"),
+            Config::key_from_value("BSC_SIGNER_ADDRESS")
+                .expect("Missing env BSC_SIGNER_ADDRESS")
+                .parse()
+                .expect("BSC_SIGNER_ADDRESS env must be an address"),
+        )),
+        Ok("local") | Err(_) => Box::new(LocalSigner::new(
+            key_provider("BSC")
+                .load()
+                .await
+                .expect("Cannot load BSC master key"),
+        )),
+        Ok(signer) => panic!("BSC_SIGNER env must be `local` or `remote`, got `{}`", signer),
+    }
+}
+
+/// Read retry options of transient errors, shared by both adapters.
+fn retry_policy() -> RetryPolicy {
+    let number = |name: &str, default: u64| {
+        Config::key_from_value(name)
+            .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{} env must be decimal number", name)))
+            .unwrap_or(default)
+    };
+
+    let max_attempts = number("RETRY_MAX_ATTEMPTS", 5);
+    let max_attempts = u32::try_from(max_attempts)
+        .unwrap_or_else(|_| panic!("RETRY_MAX_ATTEMPTS env must be at most {}, got {}", u32::MAX, max_attempts));
+
+    RetryPolicy::new(
+        max_attempts,
+        Duration::from_millis(number("RETRY_INITIAL_DELAY", 1000)),
+        Duration::from_millis(number("RETRY_MAX_DELAY", 60000)),
+    )
+}
+
+/// Read BSC gas options, node suggested price is used by default.
+fn gas_policy() -> GasPolicy {
+    let number = |name: &str| {
+        Config::key_from_value(name)
+            .ok()
+            .map(|value| value.parse::<u64>().unwrap_or_else(|_| panic!("{} env must be decimal number", name)))
+    };
+
+    let price = Config::key_from_value("BSC_GAS_PRICE")
+        .map(|value| {
+            value
+                .parse::<GasPrice>()
+                .expect("BSC_GAS_PRICE env must be `node`, `node:<percent>` or `fixed:<wei>`")
+        })
+        .unwrap_or(GasPrice::Node(100));
+    let mut policy = GasPolicy::new(price);
+
+    if let Ok(max_price) = Config::key_from_value("BSC_GAS_PRICE_MAX") {
+        policy = policy.with_max_price(
+            web3::types::U256::from_dec_str(&max_price).expect("BSC_GAS_PRICE_MAX env must be decimal number"),
+        );
+    }
+    if let Some(margin) = number("BSC_GAS_MARGIN") {
+        policy = policy.with_margin(margin);
+    }
+    if let Ok(margins) = Config::key_from_value("BSC_GAS_MARGINS") {
+        for margin in margins.split(',') {
+            let (function, margin) = margin
+                .trim()
+                .split_once(':')
+                .and_then(|(function, margin)| Some((function, margin.parse::<u64>().ok()?)))
+                .expect("BSC_GAS_MARGINS env must be comma separated `<function>:<percent>`");
+            policy = policy.with_function_margin(function, margin);
+        }
+    }
+
+    policy.with_replacement(
+        Duration::from_secs(number("BSC_REPLACE_AFTER").unwrap_or(60)),
+        number("BSC_GAS_BUMP").unwrap_or(15),
+    )
+}
+
+/// Build signer of Realis extrinsics from `REALIS_SIGNER` env.
+fn realis_signer() -> Box<dyn RealisSigner> {
+    let signer = Config::key_from_value("REALIS_SIGNER").expect("Missing env REALIS_SIGNER");
+
+    match signer.as_str() {
+        "seed" => Box::new(
+            PairSigner::from_seed_file(&PathBuf::from(
+                Config::key_from_value("REALIS_SEED_FILE").expect("Missing env REALIS_SEED_FILE"),
+            ))
+            .expect("Cannot load Realis master key"),
+        ),
+        "keystore" => Box::new(
+            PairSigner::from_keystore(
+                &PathBuf::from(Config::key_from_value("REALIS_KEYSTORE").expect("Missing env REALIS_KEYSTORE")),
+                &secret_from_env("REALIS_KEYSTORE_PASSWORD"),
+            )
+            .expect("Cannot load Realis master key"),
+        ),
+        "remote" => Box::new(
+            RealisRemoteSigner::new(
+                &Config::key_from_value("REALIS_SIGNER_URL").expect("Missing env REALIS_SIGNER_URL"),
+                &Config::key_from_value("REALIS_SIGNER_ACCOUNT").expect("Missing env REALIS_SIGNER_ACCOUNT"),
+            )
+            .expect("Invalid REALIS_SIGNER_ACCOUNT env"),
+        ),
+        signer => panic!("REALIS_SIGNER env must be `seed`, `keystore` or `remote`, got `{}`", signer),
+    }
+}
+
+/// Build source of secp256k1 key from `{prefix}_KEY_PROVIDER` env.
+fn key_provider(prefix: &str) -> Box<dyn KeyProvider> {
+    let env = |name: &str| {
+        let name = format!("{}_{}", prefix, name);
+        Config::key_from_value(&name).unwrap_or_else(|_| panic!("Missing env {}", name))
+    };
+
+    match env("KEY_PROVIDER").as_str() {
+        "keystore" => Box::new(KeystoreFile::new(
+            PathBuf::from(env("KEYSTORE")),
+            secret_from_env(&format!("{}_KEYSTORE_PASSWORD", prefix)),
+        )),
+        "file" => Box::new(RawKeyFile::new(PathBuf::from(env("KEY_FILE")))),
+        "vault" => Box::new(VaultKv::new(
+            &Config::key_from_value("VAULT_ADDR").expect("Missing env VAULT_ADDR"),
+            secret_from_env("VAULT_TOKEN"),
+            &env("VAULT_PATH"),
+            &Config::key_from_value(&format!("{}_VAULT_FIELD", prefix))
+                .unwrap_or_else(|_| String::from("private_key")),
+        )),
+        provider => panic!(
+            "{}_KEY_PROVIDER env must be `keystore`, `file` or `vault`, got `{}`",
+            prefix, provider
+        ),
+    }
+}
+
+/// Contract set in env is registered as native asset, if it isn't registered yet.
+async fn register_asset(db: &Database, binance_handler: &BinanceHandler, contract: &str, kind: AssetKind) {
+    let contract = contract
+        .parse::<Address>()
+        .unwrap_or_else(|_| panic!("Invalid {} contract address: {}", kind.as_str(), contract));
+    let assets = db.get_assets().await.expect("Cannot load assets");
+    if assets.iter().any(|asset| asset.contract == contract) {
+        return;
+    }
+    // Transfers from Realis go to the only enabled native asset of their kind
+    if let Some(native) = assets
+        .iter()
+        .find(|asset| asset.kind == kind && asset.is_native() && asset.enabled)
+    {
+        panic!(
+            "Native {} {:?} is already registered, disable it with `bridge assets disable {:?}` to use {:?}",
+            kind.as_str(),
+            native.contract,
+            native.contract,
+            contract
+        );
+    }
+
+    let decimals = match kind {
+        AssetKind::Token => binance_handler
+            .token_decimals(contract)
+            .await
+            .expect("Cannot read decimals of BSC token"),
+        AssetKind::Nft => 0,
+    };
+    let asset = Asset {
+        contract,
+        kind,
+        realis_asset: String::from(NATIVE),
+        decimals,
+        enabled: true,
+    };
+    if db.add_asset(&asset).await.expect("Cannot register asset") {
+        info!("Registered {} {:?} as native asset", kind.as_str(), contract);
+    }
+}
+
+/// Read multi-operator options, mode is enabled by `ATTESTATION_THRESHOLD` env.
+/// Operator key is loaded like BSC key, with `ATTESTATION_` prefix.
+async fn attestation(assets: Arc<AssetRegistry>) -> Option<(Arc<Attestor>, Quorum)> {
+    let threshold = Config::key_from_value("ATTESTATION_THRESHOLD")
+        .ok()?
+        .parse::<usize>()
+        .expect("ATTESTATION_THRESHOLD env must be decimal number");
+    let operators = Config::key_from_value("ATTESTATION_OPERATORS")
+        .expect("Missing env ATTESTATION_OPERATORS")
+        .split(',')
+        .map(|operator| {
+            operator
+                .trim()
+                .parse::<Address>()
+                .expect("ATTESTATION_OPERATORS env must be comma separated addresses")
+        })
+        .collect();
+    let mut quorum = Quorum::new(operators, threshold).expect("Invalid attestation options");
+    if let Ok(takeover) = Config::key_from_value("ATTESTATION_TAKEOVER") {
+        quorum = quorum.with_takeover(Duration::from_secs(
+            takeover.parse().expect("ATTESTATION_TAKEOVER env must be decimal number"),
+        ));
+    }
+
+    let key = key_provider("ATTESTATION")
+        .load()
+        .await
+        .expect("Cannot load attestation key");
+
+    let attestor = Attestor::new(*key.secret()).with_assets(assets);
+    let quorum = quorum
+        .with_operator(attestor.operator())
+        .expect("Attestation key must belong to one of ATTESTATION_OPERATORS");
+
+    Some((Arc::new(attestor), quorum))
+}
+
+/// Read secret from env `name` or from file set in env `{name}_FILE`.
+fn secret_from_env(name: &str) -> String {
+    Config::key_from_value(name).unwrap_or_else(|_| {
+        let path = Config::key_from_value(&format!("{}_FILE", name))
+            .unwrap_or_else(|_| panic!("Missing env {} or {}_FILE", name, name));
+        fs::read_to_string(&path)
+            .unwrap_or_else(|error| panic!("Cannot read {}: {:?}", path, error))
+            .trim_end()
+            .to_string()
+    })
+}
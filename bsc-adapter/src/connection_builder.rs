@@ -1,9 +1,7 @@
-use primitives::Error;
+use primitives::{asset::AssetKind, Error};
 
 use web3::{contract::Contract, transports::WebSocket, types::Address, Web3};
 
-use std::str::FromStr;
-
 pub struct ConnectionBuilder {
     url: String,
 }
@@ -17,20 +15,16 @@ impl ConnectionBuilder {
         Ok(Web3::new(WebSocket::new(&self.url).await.map_err(Error::Web3)?))
     }
 
-    pub async fn token(connection: Web3<WebSocket>, contract_address: &str) -> Result<Contract<WebSocket>, Error> {
-        let address =
-            Address::from_str(contract_address).map_err(|error| Error::Custom(format!("{:?}", error)))?;
-
-        let abi = include_bytes!("./../res/BEP20.abi");
-
-        Contract::from_json(connection.eth(), address, abi).map_err(|error| Error::Custom(format!("{:?}", error)))
-    }
-
-    pub async fn nft(connection: Web3<WebSocket>, contract_address: &str) -> Result<Contract<WebSocket>, Error> {
-        let address =
-            Address::from_str(contract_address).map_err(|error| Error::Custom(format!("{:?}", error)))?;
-
-        let abi = include_bytes!("./../res/BEP721.abi");
+    /// Contract of asset with ABI of its kind
+    pub fn contract(
+        connection: Web3<WebSocket>,
+        address: Address,
+        kind: AssetKind,
+    ) -> Result<Contract<WebSocket>, Error> {
+        let abi: &[u8] = match kind {
+            AssetKind::Token => include_bytes!("./../res/BEP20.abi"),
+            AssetKind::Nft => include_bytes!("./../res/BEP721.abi"),
+        };
 
         Contract::from_json(connection.eth(), address, abi).map_err(|error| Error::Custom(format!("{:?}", error)))
     }
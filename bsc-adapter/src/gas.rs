@@ -0,0 +1,203 @@
+use primitives::Error;
+use std::{collections::HashMap, str::FromStr, time::Duration};
+use web3::{transports::WebSocket, types::U256, Web3};
+
+/// Source of gas price for new transactions
+#[derive(Debug, Clone, Copy)]
+pub enum GasPrice {
+    /// Always the same price in wei
+    Fixed(U256),
+    /// Price suggested by node, multiplied by given percent
+    Node(u64),
+}
+
+impl FromStr for GasPrice {
+    type Err = String;
+
+    /// Parse `fixed:<wei>`, `node` or `node:<percent>`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (kind, argument) = match value.split_once(':') {
+            Some((kind, argument)) => (kind, Some(argument)),
+            None => (value, None),
+        };
+
+        match (kind, argument) {
+            ("node", None) => Ok(GasPrice::Node(100)),
+            ("node", Some(percent)) => percent
+                .parse()
+                .map(GasPrice::Node)
+                .map_err(|_| format!("Invalid gas price multiplier: {}", percent)),
+            ("fixed", Some(price)) => U256::from_dec_str(price)
+                .map(GasPrice::Fixed)
+                .map_err(|_| format!("Invalid fixed gas price: {}", price)),
+            _ => Err(format!("Unknown gas price strategy: {}", value)),
+        }
+    }
+}
+
+/// How gas price and gas limit of BSC transactions are chosen
+/// and when transaction which isn't mined is replaced.
+#[derive(Debug, Clone)]
+pub struct GasPolicy {
+    price: GasPrice,
+    /// Price is never higher, also for replacements
+    max_price: Option<U256>,
+    /// Percent added to gas estimation
+    margin: u64,
+    /// Margin of specific contract functions
+    function_margins: HashMap<String, u64>,
+    /// Transaction is replaced when it isn't mined during this time
+    replace_after: Duration,
+    /// Percent added to gas price of replaced transaction
+    bump: u64,
+}
+
+impl Default for GasPolicy {
+    fn default() -> Self {
+        Self {
+            price: GasPrice::Node(100),
+            max_price: None,
+            margin: 20,
+            function_margins: HashMap::new(),
+            replace_after: Duration::from_secs(60),
+            bump: 15,
+        }
+    }
+}
+
+impl GasPolicy {
+    #[must_use]
+    pub fn new(price: GasPrice) -> Self {
+        Self {
+            price,
+            ..Self::default()
+        }
+    }
+
+    #[must_use]
+    pub fn with_max_price(mut self, max_price: U256) -> Self {
+        self.max_price = Some(max_price);
+        self
+    }
+
+    #[must_use]
+    pub fn with_margin(mut self, margin: u64) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    #[must_use]
+    pub fn with_function_margin(mut self, function: &str, margin: u64) -> Self {
+        self.function_margins.insert(String::from(function), margin);
+        self
+    }
+
+    /// Nodes reject replacement with less than 10% higher price.
+    #[must_use]
+    pub fn with_replacement(mut self, replace_after: Duration, bump: u64) -> Self {
+        self.replace_after = replace_after;
+        self.bump = bump.max(10);
+        self
+    }
+
+    #[must_use]
+    pub fn replace_after(&self) -> Duration {
+        self.replace_after
+    }
+
+    /// Price of new transaction.
+    /// # Errors
+    pub async fn gas_price(&self, connection: &Web3<WebSocket>) -> Result<U256, Error> {
+        let price = match self.price {
+            GasPrice::Fixed(price) => price,
+            GasPrice::Node(percent) => {
+                let price = connection.eth().gas_price().await.map_err(Error::Web3)?;
+                price * percent / 100
+            }
+        };
+
+        Ok(self.cap(price))
+    }
+
+    /// Gas limit of call to contract `function` with given estimation.
+    #[must_use]
+    pub fn gas_limit(&self, function: &str, estimate: U256) -> U256 {
+        let margin = self.function_margins.get(function).copied().unwrap_or(self.margin);
+
+        estimate * (100 + margin) / 100
+    }
+
+    /// Price of replacement transaction, `None` when price is already at maximum.
+    #[must_use]
+    pub fn bump(&self, price: U256) -> Option<U256> {
+        let bumped = (price * (100 + self.bump) / 100).max(price + 1);
+        let bumped = self.cap(bumped);
+
+        if bumped > price {
+            Some(bumped)
+        } else {
+            None
+        }
+    }
+
+    fn cap(&self, price: U256) -> U256 {
+        match self.max_price {
+            Some(max_price) => price.min(max_price),
+            None => price,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gas_price_is_parsed() {
+        assert!(matches!("node".parse::<GasPrice>(), Ok(GasPrice::Node(100))));
+        assert!(matches!("node:125".parse::<GasPrice>(), Ok(GasPrice::Node(125))));
+        assert!(matches!(
+            "fixed:5000000000".parse::<GasPrice>(),
+            Ok(GasPrice::Fixed(price)) if price == U256::from(5_000_000_000_u64)
+        ));
+
+        for invalid in ["", "fixed", "fixed:5gwei", "node:-1", "node:", "auction:10"] {
+            assert!(invalid.parse::<GasPrice>().is_err(), "{} is parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn replacement_price_is_bumped_at_least_by_ten_percent() {
+        let policy = GasPolicy::default().with_replacement(Duration::from_secs(30), 5);
+
+        assert_eq!(policy.replace_after(), Duration::from_secs(30));
+        assert_eq!(policy.bump(U256::from(1000)), Some(U256::from(1100)));
+        // Rounding can't leave price unchanged
+        assert_eq!(policy.bump(U256::from(5)), Some(U256::from(6)));
+
+        let policy = GasPolicy::default().with_replacement(Duration::from_secs(30), 50);
+        assert_eq!(policy.bump(U256::from(1000)), Some(U256::from(1500)));
+    }
+
+    #[test]
+    fn price_is_capped() {
+        let policy = GasPolicy::new(GasPrice::Fixed(U256::from(1000))).with_max_price(U256::from(1100));
+
+        assert_eq!(policy.bump(U256::from(1000)), Some(U256::from(1100)));
+        assert_eq!(policy.bump(U256::from(1050)), Some(U256::from(1100)));
+        // Nothing to replace with when price is already at maximum
+        assert_eq!(policy.bump(U256::from(1100)), None);
+        assert_eq!(policy.bump(U256::from(2000)), None);
+    }
+
+    #[test]
+    fn function_margin_overrides_default() {
+        let policy = GasPolicy::default()
+            .with_margin(10)
+            .with_function_margin("transferFromRealis", 50);
+
+        assert_eq!(policy.gas_limit("transferFromRealis", U256::from(100_000)), U256::from(150_000));
+        assert_eq!(policy.gas_limit("transferNftFromRealis", U256::from(100_000)), U256::from(110_000));
+        assert_eq!(GasPolicy::default().gas_limit("any", U256::from(100_000)), U256::from(120_000));
+    }
+}
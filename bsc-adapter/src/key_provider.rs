@@ -0,0 +1,295 @@
+use async_trait::async_trait;
+use log::info;
+use primitives::{key_file, Error};
+use secp256k1::SecretKey;
+use serde_json::Value;
+use std::{fmt, fs, path::PathBuf};
+
+/// BSC master key, it is never printed
+pub struct MasterKey(SecretKey);
+
+impl MasterKey {
+    #[must_use]
+    pub fn secret(&self) -> &SecretKey {
+        &self.0
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        // Error of secp256k1 doesn't contain the key, but don't forward it anyway
+        SecretKey::from_slice(bytes)
+            .map(Self)
+            .map_err(|_| Error::Custom(String::from("Invalid BSC master key")))
+    }
+
+    fn from_hex(value: &str) -> Result<Self, Error> {
+        let value = value.trim();
+        let bytes = hex::decode(value.strip_prefix("0x").unwrap_or(value))
+            .map_err(|_| Error::Custom(String::from("BSC master key is not a hex string")))?;
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl fmt::Debug for MasterKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MasterKey(<redacted>)")
+    }
+}
+
+/// Source of BSC master key
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// # Errors
+    async fn load(&self) -> Result<MasterKey, Error>;
+}
+
+/// Encrypted JSON keystore as produced by geth or clef
+pub struct KeystoreFile {
+    path: PathBuf,
+    password: String,
+}
+
+impl KeystoreFile {
+    #[must_use]
+    pub fn new(path: PathBuf, password: String) -> Self {
+        Self { path, password }
+    }
+}
+
+#[async_trait]
+impl KeyProvider for KeystoreFile {
+    async fn load(&self) -> Result<MasterKey, Error> {
+        info!("[BSC Adapter] - load master key from keystore {:?}", self.path);
+        let bytes = eth_keystore::decrypt_key(&self.path, &self.password)
+            .map_err(|error| Error::Custom(format!("Cannot decrypt keystore {:?}: {}", self.path, error)))?;
+
+        MasterKey::from_bytes(&bytes)
+    }
+}
+
+/// File with hex encoded key, it must not be accessible by group or others
+pub struct RawKeyFile {
+    path: PathBuf,
+}
+
+impl RawKeyFile {
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl KeyProvider for RawKeyFile {
+    async fn load(&self) -> Result<MasterKey, Error> {
+        info!("[BSC Adapter] - load master key from file {:?}", self.path);
+        key_file::check_permissions(&self.path)?;
+        let content = fs::read_to_string(&self.path)
+            .map_err(|error| Error::FileNotFound(format!("{:?}: {}", self.path, error)))?;
+
+        MasterKey::from_hex(&content)
+    }
+}
+
+/// Vault compatible KV secret engine, both v1 and v2 responses are accepted
+pub struct VaultKv {
+    url: String,
+    token: String,
+    path: String,
+    field: String,
+}
+
+impl VaultKv {
+    /// * `url` - address of server, e.g. `http://127.0.0.1:8200`
+    /// * `path` - path of secret, e.g. `secret/data/bridge`
+    /// * `field` - name of field in secret which contains hex encoded key
+    #[must_use]
+    pub fn new(url: &str, token: String, path: &str, field: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            token,
+            path: path.trim_start_matches('/').to_string(),
+            field: field.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl KeyProvider for VaultKv {
+    async fn load(&self) -> Result<MasterKey, Error> {
+        let url = format!("{}/v1/{}", self.url, self.path);
+        info!("[BSC Adapter] - load master key from {}", url);
+
+        let response = reqwest::Client::new()
+            .get(&url)
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .map_err(|error| Error::Custom(format!("Vault request failed: {}", error)))?;
+        if !response.status().is_success() {
+            return Err(Error::Custom(format!("Vault responded with {}", response.status())));
+        }
+        let body = response
+            .json::<Value>()
+            .await
+            .map_err(|_| Error::Custom(String::from("Vault response is not a json")))?;
+
+        // KV v2 wraps secret into one more `data` object
+        body["data"]["data"][&self.field]
+            .as_str()
+            .or_else(|| body["data"][&self.field].as_str())
+            .ok_or_else(|| Error::Custom(format!("Missing field `{}` in secret {}", self.field, self.path)))
+            .and_then(MasterKey::from_hex)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+        task::JoinHandle,
+    };
+
+    const KEY: &str = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+    /// `KEY` encrypted with password `bridge keystore password`, scrypt is weakened to n = 1024
+    const KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "a45b2d217a49963ca745fd6abb13848d" },
+            "ciphertext": "175a76c408e4b0aade224dcfb879e3938799c8d6ff613df117b53d3606743e98",
+            "kdf": "scrypt",
+            "kdfparams": {
+                "dklen": 32,
+                "n": 1024,
+                "p": 1,
+                "r": 8,
+                "salt": "56176744b3aafa434ffde788d44a5b9375832e800ea8302bef3c99957c5c1d0d"
+            },
+            "mac": "89ea59d7ea107946dc45c4ea502de03f7677eeb34372676d02df4f6c220944d8"
+        },
+        "id": "8d9e2fd0-3d0b-4d6b-9a57-2b1f0d3c4e5f",
+        "version": 3
+    }"#;
+
+    fn secret() -> SecretKey {
+        SecretKey::from_slice(&hex::decode(KEY.trim_start_matches("0x")).unwrap()).unwrap()
+    }
+
+    fn temp_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("bsc-adapter-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Answers one request with given status and body, returns url of server and received request.
+    async fn serve_once(status: &'static str, body: &'static str) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 4096];
+            let read = stream.read(&mut request).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                Connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            String::from_utf8_lossy(&request[..read]).to_lowercase()
+        });
+
+        (url, handle)
+    }
+
+    #[test]
+    fn master_key_is_not_printed() {
+        let key = MasterKey::from_hex(KEY).unwrap();
+
+        assert_eq!(key.secret(), &secret());
+        assert!(!format!("{:?}", key).contains(&KEY[2..10]));
+    }
+
+    #[tokio::test]
+    async fn keystore_is_decrypted() {
+        let path = temp_file("keystore.json", KEYSTORE);
+
+        let key = KeystoreFile::new(path.clone(), String::from("bridge keystore password"))
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(key.secret(), &secret());
+
+        let wrong = KeystoreFile::new(path.clone(), String::from("wrong password")).load().await;
+        assert!(wrong.is_err());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn raw_key_file_must_be_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = temp_file("raw.key", &format!("{}\n", KEY));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(RawKeyFile::new(path.clone()).load().await.is_err());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+        let key = RawKeyFile::new(path.clone()).load().await.unwrap();
+        assert_eq!(key.secret(), &secret());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn vault_kv_v2_secret_is_loaded() {
+        let (url, request) = serve_once(
+            "200 OK",
+            r#"{"data":{"data":{"key":"0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"}}}"#,
+        )
+        .await;
+
+        let key = VaultKv::new(&format!("{}/", url), String::from("test-token"), "/secret/data/bridge", "key")
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(key.secret(), &secret());
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("get /v1/secret/data/bridge "), "{}", request);
+        assert!(request.contains("x-vault-token: test-token"), "{}", request);
+    }
+
+    #[tokio::test]
+    async fn vault_kv_v1_secret_is_loaded() {
+        let (url, _) = serve_once(
+            "200 OK",
+            r#"{"data":{"key":"4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318"}}"#,
+        )
+        .await;
+
+        let key = VaultKv::new(&url, String::from("test-token"), "secret/bridge", "key")
+            .load()
+            .await
+            .unwrap();
+        assert_eq!(key.secret(), &secret());
+    }
+
+    #[tokio::test]
+    async fn vault_kv_errors_are_reported() {
+        let (url, _) = serve_once("403 Forbidden", r#"{"errors":["permission denied"]}"#).await;
+        let denied = VaultKv::new(&url, String::from("bad-token"), "secret/bridge", "key").load().await;
+        assert!(denied.is_err());
+
+        let (url, _) = serve_once("200 OK", r#"{"data":{"other":"value"}}"#).await;
+        let missing = VaultKv::new(&url, String::from("test-token"), "secret/bridge", "key").load().await;
+        assert!(matches!(missing, Err(Error::Custom(message)) if message.contains("Missing field `key`")));
+    }
+}
@@ -1,113 +1,532 @@
 mod connection_builder;
+pub mod gas;
+pub mod key_provider;
+pub mod signer;
 
-use crate::connection_builder::ConnectionBuilder;
+use crate::{connection_builder::ConnectionBuilder, gas::GasPolicy, signer::Signer};
+use futures::{stream::FuturesUnordered, StreamExt};
 use tokio::{
     select,
     sync::mpsc::{Receiver, Sender},
+    time::{interval, sleep, Instant},
 };
 
-use log::{error, info};
+use log::{error, info, warn};
 use primitives::Error;
 use rust_lib::healthchecker::HealthChecker;
-use secp256k1::SecretKey;
 
-use db::Database;
-use std::{str::FromStr, sync::Arc};
+use db::{Database, NonceManager};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
 use primitives::{
-    db::Status,
+    asset::{Asset, AssetKind, AssetRegistry},
+    attestation::{self, Quorum},
+    db::{Recovery, Settlement, Status, Submission},
     events::{bsc::BscEventType, realis::RealisEventType, traits::Event},
+    retry::RetryPolicy,
 };
 use web3::{
-    contract::{tokens::Tokenize, Contract},
+    contract::{Contract, Options},
+    ethabi::Token,
     transports::WebSocket,
-    types::{TransactionReceipt, U64},
+    types::{
+        Address, BlockId, BlockNumber, Bytes, CallRequest, TransactionId, TransactionParameters,
+        TransactionReceipt, H256, U256, U64,
+    },
     Web3,
 };
 
+/// Name of BSC in database tables
+const CHAIN: &str = "bsc";
+/// How often database is checked for transfers missed by channel
+const OUTBOX_INTERVAL: Duration = Duration::from_secs(10);
+/// How often receipt of sent transaction is requested
+const RECEIPT_INTERVAL: Duration = Duration::from_secs(3);
+/// How many times receipt is requested before transaction result is unknown
+const RECEIPT_ATTEMPTS: u32 = 60;
+/// How many transactions are sent at once by default
+const DEFAULT_MAX_IN_FLIGHT: usize = 16;
+/// Gas of plain transfer, used to fill nonce gaps
+const TRANSFER_GAS: u64 = 21000;
+
 #[allow(dead_code)]
 pub struct BinanceHandler {
-    rx: Receiver<RealisEventType>,
+    rx: Option<Receiver<RealisEventType>>,
     tx: Sender<BscEventType>,
     connection_builder: ConnectionBuilder,
-    token_contract_address: String,
-    nft_contract_address: String,
     health_checker: HealthChecker,
-    master_key: SecretKey,
+    signer: Box<dyn Signer>,
+    nonces: NonceManager,
+    gas: GasPolicy,
+    /// Repeats sending after transient errors before transfer is rolled back
+    retry: RetryPolicy,
     db: Arc<Database>,
+    /// How many transactions can wait for receipt at once
+    max_in_flight: usize,
+    /// Attestations needed before release in multi-operator mode
+    quorum: Option<Quorum>,
+    /// Contracts and decimals of bridged assets
+    assets: Arc<AssetRegistry>,
 }
 
 impl BinanceHandler {
     #[must_use]
     #[allow(clippy::too_many_arguments)]
-    /// # Panics
     pub fn new(
         rx: Receiver<RealisEventType>,
         tx: Sender<BscEventType>,
         health_checker: HealthChecker,
         url: &str,
-        token_contract_address: String,
-        nft_contract_address: String,
-        master_key: &str,
+        signer: Box<dyn Signer>,
         db: Arc<Database>,
     ) -> Self {
         let connection_builder = ConnectionBuilder::new(url);
-        let master_key = SecretKey::from_str(master_key).unwrap();
+        let nonces = NonceManager::new(Arc::clone(&db), CHAIN, format!("{:?}", signer.address()));
 
         Self {
-            rx,
+            rx: Some(rx),
             tx,
             connection_builder,
-            token_contract_address,
-            nft_contract_address,
             health_checker,
-            master_key,
+            signer,
+            nonces,
+            gas: GasPolicy::default(),
+            retry: RetryPolicy::default(),
             db,
+            max_in_flight: DEFAULT_MAX_IN_FLIGHT,
+            quorum: None,
+            assets: Arc::new(AssetRegistry::default()),
         }
     }
 
+    #[must_use]
+    pub fn with_max_in_flight(mut self, max_in_flight: usize) -> Self {
+        self.max_in_flight = max_in_flight.max(1);
+        self
+    }
+
+    #[must_use]
+    pub fn with_gas_policy(mut self, gas: GasPolicy) -> Self {
+        self.gas = gas;
+        self
+    }
+
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[must_use]
+    pub fn with_quorum(mut self, quorum: Quorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    #[must_use]
+    pub fn with_assets(mut self, assets: Arc<AssetRegistry>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Decimals of BEP-20 token.
+    /// # Errors
+    pub async fn token_decimals(&self, token: Address) -> Result<u8, Error> {
+        let connection = self.connect().await?;
+        let contract = ConnectionBuilder::contract(connection, token, AssetKind::Token)?;
+
+        let decimals: U256 = contract
+            .query("decimals", (), None, Options::default(), None)
+            .await
+            .map_err(|error| Error::Custom(format!("Cannot read token decimals: {:?}", error)))?;
+
+        u8::try_from(decimals).map_err(|_| Error::Custom(format!("Invalid token decimals: {}", decimals)))
+    }
+
     /// # Panics
     /// # Errors
     pub async fn handle(mut self) {
+        let mut rx = match self.rx.take() {
+            Some(rx) => rx,
+            None => return,
+        };
+
+        if let Err(error) = self.recover().await {
+            error!("[BSC Adapter] - recover interrupted transfers: {:?}", error);
+            self.health_checker.make_sick();
+            return;
+        }
+
+        // Requests are handled concurrently, each with own nonce
+        let mut in_flight = FuturesUnordered::new();
+        let mut outbox = interval(OUTBOX_INTERVAL);
         loop {
             let health_checker = self.health_checker.clone();
             select! {
                 () = health_checker.is_alive() => break,
-                option = self.rx.recv() => {
+                Some(()) = in_flight.next(), if !in_flight.is_empty() => {}
+                option = rx.recv(), if in_flight.len() < self.max_in_flight => {
                     if let Some(request) = option {
-                        match self.execute(&request).await {
-                            Ok(_) => {
-                                info!("Success send transaction to Realis!");
-                            }
-                            Err(error) => {
-                                let rollback_request = match request {
-                                    RealisEventType::TransferNftToBsc(request, ..) => {
-                                        Some(BscEventType::TransferNftToBscFail(request))
-                                    }
-                                    RealisEventType::TransferTokenToBsc(request, ..) => {
-                                        Some(BscEventType::TransferTokenToBscFail(request))
-                                    }
-                                    // If rollback request fail
-                                    _ => None
-                                };
-                                if let Some(rollback_request) = rollback_request {
-                                    error!("Extrinsic execute: {:?}", error);
-                                    if let Err(error) = self.tx.send(rollback_request).await {
-                                        error!("[BSC Adapter] - send error: {:?}", error);
-                                        self.health_checker.make_sick();
-                                    }
-                                } else {
-                                    error!("Rollback fail: {:?}", error);
-                                    self.health_checker.make_sick();
-                                }
+                        in_flight.push(self.handle_request(request));
+                    }
+                }
+                _ = outbox.tick() => {
+                    if let Err(error) = self.fill_gaps().await {
+                        error!("[BSC Adapter] - fill nonce gaps: {:?}", error);
+                    }
+                    match self.get_outbox().await {
+                        Ok(requests) => {
+                            // Rest of requests stay in database until next check
+                            let free = self.max_in_flight.saturating_sub(in_flight.len());
+                            for request in self.ready(requests, free).await {
+                                in_flight.push(self.handle_request(request));
                             }
                         }
+                        Err(error) => error!("[BSC Adapter] - load outbox: {:?}", error),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Transfers interrupted by restart are checked on BSC side
+    /// and settled, returned to queue or marked as unresolved.
+    async fn recover(&self) -> Result<(), Error> {
+        let connection = self.connect().await?;
+
+        for (id, submission) in self.db.get_submissions_realis(Status::InProgress).await? {
+            match self.check_submission(&connection, &id, submission).await? {
+                Recovery::Landed(settlement) => {
+                    let status = if settlement.success { Status::Success } else { Status::Error };
+                    warn!("[BSC Adapter] - recover transfer {} with status {:?}", id, status);
+                    self.db.settle_realis(&id, status, &settlement).await?;
+                }
+                recovery => {
+                    let status = if let Recovery::Retry = recovery { Status::Got } else { Status::Unresolved };
+                    warn!("[BSC Adapter] - recover transfer {} with status {:?}", id, status);
+                    self.db.update_status_realis(&id, status).await?;
+                }
+            }
+        }
+
+        for (id, submission) in self.db.get_submissions_bsc(Status::RollbackInProgress).await? {
+            match self.check_submission(&connection, &id, submission).await? {
+                Recovery::Landed(settlement) => {
+                    let status = if settlement.success {
+                        Status::RollbackSuccess
+                    } else {
+                        Status::RollbackError
+                    };
+                    warn!("[BSC Adapter] - recover rollback {} with status {:?}", id, status);
+                    self.db.settle_bsc(&id, status, &settlement).await?;
+                    if !settlement.success {
+                        let error = Error::Custom(format!("Rollback transaction {} failed", settlement.tx_hash));
+                        self.dead_letter(&id, &error).await;
+                    }
+                }
+                recovery => {
+                    let status = if let Recovery::Retry = recovery { Status::Error } else { Status::Unresolved };
+                    warn!("[BSC Adapter] - recover rollback {} with status {:?}", id, status);
+                    self.db.update_status_bsc(&id, status).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_submission(
+        &self,
+        connection: &Web3<WebSocket>,
+        id: &str,
+        submission: Option<Submission>,
+    ) -> Result<Recovery, Error> {
+        // Transaction is saved before sending, so without it nothing was sent
+        let submission = match submission {
+            Some(submission) => submission,
+            None => return Ok(Recovery::Retry),
+        };
+        // Replaced transactions could be mined instead of the last one
+        let mut hashes = self
+            .db
+            .get_attempts(id, CHAIN)
+            .await?
+            .iter()
+            .map(|hash| H256::from_str(hash).map_err(|error| Error::Custom(format!("{:?}", error))))
+            .collect::<Result<Vec<_>, _>>()?;
+        hashes.push(Self::tx_hash(&submission)?);
+
+        if let Some(receipt) = Self::find_receipt(connection, &hashes).await? {
+            return Ok(Recovery::Landed(Self::settlement(connection, &receipt).await));
+        }
+
+        // Same signed transaction can't be executed twice, so it is safe to send it again
+        match connection.eth().send_raw_transaction(Self::decode_raw(&submission.raw)?).await {
+            Ok(_) => match Self::wait_receipt(connection, &hashes).await {
+                Ok(receipt) => Ok(Recovery::Landed(Self::settlement(connection, &receipt).await)),
+                Err(_) => Ok(Recovery::Unknown),
+            },
+            Err(error) => {
+                warn!("[BSC Adapter] - resend {}: {:?}", submission.tx_hash, error);
+                let nonce = self.transaction_count(connection, BlockNumber::Latest).await?;
+                // Transaction could be mined after first check
+                if let Some(receipt) = Self::find_receipt(connection, &hashes).await? {
+                    Ok(Recovery::Landed(Self::settlement(connection, &receipt).await))
+                } else if nonce > submission.nonce {
+                    // Nonce is used by other transaction, so this one will never be mined
+                    Ok(Recovery::Retry)
+                } else {
+                    Ok(Recovery::Unknown)
+                }
+            }
+        }
+    }
+
+    /// Nonce of bridge account at given block.
+    async fn transaction_count(&self, connection: &Web3<WebSocket>, block: BlockNumber) -> Result<u64, Error> {
+        let count = connection
+            .eth()
+            .transaction_count(self.signer.address(), Some(block))
+            .await
+            .map_err(Error::Web3)?;
+
+        u64::try_from(count).map_err(|_| Error::Custom(format!("Nonce {} overflows u64", count)))
+    }
+
+    /// Nonce which wasn't mined blocks all later transactions of bridge account.
+    /// Dropped transactions are sent again, unused nonces are filled with empty transfer.
+    /// Nonce of failed transfer is filled only if none of its saved transactions is known to node.
+    async fn fill_gaps(&self) -> Result<(), Error> {
+        let connection = self.connect().await?;
+
+        let gaps = self
+            .nonces
+            .gaps(self.transaction_count(&connection, BlockNumber::Latest).await?)
+            .await?;
+        if gaps.is_empty() {
+            return Ok(());
+        }
+
+        let mut submissions = self.db.get_submissions_realis(Status::InProgress).await?;
+        submissions.extend(self.db.get_submissions_bsc(Status::RollbackInProgress).await?);
+        let submissions = submissions
+            .into_iter()
+            .filter_map(|(_, submission)| submission)
+            .map(|submission| (submission.nonce, submission))
+            .collect::<HashMap<_, _>>();
+
+        for nonce in gaps {
+            match submissions.get(&nonce) {
+                Some(submission) => {
+                    let hash = H256::from_str(&submission.tx_hash)
+                        .map_err(|error| Error::Custom(format!("{:?}", error)))?;
+                    if connection
+                        .eth()
+                        .transaction(TransactionId::Hash(hash))
+                        .await
+                        .map_err(Error::Web3)?
+                        .is_none()
+                    {
+                        warn!("[BSC Adapter] - resend dropped transaction {:?} with nonce {}", hash, nonce);
+                        if let Err(error) = connection
+                            .eth()
+                            .send_raw_transaction(Self::decode_raw(&submission.raw)?)
+                            .await
+                        {
+                            warn!("[BSC Adapter] - resend {:?}: {:?}", hash, error);
+                        }
+                    }
+                }
+                None => match self.known_attempt(&connection, nonce).await? {
+                    // Transfer failed after ambiguous sending, but its transaction still can be mined
+                    Some((id, hash)) => {
+                        warn!("[BSC Adapter] - nonce {} is used by transaction {:?} of {}", nonce, hash, id);
+                        if self.db.claim_realis(&id, Status::Error, Status::Unresolved).await? {
+                            error!("[BSC Adapter] - transfer {} isn't rolled back, it is left for operator", id);
+                        }
+                    }
+                    // Filler also guarantees that lost transaction is never mined later
+                    None => {
+                        warn!("[BSC Adapter] - fill unused nonce {}", nonce);
+                        self.fill_nonce(&connection, nonce).await?;
+                    }
+                },
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saved transaction signed with `nonce` which is known to node, with id of its transfer.
+    async fn known_attempt(
+        &self,
+        connection: &Web3<WebSocket>,
+        nonce: u64,
+    ) -> Result<Option<(String, H256)>, Error> {
+        for (id, hash) in self.db.get_attempts_by_nonce(CHAIN, nonce).await? {
+            let hash = H256::from_str(&hash).map_err(|error| Error::Custom(format!("{:?}", error)))?;
+            if connection
+                .eth()
+                .transaction(TransactionId::Hash(hash))
+                .await
+                .map_err(Error::Web3)?
+                .is_some()
+            {
+                return Ok(Some((id, hash)));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Send zero value transfer to itself, so nonce is used.
+    async fn fill_nonce(&self, connection: &Web3<WebSocket>, nonce: u64) -> Result<(), Error> {
+        let gas_price = self.gas.gas_price(connection).await?;
+        let chain_id = connection.eth().chain_id().await.map_err(Error::Web3)?;
+
+        let signed = self
+            .signer
+            .sign(
+                connection,
+                TransactionParameters {
+                    nonce: Some(U256::from(nonce)),
+                    to: Some(self.signer.address()),
+                    gas: U256::from(TRANSFER_GAS),
+                    gas_price: Some(gas_price),
+                    value: U256::zero(),
+                    chain_id: Some(chain_id.as_u64()),
+                    ..TransactionParameters::default()
+                },
+            )
+            .await?;
+
+        connection
+            .eth()
+            .send_raw_transaction(signed.raw)
+            .await
+            .map_err(Error::Web3)
+            .map(|_| ())
+    }
+
+    /// Load all work stored in database: transfers from Realis which
+    /// wasn't sent yet and transfers from BSC which should be rolled back.
+    async fn get_outbox(&self) -> Result<Vec<RealisEventType>, Error> {
+        let mut requests = self.db.get_transfers_realis(Status::Got).await?;
+        requests.extend(
+            self.db
+                .get_transfers_bsc(Status::Error)
+                .await?
+                .into_iter()
+                .filter_map(|event| match event {
+                    BscEventType::TransferTokenToRealis(event) => {
+                        Some(RealisEventType::TransferTokenToRealisFail(event))
+                    }
+                    BscEventType::TransferNftToRealis(event) => {
+                        Some(RealisEventType::TransferNftToRealisFail(event))
                     }
+                    _ => None,
+                }),
+        );
+
+        Ok(requests)
+    }
+
+    /// At most `free` requests which can be sent now, in outbox order. Transfers led by other operators
+    /// or waiting for attestations would only return from `handle_request`, so they don't take free slots.
+    async fn ready(&self, requests: Vec<RealisEventType>, free: usize) -> Vec<RealisEventType> {
+        let mut ready = Vec::new();
+        for request in requests {
+            if ready.len() == free {
+                break;
+            }
+            match self.is_ready(&request).await {
+                Ok(true) => ready.push(request),
+                Ok(false) => {}
+                Err(error) => error!("[BSC Adapter] - check outbox request: {:?}", error),
+            }
+        }
+
+        ready
+    }
+
+    async fn is_ready(&self, request: &RealisEventType) -> Result<bool, Error> {
+        let (id, call) = match request {
+            RealisEventType::TransferNftToBsc(event) => {
+                (event.get_hash(), self.binance_call(event, AssetKind::Nft))
+            }
+            RealisEventType::TransferTokenToBsc(event) => {
+                (event.get_hash(), self.binance_call(event, AssetKind::Token))
+            }
+            // Rollbacks aren't attested
+            _ => return Ok(true),
+        };
+        if !self.is_submitter(&id).await? {
+            return Ok(false);
+        }
+
+        match call {
+            Ok(call) => self.is_attested(&id, &call).await,
+            // Transfer which can't be converted is rejected without attestations
+            Err(_) => Ok(true),
+        }
+    }
+
+    fn binance_call(&self, event: &impl Event, kind: AssetKind) -> Result<(String, Vec<Token>), Error> {
+        let asset = self.assets.realis_asset(kind)?;
+        event.get_binance_call(&self.assets.decimals(asset)?)
+    }
+
+    async fn handle_request(&self, request: RealisEventType) {
+        match self.execute(&request).await {
+            Ok(_) => {
+                info!("Success send transaction to Realis!");
+            }
+            Err(Error::Unconfirmed(error)) => {
+                error!("[BSC Adapter] - transaction result is unknown, will be checked on restart: {}", error);
+            }
+            // Nothing was claimed yet, request stays in outbox
+            Err(error) if error.is_transient() => {
+                warn!("[BSC Adapter] - request will be repeated: {:?}", error);
+            }
+            Err(error) => {
+                let rollback_id = match &request {
+                    RealisEventType::TransferNftToRealisFail(event) => event.get_hash(),
+                    RealisEventType::TransferTokenToRealisFail(event) => event.get_hash(),
+                    _ => String::new(),
+                };
+                let rollback_request = match request {
+                    RealisEventType::TransferNftToBsc(request, ..) => {
+                        Some(BscEventType::TransferNftToBscFail(request))
+                    }
+                    RealisEventType::TransferTokenToBsc(request, ..) => {
+                        Some(BscEventType::TransferTokenToBscFail(request))
+                    }
+                    // If rollback request fail
+                    _ => None,
+                };
+                if let Some(rollback_request) = rollback_request {
+                    error!("Extrinsic execute: {:?}", error);
+                    if let Err(error) = self.tx.send(rollback_request).await {
+                        error!("[BSC Adapter] - send error: {:?}", error);
+                        self.health_checker.make_sick();
+                    }
+                } else {
+                    self.dead_letter(&rollback_id, &error).await;
                 }
             }
         }
     }
 
+    /// Failed rollback is kept for operator, bridge continues with other transfers.
+    /// Rollback which failed before it was claimed is halted too, so outbox doesn't repeat it.
+    async fn dead_letter(&self, id: &str, error: &Error) {
+        error!("[BSC Adapter] - rollback {} failed, moved to dead letters: {}", id, error);
+        if let Err(error) = self.db.add_dead_letter_bsc(id, &error.to_string()).await {
+            error!("[BSC Adapter] - logging dead letter to db: {:?}", error);
+            self.health_checker.make_sick();
+        }
+    }
+
     async fn execute(&self, request: &RealisEventType) -> Result<(), Error> {
         let connection = self.connect().await?;
 
@@ -115,32 +534,20 @@ impl BinanceHandler {
 
         match request {
             RealisEventType::TransferNftToBsc(event) => {
-                self.process(
-                    event,
-                    ConnectionBuilder::nft(connection, &self.nft_contract_address).await?,
-                )
-                .await
+                let asset = self.assets.realis_asset(AssetKind::Nft)?;
+                self.process(event, asset, &connection).await
             }
             RealisEventType::TransferTokenToBsc(event) => {
-                self.process(
-                    event,
-                    ConnectionBuilder::token(connection, &self.token_contract_address).await?,
-                )
-                .await
+                let asset = self.assets.realis_asset(AssetKind::Token)?;
+                self.process(event, asset, &connection).await
             }
             RealisEventType::TransferNftToRealisFail(event) => {
-                self.rollback(
-                    event,
-                    ConnectionBuilder::nft(connection, &self.nft_contract_address).await?,
-                )
-                .await
+                let asset = self.assets.bsc_asset(AssetKind::Nft, event.contract)?;
+                self.rollback(event, asset, &connection).await
             }
             RealisEventType::TransferTokenToRealisFail(event) => {
-                self.rollback(
-                    event,
-                    ConnectionBuilder::token(connection, &self.token_contract_address).await?,
-                )
-                .await
+                let asset = self.assets.bsc_asset(AssetKind::Token, event.contract)?;
+                self.rollback(event, asset, &connection).await
             }
         }
     }
@@ -152,88 +559,456 @@ impl BinanceHandler {
             }
         }
 
-        Err(Error::Custom(String::from("Can't connect to binance!")))
+        Err(Error::Connection(String::from("binance")))
     }
 
-    async fn process(&self, event: &impl Event, contract: Contract<WebSocket>) -> Result<(), Error> {
-        self.db
-            .update_status_realis(&event.get_hash(), Status::InProgress)
-            .await?;
+    /// Transfer is released only when enough operators attested it.
+    /// Until then it stays in outbox and is checked again.
+    async fn is_attested(&self, id: &str, call: &(String, Vec<Token>)) -> Result<bool, Error> {
+        let quorum = match &self.quorum {
+            Some(quorum) => quorum,
+            None => return Ok(true),
+        };
 
-        let (func, params) = event.get_binance_call();
+        let digest = attestation::binance_call_digest(id, call);
+        let attestations = self.db.get_attestations(id).await?;
+        if quorum.is_reached(digest, &attestations) {
+            return Ok(true);
+        }
 
-        let result = self
-            .send_to_blockchain(
-                contract,
-                &func,
-                (params[0].clone(), params[1].clone(), params[2].clone()),
-            )
-            .await;
+        let count = quorum.count(digest, &attestations);
+        info!("[BSC Adapter] - transfer {} has {}/{} attestations", id, count, quorum.threshold());
+        Ok(false)
+    }
 
-        if let Err(error) = self
-            .db
-            .update_status_realis(
-                &event.get_hash(),
-                result.as_ref().map(|_| Status::Success).unwrap_or(Status::Error),
-            )
-            .await
-        {
+    /// In multi-operator mode transfer is submitted by its leader, other operators take it over
+    /// when it waits too long. Claim in shared database keeps transfer sent once.
+    async fn is_submitter(&self, id: &str) -> Result<bool, Error> {
+        let quorum = match &self.quorum {
+            Some(quorum) if !quorum.is_leader(id) => quorum,
+            _ => return Ok(true),
+        };
+
+        let waited = self.db.get_attestation_age(id).await?.unwrap_or_default();
+        Ok(quorum.may_submit(id, waited))
+    }
+
+    async fn process(&self, event: &impl Event, asset: &Asset, connection: &Web3<WebSocket>) -> Result<(), Error> {
+        let contract = ConnectionBuilder::contract(connection.clone(), asset.contract, asset.kind)?;
+        let decimals = self.assets.decimals(asset)?;
+
+        let id = event.get_hash();
+        if !self.is_submitter(&id).await? {
+            return Ok(());
+        }
+        // Transfer which can't be converted is rejected without attestations
+        let call = event.get_binance_call(&decimals);
+        if let Ok(call) = &call {
+            if !self.is_attested(&id, call).await? {
+                return Ok(());
+            }
+        }
+        if !self.db.claim_realis(&id, Status::Got, Status::InProgress).await? {
+            warn!("[BSC Adapter] - skip transfer in progress or settled: {}", id);
+            return Ok(());
+        }
+
+        let (func, params) = match call {
+            Ok(call) => call,
+            Err(error) => {
+                if let Err(error) = self.db.update_status_realis(&id, Status::Error).await {
+                    error!("[BSC Adapter] - logging status to db: {:?}", error);
+                    self.health_checker.make_sick();
+                }
+                return Err(error);
+            }
+        };
+
+        let result = self.submit(&id, false, connection, &contract, &func, &params).await;
+
+        let logged = match &result {
+            Ok(settlement) => {
+                let status = if settlement.success { Status::Success } else { Status::Error };
+                self.db.settle_realis(&event.get_hash(), status, settlement).await
+            }
+            // Transfer stays in progress until it is checked on restart
+            Err(Error::Unconfirmed(_)) => Ok(()),
+            Err(_) => self.db.update_status_realis(&event.get_hash(), Status::Error).await,
+        };
+        if let Err(error) = logged {
             error!("[BSC Adapter] - logging status to db: {:?}", error);
             self.health_checker.make_sick();
         }
 
-        result
+        result.and_then(|settlement| Self::check_extrinsic(&settlement))
     }
 
-    async fn rollback(&self, event: &impl Event, contract: Contract<WebSocket>) -> Result<(), Error> {
-        let (func, params) = event.get_binance_call();
+    async fn rollback(
+        &self,
+        event: &impl Event,
+        asset: &Asset,
+        connection: &Web3<WebSocket>,
+    ) -> Result<(), Error> {
+        let contract = ConnectionBuilder::contract(connection.clone(), asset.contract, asset.kind)?;
+        let decimals = self.assets.decimals(asset)?;
 
-        let result = if params.len() == 3 {
-            self.send_to_blockchain(
-                contract,
-                &func,
-                (params[0].clone(), params[1].clone(), params[2].clone()),
-            )
-            .await
-        } else {
-            self.send_to_blockchain(contract, &func, (params[0].clone(), params[1].clone()))
-                .await
-        };
+        let id = event.get_hash();
+        let (func, params) = event.get_binance_call(&decimals)?;
 
-        if let Err(error) = self
-            .db
-            .update_status_realis(
-                &event.get_hash(),
-                result
-                    .as_ref()
-                    .map(|_| Status::RollbackSuccess)
-                    .unwrap_or(Status::RollbackError),
-            )
-            .await
-        {
+        if !self.db.claim_bsc(&id, Status::Error, Status::RollbackInProgress).await? {
+            warn!("[BSC Adapter] - skip rollback in progress, settled or halted: {}", id);
+            return Ok(());
+        }
+
+        let result = self.submit(&id, true, connection, &contract, &func, &params).await;
+
+        let logged = match &result {
+            Ok(settlement) => {
+                let status = if settlement.success {
+                    Status::RollbackSuccess
+                } else {
+                    Status::RollbackError
+                };
+                self.db.settle_bsc(&event.get_hash(), status, settlement).await
+            }
+            // Rollback stays in progress until it is checked on restart
+            Err(Error::Unconfirmed(_)) => Ok(()),
+            Err(_) => self.db.update_status_bsc(&event.get_hash(), Status::RollbackError).await,
+        };
+        if let Err(error) = logged {
             error!("[BSC Adapter] - logging status to db: {:?}", error);
             self.health_checker.make_sick();
         }
 
+        result.and_then(|settlement| Self::check_extrinsic(&settlement))
+    }
+
+    /// Sign, save and send transaction. Transaction which isn't mined in time
+    /// is replaced by the same one with higher gas price and the same nonce.
+    /// Transaction is signed again only while nothing was sent, after that
+    /// the same signed transaction is broadcast again, so transfer can't be executed twice.
+    async fn submit(
+        &self,
+        id: &str,
+        rollback: bool,
+        connection: &Web3<WebSocket>,
+        contract: &Contract<WebSocket>,
+        func: &str,
+        params: &[Token],
+    ) -> Result<Settlement, Error> {
+        let (submission, parameters) = self
+            .retry
+            .run("[BSC Adapter] - sign transaction", || {
+                self.prepare(id, rollback, connection, contract, func, params)
+            })
+            .await?;
+        let nonce = submission.nonce;
+
+        let result = self
+            .retry
+            .run("[BSC Adapter] - send transaction", || {
+                self.send_to_blockchain(id, rollback, connection, &submission, parameters.clone())
+            })
+            .await
+            .map_err(|error| match error {
+                // Transaction could reach node before error, so it is checked on restart instead of rollback
+                Error::Exhausted(error) => Error::Unconfirmed(format!("{:?}", error)),
+                error => error,
+            });
+        // Nonce of failed transaction is filled on next outbox check
+        self.nonces.complete(nonce).await;
+        if let Ok(settlement) = &result {
+            if !settlement.success {
+                let error = Error::Custom(String::from("Transaction reverted"));
+                self.attempt_failed(&settlement.tx_hash, &error).await;
+            }
+        }
+
         result
     }
 
-    async fn send_to_blockchain(
+    /// Sign transaction and save it before sending.
+    async fn prepare(
         &self,
-        contract: Contract<WebSocket>,
+        id: &str,
+        rollback: bool,
+        connection: &Web3<WebSocket>,
+        contract: &Contract<WebSocket>,
         func: &str,
-        params: impl Tokenize,
+        params: &[Token],
+    ) -> Result<(Submission, TransactionParameters), Error> {
+        let (submission, parameters) = self.sign(connection, contract, func, params).await?;
+
+        if let Err(error) = self.save_submission(id, rollback, &submission, &parameters).await {
+            // Nothing was sent, nonce is filled on next outbox check
+            self.nonces.complete(submission.nonce).await;
+            return Err(error);
+        }
+
+        Ok((submission, parameters))
+    }
+
+    /// Every signed transaction is remembered, last one is kept with transfer.
+    async fn save_submission(
+        &self,
+        id: &str,
+        rollback: bool,
+        submission: &Submission,
+        parameters: &TransactionParameters,
     ) -> Result<(), Error> {
-        let receipt = contract
-            .signed_call_with_confirmations(func, params, web3::contract::Options::default(), 1, &self.master_key)
+        let gas_price = parameters.gas_price.unwrap_or_default().to_string();
+        self.db.add_attempt(id, CHAIN, submission, Some(&gas_price)).await?;
+
+        if rollback {
+            self.db.set_submission_bsc(id, submission).await
+        } else {
+            self.db.set_submission_realis(id, submission).await
+        }
+    }
+
+    /// Build contract call with gas from policy and reserve nonce for it.
+    async fn sign(
+        &self,
+        connection: &Web3<WebSocket>,
+        contract: &Contract<WebSocket>,
+        func: &str,
+        params: &[Token],
+    ) -> Result<(Submission, TransactionParameters), Error> {
+        let data = contract
+            .abi()
+            .function(func)
+            .and_then(|function| function.encode_input(params))
+            .map_err(|error| Error::Custom(format!("{:?}", error)))?;
+
+        let estimate = connection
+            .eth()
+            .estimate_gas(
+                CallRequest {
+                    from: Some(self.signer.address()),
+                    to: Some(contract.address()),
+                    data: Some(Bytes(data.clone())),
+                    ..CallRequest::default()
+                },
+                None,
+            )
             .await
             .map_err(Error::Web3)?;
+        let gas_price = self.gas.gas_price(connection).await?;
+        let chain_id = connection.eth().chain_id().await.map_err(Error::Web3)?;
+
+        // Nonce is reserved last, so it is given back if signing fails
+        let nonce = self
+            .nonces
+            .reserve(self.transaction_count(connection, BlockNumber::Pending).await?)
+            .await?;
+        let parameters = TransactionParameters {
+            nonce: Some(U256::from(nonce)),
+            to: Some(contract.address()),
+            gas: self.gas.gas_limit(func, estimate),
+            gas_price: Some(gas_price),
+            data: Bytes(data),
+            chain_id: Some(chain_id.as_u64()),
+            ..TransactionParameters::default()
+        };
+
+        match self.sign_parameters(connection, parameters.clone()).await {
+            Ok(submission) => Ok((submission, parameters)),
+            Err(error) => {
+                self.nonces.release(nonce).await?;
+                Err(error)
+            }
+        }
+    }
+
+    /// Sign fully filled transaction, nothing is sent to blockchain yet.
+    /// Signer doesn't need access to node.
+    async fn sign_parameters(
+        &self,
+        connection: &Web3<WebSocket>,
+        parameters: TransactionParameters,
+    ) -> Result<Submission, Error> {
+        let nonce = parameters.nonce.unwrap_or_default();
+        let block = connection.eth().block_number().await.map_err(Error::Web3)?;
+        let signed = self.signer.sign(connection, parameters).await?;
+
+        Ok(Submission {
+            tx_hash: format!("{:?}", signed.hash),
+            raw: format!("0x{}", hex::encode(signed.raw.0)),
+            nonce: nonce.as_u64(),
+            block: block.as_u64(),
+            batch_index: None,
+        })
+    }
+
+    async fn send_to_blockchain(
+        &self,
+        id: &str,
+        rollback: bool,
+        connection: &Web3<WebSocket>,
+        submission: &Submission,
+        mut parameters: TransactionParameters,
+    ) -> Result<Settlement, Error> {
+        let hash = Self::tx_hash(submission)?;
+
+        if let Err(error) = connection
+            .eth()
+            .send_raw_transaction(Self::decode_raw(&submission.raw)?)
+            .await
+        {
+            let error = Error::Web3(error);
+            // Transaction sent by previous attempt is already in pool or mined, receipt is awaited
+            if error.is_already_sent() {
+                warn!("[BSC Adapter] - {:?} is already sent: {:?}", hash, error);
+            } else {
+                // Node could accept transaction even if response was lost
+                match connection.eth().transaction(TransactionId::Hash(hash)).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        self.attempt_failed(&submission.tx_hash, &error).await;
+                        return Err(error);
+                    }
+                    Err(_) => return Err(Error::Unconfirmed(format!("{:?}", error))),
+                }
+            }
+        }
+
+        // Any of sent transactions can be mined, all of them are checked
+        let mut hashes = vec![hash];
+        let mut sent = Instant::now();
+        for _ in 0..RECEIPT_ATTEMPTS {
+            if let Some(receipt) = Self::find_receipt(connection, &hashes).await? {
+                return Ok(Self::settlement(connection, &receipt).await);
+            }
+
+            if sent.elapsed() >= self.gas.replace_after() {
+                sent = Instant::now();
+                if let Some(gas_price) = self.gas.bump(parameters.gas_price.unwrap_or_default()) {
+                    let replacement = TransactionParameters {
+                        gas_price: Some(gas_price),
+                        ..parameters.clone()
+                    };
+                    match self.replace(id, rollback, connection, replacement.clone()).await {
+                        Ok(hash) => {
+                            warn!("[BSC Adapter] - replace {:?} with gas price {}", hashes.last(), gas_price);
+                            hashes.push(hash);
+                            parameters = replacement;
+                        }
+                        // Original transaction could be mined already
+                        Err(error) => warn!("[BSC Adapter] - replace {:?}: {:?}", hashes.last(), error),
+                    }
+                }
+            }
+
+            sleep(RECEIPT_INTERVAL).await;
+        }
 
-        Self::check_extrinsic(&receipt)
+        Err(Error::Unconfirmed(format!("No receipt for transactions {:?}", hashes)))
+    }
+
+    /// Send transaction with the same nonce and higher gas price.
+    async fn replace(
+        &self,
+        id: &str,
+        rollback: bool,
+        connection: &Web3<WebSocket>,
+        parameters: TransactionParameters,
+    ) -> Result<H256, Error> {
+        let submission = self.sign_parameters(connection, parameters.clone()).await?;
+        self.save_submission(id, rollback, &submission, &parameters).await?;
+
+        match connection
+            .eth()
+            .send_raw_transaction(Self::decode_raw(&submission.raw)?)
+            .await
+        {
+            Ok(hash) => Ok(hash),
+            Err(error) => {
+                let error = Error::Web3(error);
+                self.attempt_failed(&submission.tx_hash, &error).await;
+                Err(error)
+            }
+        }
+    }
+
+    /// Reason of failed transaction is kept with attempts for operator.
+    async fn attempt_failed(&self, tx_hash: &str, error: &Error) {
+        if let Err(error) = self.db.set_attempt_error(tx_hash, &error.to_string()).await {
+            error!("[BSC Adapter] - logging attempt error to db: {:?}", error);
+            self.health_checker.make_sick();
+        }
+    }
+
+    async fn wait_receipt(connection: &Web3<WebSocket>, hashes: &[H256]) -> Result<TransactionReceipt, Error> {
+        for _ in 0..RECEIPT_ATTEMPTS {
+            if let Some(receipt) = Self::find_receipt(connection, hashes).await? {
+                return Ok(receipt);
+            }
+            sleep(RECEIPT_INTERVAL).await;
+        }
+
+        Err(Error::Unconfirmed(format!("No receipt for transactions {:?}", hashes)))
+    }
+
+    async fn find_receipt(
+        connection: &Web3<WebSocket>,
+        hashes: &[H256],
+    ) -> Result<Option<TransactionReceipt>, Error> {
+        for hash in hashes {
+            if let Some(receipt) = connection
+                .eth()
+                .transaction_receipt(*hash)
+                .await
+                .map_err(|error| Error::Unconfirmed(format!("{:?}", error)))?
+            {
+                return Ok(Some(receipt));
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn tx_hash(submission: &Submission) -> Result<H256, Error> {
+        H256::from_str(&submission.tx_hash).map_err(|error| Error::Custom(format!("{:?}", error)))
+    }
+
+    fn decode_raw(raw: &str) -> Result<Bytes, Error> {
+        hex::decode(raw.trim_start_matches("0x"))
+            .map(Bytes)
+            .map_err(|error| Error::Custom(format!("{:?}", error)))
+    }
+
+    /// Result of mined transaction with time of its block. Transaction is already mined,
+    /// so block which can't be loaded only leaves time unknown.
+    async fn settlement(connection: &Web3<WebSocket>, receipt: &TransactionReceipt) -> Settlement {
+        let block = match receipt.block_hash {
+            Some(hash) => connection.eth().block(BlockId::Hash(hash)).await,
+            None => Ok(None),
+        };
+        let timestamp = match block {
+            Ok(block) => block
+                .and_then(|block| u64::try_from(block.timestamp).ok())
+                .map(|secs| secs.saturating_mul(1000)),
+            Err(error) => {
+                warn!("[BSC Adapter] - load block of {:?}: {:?}", receipt.transaction_hash, error);
+                None
+            }
+        };
+
+        Settlement {
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block: receipt.block_number.unwrap_or_default().as_u64(),
+            gas_used: receipt.gas_used.map(|gas_used| gas_used.to_string()),
+            fee: receipt
+                .gas_used
+                .zip(receipt.effective_gas_price)
+                .map(|(gas_used, gas_price)| (gas_used * gas_price).to_string()),
+            timestamp,
+            success: receipt.status == Some(U64::from(1)),
+        }
     }
 
-    fn check_extrinsic(receipt: &TransactionReceipt) -> Result<(), Error> {
-        if receipt.status == Some(U64::from(1)) {
+    fn check_extrinsic(settlement: &Settlement) -> Result<(), Error> {
+        if settlement.success {
             Ok(())
         } else {
             Err(Error::Custom(String::from("No confirmation found!")))
@@ -0,0 +1,341 @@
+use crate::key_provider::MasterKey;
+
+use async_trait::async_trait;
+use primitives::Error;
+use rlp::{Rlp, RlpStream};
+use serde_json::{json, Value};
+use std::convert::TryFrom;
+use web3::{
+    signing::{keccak256, recover, Key, SecretKeyRef},
+    transports::WebSocket,
+    types::{Address, Bytes, TransactionParameters, H256, U256},
+    Web3,
+};
+
+/// Transaction signed and ready to be broadcast
+pub struct SignedTransaction {
+    pub hash: H256,
+    pub raw: Bytes,
+}
+
+/// Signs BSC transactions on behalf of bridge account
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Account which pays for transactions
+    fn address(&self) -> Address;
+
+    /// Sign fully filled transaction, it is not sent to blockchain.
+    /// # Errors
+    async fn sign(
+        &self,
+        connection: &Web3<WebSocket>,
+        transaction: TransactionParameters,
+    ) -> Result<SignedTransaction, Error>;
+}
+
+/// Signs with key held in memory of bridge process
+pub struct LocalSigner {
+    key: MasterKey,
+}
+
+impl LocalSigner {
+    #[must_use]
+    pub fn new(key: MasterKey) -> Self {
+        Self { key }
+    }
+}
+
+#[async_trait]
+impl Signer for LocalSigner {
+    fn address(&self) -> Address {
+        SecretKeyRef::new(self.key.secret()).address()
+    }
+
+    async fn sign(
+        &self,
+        connection: &Web3<WebSocket>,
+        transaction: TransactionParameters,
+    ) -> Result<SignedTransaction, Error> {
+        let signed = connection
+            .accounts()
+            .sign_transaction(transaction, self.key.secret())
+            .await
+            .map_err(Error::Web3)?;
+
+        Ok(SignedTransaction {
+            hash: signed.transaction_hash,
+            raw: signed.raw_transaction,
+        })
+    }
+}
+
+/// Asks external signer (Web3Signer, Clef, ...) over JSON-RPC `eth_signTransaction`,
+/// key never leaves the signer
+pub struct RemoteSigner {
+    url: String,
+    address: Address,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    #[must_use]
+    pub fn new(url: &str, address: Address) -> Self {
+        Self {
+            url: url.to_string(),
+            address,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    fn request(&self, transaction: &TransactionParameters) -> Result<Value, Error> {
+        let missing = |field: &str| Error::Custom(format!("Transaction for remote signer must have {}", field));
+
+        Ok(json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_signTransaction",
+            "params": [{
+                "from": format!("{:?}", self.address),
+                "to": transaction.to.map(|to| format!("{:?}", to)),
+                "gas": format!("{:#x}", transaction.gas),
+                "gasPrice": format!("{:#x}", transaction.gas_price.ok_or_else(|| missing("gas price"))?),
+                "nonce": format!("{:#x}", transaction.nonce.ok_or_else(|| missing("nonce"))?),
+                "value": format!("{:#x}", transaction.value),
+                "data": format!("0x{}", hex::encode(&transaction.data.0)),
+                "chainId": format!("{:#x}", transaction.chain_id.ok_or_else(|| missing("chain id"))?),
+            }],
+        }))
+    }
+
+    /// Signer is trusted with key, not with transaction: returned one must be signed by bridge account
+    /// and match requested one, otherwise compromised signer could send anything on behalf of bridge.
+    fn check(&self, transaction: &TransactionParameters, raw: &[u8]) -> Result<(), Error> {
+        let signed = LegacyTransaction::decode(raw)?;
+        let mismatch = |field: &str| Error::Custom(format!("Remote signer changed {} of transaction", field));
+
+        if signed.from != self.address {
+            return Err(mismatch("sender"));
+        }
+        if Some(signed.nonce) != transaction.nonce {
+            return Err(mismatch("nonce"));
+        }
+        if signed.to != transaction.to {
+            return Err(mismatch("recipient"));
+        }
+        if signed.value != transaction.value {
+            return Err(mismatch("value"));
+        }
+        if signed.data != transaction.data.0 {
+            return Err(mismatch("data"));
+        }
+        if signed.gas != transaction.gas || Some(signed.gas_price) != transaction.gas_price {
+            return Err(mismatch("gas"));
+        }
+        if Some(signed.chain_id) != transaction.chain_id {
+            return Err(mismatch("chain id"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Signed EIP-155 transaction, the only kind bridge sends
+#[derive(Debug)]
+struct LegacyTransaction {
+    from: Address,
+    nonce: U256,
+    gas_price: U256,
+    gas: U256,
+    to: Option<Address>,
+    value: U256,
+    data: Vec<u8>,
+    chain_id: u64,
+}
+
+impl LegacyTransaction {
+    /// Decode RLP of `[nonce, gasPrice, gas, to, value, data, v, r, s]` and recover its sender.
+    fn decode(raw: &[u8]) -> Result<Self, Error> {
+        let invalid =
+            |reason: String| Error::Custom(format!("Remote signer returned invalid transaction: {}", reason));
+        let rlp_error = |error: rlp::DecoderError| invalid(format!("{:?}", error));
+
+        // Typed transactions start with their type, legacy one is RLP list
+        if raw.first().map_or(true, |byte| *byte < 0xc0) {
+            return Err(invalid(String::from("not a legacy transaction")));
+        }
+        let rlp = Rlp::new(raw);
+        if rlp.item_count().map_err(rlp_error)? != 9 {
+            return Err(invalid(String::from("wrong number of fields")));
+        }
+
+        let to = rlp.at(3).map_err(rlp_error)?;
+        let to = if to.is_empty() {
+            None
+        } else {
+            Some(to.as_val::<Address>().map_err(rlp_error)?)
+        };
+        // Replay protected `v` is `chain_id * 2 + 35 + recovery id`
+        let v = rlp.val_at::<u64>(6).map_err(rlp_error)?;
+        if v < 35 {
+            return Err(invalid(String::from("no chain id")));
+        }
+        let chain_id = (v - 35) / 2;
+
+        let mut unsigned = RlpStream::new_list(9);
+        for index in 0..6 {
+            unsigned.append_raw(rlp.at(index).map_err(rlp_error)?.as_raw(), 1);
+        }
+        unsigned.append(&chain_id);
+        unsigned.append(&0_u8);
+        unsigned.append(&0_u8);
+
+        let mut signature = [0_u8; 64];
+        rlp.val_at::<U256>(7).map_err(rlp_error)?.to_big_endian(&mut signature[..32]);
+        rlp.val_at::<U256>(8).map_err(rlp_error)?.to_big_endian(&mut signature[32..]);
+        let recovery_id = i32::try_from((v - 35) % 2).map_err(|error| invalid(format!("{:?}", error)))?;
+        let from = recover(&keccak256(&unsigned.out()), &signature, recovery_id)
+            .map_err(|error| invalid(format!("{:?}", error)))?;
+
+        Ok(Self {
+            from,
+            nonce: rlp.val_at(0).map_err(rlp_error)?,
+            gas_price: rlp.val_at(1).map_err(rlp_error)?,
+            gas: rlp.val_at(2).map_err(rlp_error)?,
+            to,
+            value: rlp.val_at(4).map_err(rlp_error)?,
+            data: rlp.val_at(5).map_err(rlp_error)?,
+            chain_id,
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    async fn sign(
+        &self,
+        _connection: &Web3<WebSocket>,
+        transaction: TransactionParameters,
+    ) -> Result<SignedTransaction, Error> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&self.request(&transaction)?)
+            .send()
+            .await
+            .map_err(|error| Error::Custom(format!("Remote signer request failed: {}", error)))?
+            .json::<Value>()
+            .await
+            .map_err(|error| Error::Custom(format!("Remote signer response is not a json: {}", error)))?;
+
+        if let Some(error) = response.get("error") {
+            return Err(Error::Custom(format!("Remote signer refused: {}", error)));
+        }
+        // Web3Signer returns raw transaction, Clef wraps it into `{ raw, tx }`
+        let raw = response["result"]
+            .as_str()
+            .or_else(|| response["result"]["raw"].as_str())
+            .ok_or_else(|| Error::Custom(format!("Unexpected remote signer response: {}", response)))?;
+        let raw = hex::decode(raw.trim_start_matches("0x"))
+            .map_err(|error| Error::Custom(format!("Remote signer returned invalid hex: {:?}", error)))?;
+        self.check(&transaction, &raw)?;
+
+        Ok(SignedTransaction {
+            hash: H256::from(keccak256(&raw)),
+            raw: Bytes(raw),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use web3::{signing::SecretKey, transports::Http};
+
+    fn key() -> SecretKey {
+        SecretKey::from_slice(&[7; 32]).unwrap()
+    }
+
+    fn transaction() -> TransactionParameters {
+        TransactionParameters {
+            nonce: Some(U256::from(7)),
+            to: Some(Address::repeat_byte(1)),
+            gas: U256::from(100_000),
+            gas_price: Some(U256::from(5_000_000_000_u64)),
+            value: U256::zero(),
+            data: Bytes(vec![0xab, 0xcd, 0xef]),
+            chain_id: Some(97),
+            ..TransactionParameters::default()
+        }
+    }
+
+    /// Transaction signed by web3, all fields are filled, so node isn't asked.
+    async fn signed(transaction: TransactionParameters) -> Vec<u8> {
+        let web3 = Web3::new(Http::new("http://127.0.0.1:1").unwrap());
+        web3.accounts()
+            .sign_transaction(transaction, &key())
+            .await
+            .unwrap()
+            .raw_transaction
+            .0
+    }
+
+    #[tokio::test]
+    async fn signed_transaction_is_decoded() {
+        let raw = signed(transaction()).await;
+
+        let decoded = LegacyTransaction::decode(&raw).unwrap();
+        assert_eq!(decoded.from, SecretKeyRef::new(&key()).address());
+        assert_eq!(decoded.nonce, U256::from(7));
+        assert_eq!(decoded.to, Some(Address::repeat_byte(1)));
+        assert_eq!(decoded.data, vec![0xab, 0xcd, 0xef]);
+        assert_eq!(decoded.chain_id, 97);
+
+        assert!(LegacyTransaction::decode(&raw[1..]).is_err());
+        assert!(LegacyTransaction::decode(&[0x02, 0xc0]).is_err());
+    }
+
+    #[tokio::test]
+    async fn transaction_changed_by_signer_is_rejected() {
+        let signer = RemoteSigner::new("http://127.0.0.1:1", SecretKeyRef::new(&key()).address());
+        assert!(signer.check(&transaction(), &signed(transaction()).await).is_ok());
+
+        let changes = [
+            TransactionParameters {
+                nonce: Some(U256::from(8)),
+                ..transaction()
+            },
+            TransactionParameters {
+                to: Some(Address::repeat_byte(2)),
+                ..transaction()
+            },
+            TransactionParameters {
+                value: U256::from(1),
+                ..transaction()
+            },
+            TransactionParameters {
+                data: Bytes(vec![0xab]),
+                ..transaction()
+            },
+            TransactionParameters {
+                gas_price: Some(U256::from(6_000_000_000_u64)),
+                ..transaction()
+            },
+            TransactionParameters {
+                chain_id: Some(56),
+                ..transaction()
+            },
+        ];
+        for changed in changes {
+            let raw = signed(changed.clone()).await;
+            assert!(signer.check(&transaction(), &raw).is_err(), "{:?} is accepted", changed);
+        }
+
+        // Same transaction signed by other key
+        let other = RemoteSigner::new("http://127.0.0.1:1", Address::repeat_byte(9));
+        assert!(other.check(&transaction(), &signed(transaction()).await).is_err());
+    }
+}
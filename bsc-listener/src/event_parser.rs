@@ -1,4 +1,4 @@
-use ethabi::Error;
+use ethabi::{Contract, Error, LogParam, RawLog, Token};
 use primitives::{
     events::bsc::{BscEventType, TransferNftToRealis, TransferTokenToRealis},
     types::RawEvent,
@@ -7,29 +7,35 @@ use realis_primitives::TokenId;
 use runtime::AccountId;
 use serde::Deserialize;
 use serde_json::Value;
-use std::str::FromStr;
-use web3::{
-    ethabi::ParamType,
-    types::{TransactionReceipt, H256},
-};
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
+use web3::types::{Log, H256};
+
+const BEP20_ABI: &[u8] = include_bytes!("../../bsc-adapter/res/BEP20.abi");
+const BEP721_ABI: &[u8] = include_bytes!("../../bsc-adapter/res/BEP721.abi");
 
 #[derive(Debug)]
 pub enum ParseError {
-    MissingParam(RawEvent, usize),
+    /// Log topic isn't expected bridge event of contract
+    UnknownEvent(RawEvent),
+    MissingParam(RawEvent, &'static str),
     DecodeError(RawEvent, Error),
     U128(RawEvent),
     Address(RawEvent),
+    AccountId(RawEvent),
     TokenID(RawEvent),
     SerdeError(RawEvent, serde_json::error::Error),
 }
 
 impl ParseError {
+    #[must_use]
     pub fn get_event(&self) -> RawEvent {
         match self {
-            ParseError::MissingParam(event, _)
+            ParseError::UnknownEvent(event)
+            | ParseError::MissingParam(event, _)
             | ParseError::DecodeError(event, _)
             | ParseError::U128(event)
             | ParseError::Address(event)
+            | ParseError::AccountId(event)
             | ParseError::SerdeError(event, _)
             | ParseError::TokenID(event) => event,
         }
@@ -38,122 +44,208 @@ impl ParseError {
 }
 
 pub trait EventParser {
-    fn parse(receipt: TransactionReceipt, topic: &H256) -> Vec<Result<BscEventType, ParseError>>;
+    /// Topic of bridge event
+    fn topic(&self) -> H256;
+
+    /// # Errors
+    fn parse(&self, log: &Log) -> Result<BscEventType, ParseError>;
 }
 
-pub struct TokenParser {}
+fn raw_event(log: &Log) -> RawEvent {
+    RawEvent {
+        block_number: log.block_number,
+        hash: log.transaction_hash.unwrap_or_default(),
+        log_index: log.log_index.map(|index| index.as_u64()).unwrap_or_default(),
+        data: log.data.0.clone(),
+    }
+}
 
-impl EventParser for TokenParser {
-    fn parse(receipt: TransactionReceipt, topic: &H256) -> Vec<Result<BscEventType, ParseError>> {
-        receipt
-            .logs
+/// Event definitions of contract ABI by their topic
+struct ContractEvents {
+    events: HashMap<H256, ethabi::Event>,
+}
+
+impl ContractEvents {
+    fn load(abi: &[u8]) -> Result<Self, Error> {
+        let contract = Contract::load(abi)?;
+
+        Ok(Self {
+            events: contract
+                .events()
+                .map(|event| (event.signature(), event.clone()))
+                .collect(),
+        })
+    }
+
+    /// Topic of `name` event, computed from its signature
+    fn topic(&self, name: &str) -> Result<H256, Error> {
+        self.events
             .iter()
-            .filter(|log| log.topics.contains(topic))
-            .map(|log| {
-                let raw_event = RawEvent {
-                    block_number: receipt.block_number,
-                    hash: receipt.transaction_hash,
-                    data: log.data.0.clone(),
-                };
-
-                let params = ethabi::decode(
-                    &[ParamType::String, ParamType::Uint(256), ParamType::Address],
-                    &log.data.0,
-                )
-                .map_err(|error| ParseError::DecodeError(raw_event.clone(), error))?;
-
-                let json: Value = serde_json::to_value(
-                    &params
-                        .get(1)
-                        .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 1))?
-                        .to_string(),
-                )
-                .map_err(|error| ParseError::SerdeError(raw_event.clone(), error))?;
-
-                let to: AccountId = Deserialize::deserialize(json)
-                    .map_err(|error| ParseError::SerdeError(raw_event.clone(), error))?;
-
-                let amount = params
-                    .get(1)
-                    .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 1))?
-                    .clone()
-                    .into_uint()
-                    .ok_or_else(|| ParseError::U128(raw_event.clone()))?
-                    .as_u128();
-
-                let from = params
-                    .get(2)
-                    .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 2))?
-                    .clone()
-                    .into_address()
-                    .ok_or_else(|| ParseError::Address(raw_event.clone()))?;
-
-                Ok(BscEventType::TransferTokenToRealis(TransferTokenToRealis {
-                    block: receipt.block_number,
-                    hash: receipt.transaction_hash,
-                    from,
-                    to,
-                    amount,
-                }))
+            .find(|(_, event)| event.name == name)
+            .map(|(topic, _)| *topic)
+            .ok_or_else(|| Error::InvalidName(String::from(name)))
+    }
+
+    /// Decode logs with `topic` as `name` event
+    fn alias(&mut self, topic: H256, name: &str) {
+        if let Some(event) = self.events.values().find(|event| event.name == name).cloned() {
+            self.events.insert(topic, event);
+        }
+    }
+
+    /// Decode indexed and non-indexed params of `name` event,
+    /// event is found by first topic of log.
+    fn decode(&self, log: &Log, name: &str, raw_event: &RawEvent) -> Result<Vec<LogParam>, ParseError> {
+        let event = log
+            .topics
+            .first()
+            .and_then(|topic| self.events.get(topic))
+            .filter(|event| event.name == name)
+            .ok_or_else(|| ParseError::UnknownEvent(raw_event.clone()))?;
+
+        // Topic can be overridden, so signature is restored before decoding
+        let mut topics = log.topics.clone();
+        topics[0] = event.signature();
+
+        let log = event
+            .parse_log(RawLog {
+                topics,
+                data: log.data.0.clone(),
             })
-            .collect()
+            .map_err(|error| ParseError::DecodeError(raw_event.clone(), error))?;
+
+        Ok(log.params)
     }
 }
 
-pub struct NftParser {}
+fn param(params: &[LogParam], name: &'static str, raw_event: &RawEvent) -> Result<Token, ParseError> {
+    params
+        .iter()
+        .find(|param| param.name == name)
+        .map(|param| param.value.clone())
+        .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), name))
+}
+
+/// Realis account from ss58 string param
+fn account(token: Token, raw_event: &RawEvent) -> Result<AccountId, ParseError> {
+    let address = token
+        .into_string()
+        .ok_or_else(|| ParseError::AccountId(raw_event.clone()))?;
+
+    Deserialize::deserialize(Value::String(address))
+        .map_err(|error| ParseError::SerdeError(raw_event.clone(), error))
+}
+
+/// Decodes `TransferToRealis` event of BEP20 contract
+pub struct TokenParser {
+    contract: ContractEvents,
+    topic: H256,
+}
+
+impl TokenParser {
+    const EVENT: &'static str = "TransferToRealis";
+
+    /// # Errors
+    pub fn new() -> Result<Self, Error> {
+        let contract = ContractEvents::load(BEP20_ABI)?;
+        let topic = contract.topic(Self::EVENT)?;
+
+        Ok(Self { contract, topic })
+    }
+
+    /// Decode logs with given topic instead of one from ABI
+    #[must_use]
+    pub fn with_topic(mut self, topic: H256) -> Self {
+        self.contract.alias(topic, Self::EVENT);
+        self.topic = topic;
+        self
+    }
+}
+
+impl EventParser for TokenParser {
+    fn topic(&self) -> H256 {
+        self.topic
+    }
+
+    fn parse(&self, log: &Log) -> Result<BscEventType, ParseError> {
+        let raw_event = raw_event(log);
+        let params = self.contract.decode(log, Self::EVENT, &raw_event)?;
+
+        let from = param(&params, "signer", &raw_event)?
+            .into_address()
+            .ok_or_else(|| ParseError::Address(raw_event.clone()))?;
+
+        let to = account(param(&params, "recipient", &raw_event)?, &raw_event)?;
+
+        let amount = param(&params, "amount", &raw_event)?
+            .into_uint()
+            .and_then(|amount| u128::try_from(amount).ok())
+            .ok_or_else(|| ParseError::U128(raw_event.clone()))?;
+
+        Ok(BscEventType::TransferTokenToRealis(TransferTokenToRealis {
+            block: raw_event.block_number,
+            hash: raw_event.hash,
+            log_index: raw_event.log_index,
+            contract: log.address,
+            from,
+            to,
+            amount,
+        }))
+    }
+}
+
+/// Decodes `TransferNftToRealis` event of BEP721 contract
+pub struct NftParser {
+    contract: ContractEvents,
+    topic: H256,
+}
+
+impl NftParser {
+    const EVENT: &'static str = "TransferNftToRealis";
+
+    /// # Errors
+    pub fn new() -> Result<Self, Error> {
+        let contract = ContractEvents::load(BEP721_ABI)?;
+        let topic = contract.topic(Self::EVENT)?;
+
+        Ok(Self { contract, topic })
+    }
+
+    /// Decode logs with given topic instead of one from ABI
+    #[must_use]
+    pub fn with_topic(mut self, topic: H256) -> Self {
+        self.contract.alias(topic, Self::EVENT);
+        self.topic = topic;
+        self
+    }
+}
 
 impl EventParser for NftParser {
-    fn parse(receipt: TransactionReceipt, topic: &H256) -> Vec<Result<BscEventType, ParseError>> {
-        receipt
-            .logs
-            .iter()
-            .filter(|log| log.topics.contains(topic))
-            .map(|log| {
-                let raw_event = RawEvent {
-                    block_number: receipt.block_number,
-                    hash: receipt.transaction_hash,
-                    data: log.data.0.clone(),
-                };
-
-                let params = ethabi::decode(
-                    &[ParamType::Address, ParamType::String, ParamType::Uint(256)],
-                    &log.data.0,
-                )
-                .map_err(|error| ParseError::DecodeError(raw_event.clone(), error))?;
-
-                let from = params
-                    .get(0)
-                    .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 0))?
-                    .clone()
-                    .into_address()
-                    .ok_or_else(|| ParseError::Address(raw_event.clone()))?;
-
-                let json: Value = serde_json::to_value(
-                    &params
-                        .get(1)
-                        .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 1))?
-                        .to_string(),
-                )
-                .map_err(|error| ParseError::SerdeError(raw_event.clone(), error))?;
-
-                let to: AccountId = Deserialize::deserialize(json)
-                    .map_err(|error| ParseError::SerdeError(raw_event.clone(), error))?;
-                let token_id = TokenId::from_str(
-                    &params
-                        .get(2)
-                        .ok_or_else(|| ParseError::MissingParam(raw_event.clone(), 2))?
-                        .to_string(),
-                )
-                .map_err(|_| ParseError::TokenID(raw_event.clone()))?;
-
-                Ok(BscEventType::TransferNftToRealis(TransferNftToRealis {
-                    block: receipt.block_number,
-                    hash: receipt.transaction_hash,
-                    from,
-                    dest: to,
-                    token_id,
-                }))
-            })
-            .collect()
+    fn topic(&self) -> H256 {
+        self.topic
+    }
+
+    fn parse(&self, log: &Log) -> Result<BscEventType, ParseError> {
+        let raw_event = raw_event(log);
+        let params = self.contract.decode(log, Self::EVENT, &raw_event)?;
+
+        let from = param(&params, "sender", &raw_event)?
+            .into_address()
+            .ok_or_else(|| ParseError::Address(raw_event.clone()))?;
+
+        let dest = account(param(&params, "to", &raw_event)?, &raw_event)?;
+
+        let token_id = TokenId::from_str(&param(&params, "tokenId", &raw_event)?.to_string())
+            .map_err(|_| ParseError::TokenID(raw_event.clone()))?;
+
+        Ok(BscEventType::TransferNftToRealis(TransferNftToRealis {
+            block: raw_event.block_number,
+            hash: raw_event.hash,
+            log_index: raw_event.log_index,
+            contract: log.address,
+            from,
+            dest,
+            token_id,
+        }))
     }
 }
@@ -1,35 +1,83 @@
-mod event_parser;
-use crate::event_parser::{EventParser, ParseError};
+pub mod event_parser;
+use crate::event_parser::{EventParser, NftParser, TokenParser};
 
-use db::Database;
-use primitives::events::bsc::BscEventType;
+use db::{Database, UnitOfWork};
+use primitives::{
+    asset::{AssetKind, AssetRegistry},
+    attestation::Attestor,
+    events::bsc::BscEventType,
+};
 use rust_lib::healthchecker::HealthChecker;
 
 use ethabi::ethereum_types::H256;
 use log::{error, info, warn};
 use primitives::Error;
-use std::{str::FromStr, sync::Arc};
+use std::{cmp::min, collections::BTreeSet, iter, str::FromStr, sync::Arc};
 use tokio::{select, sync::mpsc::Sender};
 use web3::{
     self,
     futures::StreamExt,
     transports::WebSocket,
-    types::{Address, BlockNumber, Transaction},
+    types::{Address, BlockId, BlockNumber, FilterBuilder, Log, Transaction, U64},
     Web3,
 };
 
+/// How contract events are searched in BSC blocks
+#[derive(Debug, Clone, Copy)]
+pub enum ScanMode {
+    /// Fetch every block with transactions and receipt
+    /// of each transaction sent directly to the contracts
+    Receipts,
+    /// Query `eth_getLogs` over block ranges of given size,
+    /// also catches calls made through other contracts
+    Logs(u64),
+}
+
+impl FromStr for ScanMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "receipts" => Ok(ScanMode::Receipts),
+            "logs" => Ok(ScanMode::Logs(1000)),
+            value => Err(format!("Unknown scan mode: {}", value)),
+        }
+    }
+}
+
+/// Hashes of this many last processed blocks are kept to detect reorganizations
+const HASH_WINDOW: u64 = 1000;
+/// Events of contracts are sampled in this many last blocks at startup
+const VALIDATION_BLOCKS: u64 = 1000;
+
+struct Header {
+    number: u64,
+    hash: H256,
+    parent_hash: H256,
+}
+
 pub struct BlockListener {
     web3: Web3<WebSocket>,
     tx: Sender<BscEventType>,
     health_checker: HealthChecker,
     db: Arc<Database>,
-    token_contract: Address,
-    nft_contract: Address,
-    token_topic: H256,
-    nft_topic: H256,
+    /// Transfers are listened on contracts of enabled assets
+    assets: Arc<AssetRegistry>,
+    /// Bridge events are decoded with contract ABIs, their topics are used in logs filter
+    token_parser: TokenParser,
+    nft_parser: NftParser,
+    /// Block `N` is processed only when head is at least `N + confirmations`
+    confirmations: u64,
+    scan_mode: ScanMode,
+    last_processed: Option<u64>,
+    /// Signs every found transfer in multi-operator mode
+    attestor: Option<Arc<Attestor>>,
 }
 
 impl BlockListener {
+    /// Topics of bridge events are computed from contract ABIs,
+    /// `token_topic` and `nft_topic` override them and skip check of contract code.
+    /// Fails when contracts of enabled assets aren't deployed or emit events of other ABI.
     #[allow(clippy::too_many_arguments)]
     /// # Errors
     /// # Panics
@@ -38,65 +86,126 @@ impl BlockListener {
         tx: Sender<BscEventType>,
         health_checker: HealthChecker,
         db: Arc<Database>,
-        token_contract: &str,
-        nft_contract: &str,
-        token_topic: &str,
-        nft_topic: &str,
+        assets: Arc<AssetRegistry>,
+        token_topic: Option<&str>,
+        nft_topic: Option<&str>,
+        confirmations: u64,
+        scan_mode: ScanMode,
     ) -> Result<Self, String> {
         let ws = web3::transports::WebSocket::new(&url)
             .await
             .map_err(|error| format!("{:?}", error))?;
         let web3 = web3::Web3::new(ws);
-        let token_contract = Address::from_str(token_contract).map_err(|error| format!("{:?}", error))?;
-        let nft_contract = Address::from_str(nft_contract).map_err(|error| format!("{:?}", error))?;
 
-        let token_topic = H256::from_str(token_topic).map_err(|error| format!("{:?}", error))?;
-        let nft_topic = H256::from_str(nft_topic).map_err(|error| format!("{:?}", error))?;
+        let mut overridden = Vec::new();
+        let mut token_parser = TokenParser::new().map_err(|error| format!("{:?}", error))?;
+        if let Some(topic) = token_topic {
+            let topic = H256::from_str(topic).map_err(|error| format!("{:?}", error))?;
+            warn!("[BSC Listener] - token topic is overridden: {:?}", topic);
+            token_parser = token_parser.with_topic(topic);
+            overridden.push(AssetKind::Token);
+        }
+        let mut nft_parser = NftParser::new().map_err(|error| format!("{:?}", error))?;
+        if let Some(topic) = nft_topic {
+            let topic = H256::from_str(topic).map_err(|error| format!("{:?}", error))?;
+            warn!("[BSC Listener] - nft topic is overridden: {:?}", topic);
+            nft_parser = nft_parser.with_topic(topic);
+            overridden.push(AssetKind::Nft);
+        }
 
-        Ok(Self {
+        let listener = Self {
             web3,
             tx,
             health_checker,
             db,
-            token_contract,
-            nft_contract,
-            token_topic,
-            nft_topic,
-        })
+            assets,
+            token_parser,
+            nft_parser,
+            confirmations,
+            scan_mode,
+            last_processed: None,
+            attestor: None,
+        };
+        listener
+            .validate(&overridden)
+            .await
+            .map_err(|error| format!("{:?}", error))?;
+
+        Ok(listener)
+    }
+
+    /// Check that contracts are deployed and emit bridge events,
+    /// otherwise transfers in one direction would be silently missed.
+    /// Code of proxy contract doesn't contain topic, so missing topic is only a warning
+    /// and recent events of contract are decoded instead.
+    async fn validate(&self, overridden: &[AssetKind]) -> Result<(), Error> {
+        // Empty address filter would match logs of all contracts
+        if self.assets.enabled().next().is_none() {
+            return Err(Error::Custom(String::from("No enabled assets to listen")));
+        }
+        let head = self.web3.eth().block_number().await.map_err(Error::Web3)?.as_u64();
+
+        for asset in self.assets.enabled() {
+            let (contract, parser) = (asset.contract, self.parser(asset.kind));
+            let topic = parser.topic();
+            let code = self.web3.eth().code(contract, None).await.map_err(Error::Web3)?;
+            if code.0.is_empty() {
+                return Err(Error::Custom(format!("No contract is deployed at {:?}", contract)));
+            }
+            // Topic of emitted event is pushed to stack as constant, so it is part of contract code
+            let in_code = code.0.windows(topic.as_bytes().len()).any(|window| window == topic.as_bytes());
+            if overridden.contains(&asset.kind) {
+                warn!(
+                    "[BSC Listener] - topic {:?} is overridden, code of {:?} isn't checked",
+                    topic, contract
+                );
+            } else if !in_code {
+                warn!(
+                    "[BSC Listener] - code of {:?} doesn't contain topic {:?}, it could be a proxy",
+                    contract, topic
+                );
+            }
+
+            let filter = FilterBuilder::default()
+                .from_block(BlockNumber::Number(U64::from(head.saturating_sub(VALIDATION_BLOCKS))))
+                .to_block(BlockNumber::Number(U64::from(head)))
+                .address(vec![contract])
+                .topics(Some(vec![topic]), None, None, None)
+                .build();
+            let logs = self.web3.eth().logs(filter).await.map_err(Error::Web3)?;
+            let decoded = check_sample(parser, contract, &logs)?;
+            info!(
+                "[BSC Listener] - contract {:?} emitted {} events with topic {:?} in last {} blocks",
+                contract, decoded, topic, VALIDATION_BLOCKS
+            );
+        }
+
+        Ok(())
+    }
+
+    fn parser(&self, kind: AssetKind) -> &dyn EventParser {
+        match kind {
+            AssetKind::Token => &self.token_parser,
+            AssetKind::Nft => &self.nft_parser,
+        }
+    }
+
+    #[must_use]
+    pub fn with_attestor(mut self, attestor: Arc<Attestor>) -> Self {
+        self.attestor = Some(attestor);
+        self
     }
 
+    /// Continue processing from block next after `from`.
     /// # Errors
     /// # Panics
-    #[allow(clippy::match_same_arms)]
     pub async fn listen_with_restore(&mut self, from: u64) {
-        warn!("Start restore BSC!!!");
-        let block_number = self.web3.eth().block_number().await.unwrap().as_u64();
-        for number in from..block_number {
-            warn!("Start restore!!!");
-            let block = self
-                .web3
-                .eth()
-                .block(web3::types::BlockId::Number(BlockNumber::from(number)))
-                .await
-                .unwrap()
-                .unwrap();
-            let some = self
-                .web3
-                .eth()
-                .block_with_txs(web3::types::BlockId::Hash(block.hash.unwrap()))
-                .await
-                .unwrap()
-                .unwrap();
-
-            for transaction in some.transactions {
-                self.process(transaction).await;
-            }
-        }
+        warn!("Start restore BSC from block {}!!!", from);
+        self.last_processed = Some(from);
         self.listen().await;
     }
 
     /// # Panics
-    #[allow(clippy::single_match)]
     pub async fn listen(&mut self) {
         // Stream
         let mut sub = self.web3.eth_subscribe().subscribe_new_heads().await.unwrap();
@@ -109,26 +218,10 @@ impl BlockListener {
                 () = health_checker.is_alive() => break,
                 option = sub.next() => {
                     if let Some(value) = option {
-                        let block_header = value.unwrap();
-                        match self.db.update_block_bsc(block_header.number).await {
-                            Ok(_) => {
-                                info!("Success add binance block to database");
-                            }
-                            Err(error) => {
-                                self.health_checker.make_sick();
-                                error!("Can't add binance block with error: {:?}", error);
-                            }
-                        }
-                        let block = self
-                            .web3
-                            .eth()
-                            .block_with_txs(web3::types::BlockId::Hash(block_header.hash.unwrap()))
-                            .await
-                            .unwrap()
-                            .unwrap();
-
-                        for transaction in block.transactions {
-                            self.process(transaction).await;
+                        match value.map(|header| header.number) {
+                            Ok(Some(head)) => self.process_blocks(head.as_u64()).await,
+                            Ok(None) => warn!("[BSC Listener] - got head without number"),
+                            Err(error) => error!("[BSC Listener] - subscription error: {:?}", error),
                         }
                     }
                 }
@@ -137,46 +230,322 @@ impl BlockListener {
         sub.unsubscribe().await.unwrap();
     }
 
-    async fn process(&self, transaction: Transaction) {
-        if let Some(account) = transaction.to {
-            if account == self.token_contract {
-                if let Ok(Some(receipt)) = self.web3.eth().transaction_receipt(transaction.hash).await {
-                    let events = event_parser::TokenParser::parse(receipt, &self.token_topic);
-                    self.execute(events).await;
+    /// Process all blocks which got enough confirmations at `head`.
+    /// If some block fail it will be retried with next head.
+    async fn process_blocks(&mut self, head: u64) {
+        let target = match head.checked_sub(self.confirmations) {
+            Some(target) => target,
+            None => return,
+        };
+        let mut from = self.last_processed.map_or(target, |last| last + 1);
+
+        while from <= target {
+            let to = match self.scan_mode {
+                ScanMode::Receipts => from,
+                ScanMode::Logs(range) => min(from + range.max(1) - 1, target),
+            };
+            match self.process_range(from, to).await {
+                Ok(None) => {
+                    info!("Success process binance blocks [{:^8}..={:^8}]", from, to);
+                    self.last_processed = Some(to);
+                    from = to + 1;
                 }
-            } else if account == self.nft_contract {
-                if let Ok(Some(receipt)) = self.web3.eth().transaction_receipt(transaction.hash).await {
-                    let events = event_parser::NftParser::parse(receipt, &self.nft_topic);
-                    self.execute(events).await;
+                Ok(Some(fork)) => {
+                    self.last_processed = Some(fork);
+                    from = fork + 1;
+                }
+                Err(error) => {
+                    error!("[BSC Listener] - can't process blocks [{:^8}..={:^8}]: {:?}", from, to, error);
+                    break;
                 }
             }
         }
     }
 
-    async fn execute(&self, events: Vec<Result<BscEventType, ParseError>>) {
-        for event in events {
-            match event {
-                Ok(event) => {
-                    if let Err(error) = self.send(event).await {
-                        self.health_checker.make_sick();
-                        error!("[BSC Listener] - {:?}", error);
-                    }
+    /// Returns number of last common block if reorganization was detected,
+    /// in this case blocks wasn't processed.
+    async fn process_range(&self, from: u64, to: u64) -> Result<Option<u64>, Error> {
+        let first = self.get_header(from).await?;
+        if let Some(fork) = self.find_fork(from, first.parent_hash).await? {
+            self.handle_reorg(fork, from - 1).await?;
+            return Ok(Some(fork));
+        }
+
+        let mut headers = vec![first];
+        let mut work = UnitOfWork::new();
+        match self.scan_mode {
+            ScanMode::Receipts => {
+                for number in from + 1..=to {
+                    headers.push(self.get_header(number).await?);
                 }
-                Err(error) => {
-                    error!("Error while decode event: {:?}", error);
-                    if let Err(error) = self.db.add_raw_event(error.get_event()).await {
-                        error!("[BSC Listener] - logging undecoded event - {:?}", error);
-                        self.health_checker.make_sick();
+                Self::check_chain(&headers)?;
+
+                for header in &headers {
+                    let block = self
+                        .web3
+                        .eth()
+                        .block_with_txs(BlockId::Hash(header.hash))
+                        .await
+                        .map_err(Error::Web3)?
+                        .ok_or_else(|| Error::Custom(format!("Missing binance block {:?}", header.hash)))?;
+
+                    for transaction in block.transactions {
+                        self.process(transaction, &mut work).await?;
                     }
                 }
             }
+            ScanMode::Logs(_) => {
+                let logs = self.get_logs(from, to).await?;
+
+                // Only blocks with logs are checked, last one links range with the next
+                let numbers = logs
+                    .iter()
+                    .filter_map(|log| log.block_number)
+                    .map(|number| number.as_u64())
+                    .chain(iter::once(to))
+                    .filter(|number| *number != from)
+                    .collect::<BTreeSet<_>>();
+                for number in numbers {
+                    headers.push(self.get_header(number).await?);
+                }
+                Self::check_chain(&headers)?;
+
+                let reorged = logs
+                    .iter()
+                    .any(|log| !headers.iter().any(|header| log.block_hash == Some(header.hash)));
+                if reorged {
+                    return Err(Error::Custom(String::from("Logs are not from scanned blocks")));
+                }
+
+                for log in logs {
+                    self.process_log(&log, &mut work)?;
+                }
+            }
         }
+
+        for header in &headers {
+            work.add_block_bsc(header.number, header.hash, header.parent_hash);
+        }
+        if let Some(last) = headers.last() {
+            work.advance_cursor_bsc(last.number, &format!("{:?}", last.hash));
+            work.prune_blocks_bsc(last.number.saturating_sub(HASH_WINDOW));
+        }
+
+        // Transfers are sent only after block is stored with them,
+        // if sending fails they are picked up from database later
+        for event in self.db.commit(work).await?.extrinsics_bsc {
+            if self.tx.send(event).await.is_err() {
+                self.health_checker.make_sick();
+                return Err(Error::Send);
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Headers of adjacent blocks must be linked, otherwise chain changed while they were fetched.
+    fn check_chain(headers: &[Header]) -> Result<(), Error> {
+        let changed = headers
+            .windows(2)
+            .any(|pair| pair[1].number == pair[0].number + 1 && pair[1].parent_hash != pair[0].hash);
+        if changed {
+            return Err(Error::Custom(String::from("Chain was changed while scanning")));
+        }
+
+        Ok(())
+    }
+
+    async fn get_header(&self, number: u64) -> Result<Header, Error> {
+        let block = self
+            .web3
+            .eth()
+            .block(BlockId::Number(BlockNumber::Number(U64::from(number))))
+            .await
+            .map_err(Error::Web3)?
+            .ok_or_else(|| Error::Custom(format!("Missing binance block {}", number)))?;
+
+        Ok(Header {
+            number,
+            hash: block
+                .hash
+                .ok_or_else(|| Error::Custom(format!("Missing hash of binance block {}", number)))?,
+            parent_hash: block.parent_hash,
+        })
+    }
+
+    async fn get_logs(&self, from: u64, to: u64) -> Result<Vec<Log>, Error> {
+        let filter = FilterBuilder::default()
+            .from_block(BlockNumber::Number(U64::from(from)))
+            .to_block(BlockNumber::Number(U64::from(to)))
+            .address(self.assets.enabled().map(|asset| asset.contract).collect())
+            .topics(
+                Some(vec![self.token_parser.topic(), self.nft_parser.topic()]),
+                None,
+                None,
+                None,
+            )
+            .build();
+
+        self.web3.eth().logs(filter).await.map_err(Error::Web3)
+    }
+
+    /// Walk back from block `number` over stored hashes while they differ from
+    /// canonical chain. Heights without stored hash are skipped, in logs mode only
+    /// some blocks of every range are stored. Returns last common block if any stored block differs,
+    /// blocks between it and first differing one are unknown, so they are treated as reorganized.
+    async fn find_fork(&self, number: u64, parent_hash: H256) -> Result<Option<u64>, Error> {
+        let mut below = match number.checked_sub(1) {
+            Some(below) => below,
+            None => return Ok(None),
+        };
+        // Lowest stored block which differs from canonical chain
+        let mut reorged = None;
+
+        let common = loop {
+            let (current, stored) = match self.db.get_stored_block_bsc(below).await? {
+                Some(block) => block,
+                // Older hashes are pruned, reorganization can't be deeper
+                None => break reorged.map(|reorged: u64| reorged.saturating_sub(1)),
+            };
+            let canonical = if current + 1 == number {
+                parent_hash
+            } else {
+                self.get_header(current).await?.hash
+            };
+            if stored == format!("{:?}", canonical) {
+                break Some(current);
+            }
+
+            warn!(
+                "[BSC Listener] - block [{:^8}] was reorganized: {} -> {:?}",
+                current, stored, canonical
+            );
+            reorged = Some(current);
+            below = match current.checked_sub(1) {
+                Some(below) => below,
+                None => break Some(0),
+            };
+        };
+
+        Ok(reorged.and(common))
     }
 
-    async fn send(&self, event: BscEventType) -> Result<(), Error> {
-        self.db.add_extrinsic_bsc(&event).await?;
-        self.tx.send(event).await.map_err(|_| Error::Send)?;
+    /// Halt all not settled transfers from blocks `fork + 1 ..= to`,
+    /// transfers which will be found again in canonical chain are restored.
+    async fn handle_reorg(&self, fork: u64, to: u64) -> Result<(), Error> {
+        let fork_hash = self.get_header(fork).await?.hash;
+        let mut work = UnitOfWork::new();
+        work.rewind_bsc(fork, &format!("{:?}", fork_hash), to);
+        let flagged = self.db.commit(work).await?.reorged_bsc;
+        error!(
+            "[BSC Listener] - reorganization after block [{:^8}], flagged {} transfers from blocks {}..={}",
+            fork, flagged, fork + 1, to
+        );
 
         Ok(())
     }
+
+    async fn process(&self, transaction: Transaction, work: &mut UnitOfWork) -> Result<(), Error> {
+        if let Some(account) = transaction.to {
+            if self.assets.get(&account).map_or(false, |asset| asset.enabled) {
+                let receipt = self
+                    .web3
+                    .eth()
+                    .transaction_receipt(transaction.hash)
+                    .await
+                    .map_err(Error::Web3)?
+                    .ok_or_else(|| Error::Custom(format!("Missing receipt of {:?}", transaction.hash)))?;
+                for log in receipt.logs {
+                    self.process_log(&log, work)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn process_log(&self, log: &Log, work: &mut UnitOfWork) -> Result<(), Error> {
+        let parser = match self.assets.get(&log.address) {
+            Some(asset) if asset.enabled => self.parser(asset.kind),
+            _ => return Ok(()),
+        };
+        if log.topics.first() != Some(&parser.topic()) {
+            return Ok(());
+        }
+        let event = parser.parse(log);
+
+        match event {
+            Ok(event) => {
+                if let Some(attestor) = &self.attestor {
+                    if let Some(attestation) = attestor.attest_bsc(&event)? {
+                        work.add_attestation(attestation);
+                    }
+                }
+                work.add_extrinsic_bsc(event);
+            }
+            Err(error) => {
+                error!("Error while decode event: {:?}", error);
+                work.add_raw_event(error.get_event());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Recent logs of contract with bridge topic must be decoded by bridge ABI,
+/// otherwise topic belongs to other event. Returns number of decoded logs.
+fn check_sample(parser: &dyn EventParser, contract: Address, logs: &[Log]) -> Result<usize, Error> {
+    if let Some(log) = logs.iter().find(|log| log.address != contract) {
+        return Err(Error::Custom(format!(
+            "Node returned log of {:?} for contract {:?}",
+            log.address, contract
+        )));
+    }
+
+    let decoded = logs.iter().filter(|log| parser.parse(log).is_ok()).count();
+    if decoded == 0 && !logs.is_empty() {
+        return Err(Error::Custom(format!(
+            "None of {} recent events of {:?} is decoded by bridge ABI",
+            logs.len(),
+            contract
+        )));
+    }
+    if decoded < logs.len() {
+        warn!(
+            "[BSC Listener] - {} of {} recent events of {:?} aren't decoded",
+            logs.len() - decoded,
+            logs.len(),
+            contract
+        );
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn log(fixture: &str) -> Log {
+        let fixture = serde_json::from_str::<Value>(fixture).unwrap();
+        serde_json::from_value(fixture["log"].clone()).unwrap()
+    }
+
+    #[test]
+    fn sample_logs_must_be_decoded() {
+        let parser = TokenParser::new().unwrap();
+        let valid = log(include_str!("../tests/fixtures/token_to_realis.json"));
+        let contract = valid.address;
+        let mut broken = valid.clone();
+        broken.data.0.truncate(4);
+
+        assert_eq!(check_sample(&parser, contract, &[]).unwrap(), 0);
+        assert_eq!(check_sample(&parser, contract, &[valid.clone(), broken.clone()]).unwrap(), 1);
+        // Same topic is emitted with other ABI
+        assert!(check_sample(&parser, contract, &[broken]).is_err());
+        // Logs of other contract
+        assert!(check_sample(&parser, Address::repeat_byte(1), &[valid]).is_err());
+    }
 }
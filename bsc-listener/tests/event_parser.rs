@@ -0,0 +1,134 @@
+//! Bridge logs in `eth_getLogs` format are decoded with bundled contract ABIs
+//! and compared with expected transfers. Fixtures marked as synthetic aren't
+//! recorded from chain yet, see `fixtures/README.md`.
+
+use bsc_listener::event_parser::{EventParser, NftParser, ParseError, TokenParser};
+use primitives::events::bsc::BscEventType;
+use serde_json::Value;
+use std::fs;
+use web3::types::{Log, H160, H256, U64};
+
+fn fixture(name: &str) -> (Log, Value) {
+    let path = format!("{}/tests/fixtures/{}.json", env!("CARGO_MANIFEST_DIR"), name);
+    let mut fixture: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+
+    let log = serde_json::from_value(fixture["log"].take()).unwrap();
+    (log, fixture["expected"].take())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::from("0x"), |hex, byte| format!("{}{:02x}", hex, byte))
+}
+
+fn h160(value: &Value) -> H160 {
+    serde_json::from_value(value.clone()).unwrap()
+}
+
+fn h256(value: &Value) -> H256 {
+    serde_json::from_value(value.clone()).unwrap()
+}
+
+#[test]
+fn token_to_realis() {
+    let (log, expected) = fixture("token_to_realis");
+
+    let event = match TokenParser::new().unwrap().parse(&log).unwrap() {
+        BscEventType::TransferTokenToRealis(event) => event,
+        event => panic!("Unexpected event: {:?}", event),
+    };
+
+    assert_eq!(event.block, Some(U64::from(expected["block"].as_u64().unwrap())));
+    assert_eq!(event.hash, h256(&expected["hash"]));
+    assert_eq!(event.log_index, expected["log_index"].as_u64().unwrap());
+    assert_eq!(event.contract, log.address);
+    assert_eq!(event.from, h160(&expected["from"]));
+    assert_eq!(hex(event.to.as_ref()), expected["to"].as_str().unwrap());
+    assert_eq!(event.amount.to_string(), expected["amount"].as_str().unwrap());
+}
+
+#[test]
+fn nft_to_realis() {
+    let (log, expected) = fixture("nft_to_realis");
+
+    let event = match NftParser::new().unwrap().parse(&log).unwrap() {
+        BscEventType::TransferNftToRealis(event) => event,
+        event => panic!("Unexpected event: {:?}", event),
+    };
+
+    assert_eq!(event.block, Some(U64::from(expected["block"].as_u64().unwrap())));
+    assert_eq!(event.hash, h256(&expected["hash"]));
+    assert_eq!(event.log_index, expected["log_index"].as_u64().unwrap());
+    assert_eq!(event.contract, log.address);
+    assert_eq!(event.from, h160(&expected["from"]));
+    assert_eq!(hex(event.dest.as_ref()), expected["dest"].as_str().unwrap());
+    assert_eq!(event.token_id.to_string(), expected["token_id"].as_str().unwrap());
+}
+
+#[test]
+fn other_event_of_contract_is_rejected() {
+    let (log, _) = fixture("token_transfer");
+
+    assert!(matches!(
+        TokenParser::new().unwrap().parse(&log),
+        Err(ParseError::UnknownEvent(_))
+    ));
+}
+
+#[test]
+fn event_of_other_contract_is_rejected() {
+    let (log, _) = fixture("token_to_realis");
+
+    assert!(matches!(
+        NftParser::new().unwrap().parse(&log),
+        Err(ParseError::UnknownEvent(_))
+    ));
+}
+
+#[test]
+fn truncated_log_is_not_decoded() {
+    let (mut log, _) = fixture("token_to_realis");
+    log.data.0.truncate(64);
+
+    assert!(matches!(
+        TokenParser::new().unwrap().parse(&log),
+        Err(ParseError::DecodeError(..))
+    ));
+}
+
+#[test]
+fn topics_are_computed_from_abi() {
+    let (token_log, _) = fixture("token_to_realis");
+    let (nft_log, _) = fixture("nft_to_realis");
+
+    assert_eq!(TokenParser::new().unwrap().topic(), token_log.topics[0]);
+    assert_eq!(NftParser::new().unwrap().topic(), nft_log.topics[0]);
+}
+
+#[test]
+fn overridden_topic_is_decoded() {
+    let (mut log, _) = fixture("token_to_realis");
+    let topic = H256::repeat_byte(0x42);
+    log.topics[0] = topic;
+
+    let parser = TokenParser::new().unwrap().with_topic(topic);
+
+    assert_eq!(parser.topic(), topic);
+    assert!(matches!(parser.parse(&log), Ok(BscEventType::TransferTokenToRealis(_))));
+}
+
+#[test]
+#[ignore = "fixtures must be recorded from BSC testnet with capture.sh"]
+fn fixtures_are_recorded_from_chain() {
+    let dir = format!("{}/tests/fixtures", env!("CARGO_MANIFEST_DIR"));
+    let synthetic = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |extension| extension == "json"))
+        .filter(|path| {
+            let fixture: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+            !fixture["source"]["tx_hash"].is_string()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(synthetic.is_empty(), "Synthetic fixtures: {:?}", synthetic);
+}
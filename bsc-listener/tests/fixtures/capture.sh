@@ -0,0 +1,38 @@
+#!/bin/sh
+# Capture bridge log from BSC node into fixture, `expected` is filled by hand
+# from explorer and checked by tests/event_parser.rs.
+#
+# Usage: capture.sh <rpc url> <tx hash> <log index in tx> <fixture name> [network]
+# Example: capture.sh https://data-seed-prebsc-1-s1.binance.org:8545 0x... 0 token_to_realis bsc-testnet
+set -eu
+
+if [ $# -lt 4 ]; then
+    sed -n '5,6p' "$0" | cut -c 3-
+    exit 1
+fi
+url=$1
+tx_hash=$2
+index=$3
+name=$4
+network=${5:-bsc-testnet}
+
+receipt=$(curl -sf -H 'Content-Type: application/json' \
+    -d "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"eth_getTransactionReceipt\",\"params\":[\"$tx_hash\"]}" \
+    "$url")
+
+echo "$receipt" | jq --arg network "$network" --arg tx_hash "$tx_hash" --argjson index "$index" '
+    .result.logs[$index] as $log
+    | if $log == null then error("no log \($index) in \($tx_hash)") else . end
+    | {
+        source: { network: $network, tx_hash: $tx_hash, log_index: $index },
+        log: $log,
+        expected: {
+            block: ($log.blockNumber | ltrimstr("0x") | explode | reduce .[] as $c (0;
+                . * 16 + (if $c >= 97 then $c - 87 elif $c >= 65 then $c - 55 else $c - 48 end))),
+            hash: $log.transactionHash,
+            log_index: ($log.logIndex | ltrimstr("0x") | explode | reduce .[] as $c (0;
+                . * 16 + (if $c >= 97 then $c - 87 elif $c >= 65 then $c - 55 else $c - 48 end)))
+        }
+    }' > "$(dirname "$0")/$name.json"
+
+echo "Saved $name.json, fill rest of \`expected\` from explorer"
@@ -0,0 +1,149 @@
+-- name: 1.0-extrinsic-type
+CREATE TABLE types
+(
+    id   OID PRIMARY KEY,
+    name TEXT
+);
+
+-- name: 1.1-tokens
+INSERT INTO types (id, name)
+VALUES ('1', 'Tokens');
+
+-- name: 1.2-nft
+INSERT INTO types (id, name)
+VALUES ('2', 'Nft');
+
+-- name: 2.0-message-status
+CREATE TABLE request_status
+(
+    id   OID PRIMARY KEY,
+    name TEXT
+);
+
+-- name: 2.1-got
+INSERT INTO request_status (id, name)
+VALUES ('1', 'Got');
+
+-- name: 2.2-in-progress
+INSERT INTO request_status (id, name)
+VALUES ('2', 'InProgress');
+
+-- name: 2.3-success
+INSERT INTO request_status (id, name)
+VALUES ('3', 'Success');
+
+-- name: 2.4-error
+INSERT INTO request_status (id, name)
+VALUES ('4', 'Error');
+
+-- name: 2.5-rollback-success
+INSERT INTO request_status (id, name)
+VALUES ('5', 'RollbackSuccess');
+
+-- name: 2.6-rollback-error
+INSERT INTO request_status (id, name)
+VALUES ('6', 'RollbackError');
+
+-- name: 2.7-reorged
+INSERT INTO request_status (id, name)
+VALUES ('7', 'Reorged');
+
+-- name: 2.8-rollback-in-progress
+INSERT INTO request_status (id, name)
+VALUES ('8', 'RollbackInProgress');
+
+-- name: 2.9-unresolved
+INSERT INTO request_status (id, name)
+VALUES ('9', 'Unresolved');
+
+
+-- name: 3.1-extrinsics-realis
+CREATE TABLE extrinsics_realis
+(
+    id                  TEXT PRIMARY KEY,
+    hash                TEXT,
+    extrinsic_index     OID,
+    event_index         OID,
+    block               OID,
+    from_account        TEXT,
+    to_account          TEXT,
+    value               JSONB,
+    type                OID,
+    status              OID,
+    payload             JSONB,
+    submitted_tx_hash   TEXT,
+    submitted_raw_tx    TEXT,
+    submitted_nonce     OID,
+    submitted_block     OID,
+    dest_tx_hash        TEXT,
+    dest_block          OID,
+    dest_gas_used       TEXT,
+    dest_fee            TEXT,
+    dest_settled_at     TIMESTAMPTZ,
+    rollback_tx_hash    TEXT,
+    rollback_block      OID,
+    rollback_gas_used   TEXT,
+    rollback_fee        TEXT,
+    rollback_settled_at TIMESTAMPTZ,
+    CONSTRAINT fk_extrinsic_status_realis
+        FOREIGN KEY (status) REFERENCES request_status (id),
+    CONSTRAINT fk_extrinsic_type_realis
+        FOREIGN KEY (type) REFERENCES types (id)
+);
+
+-- name: 3.2-extrinsics-bsc
+CREATE TABLE extrinsics_bsc
+(
+    id                  TEXT PRIMARY KEY,
+    hash                TEXT,
+    log_index           OID,
+    block               OID,
+    from_account        TEXT,
+    to_account          TEXT,
+    value               JSONB,
+    type                OID,
+    status              OID,
+    payload             JSONB,
+    submitted_tx_hash   TEXT,
+    submitted_raw_tx    TEXT,
+    submitted_nonce     OID,
+    submitted_block     OID,
+    dest_tx_hash        TEXT,
+    dest_block          OID,
+    dest_gas_used       TEXT,
+    dest_fee            TEXT,
+    dest_settled_at     TIMESTAMPTZ,
+    rollback_tx_hash    TEXT,
+    rollback_block      OID,
+    rollback_gas_used   TEXT,
+    rollback_fee        TEXT,
+    rollback_settled_at TIMESTAMPTZ,
+    CONSTRAINT fk_extrinsic_status_bsc
+        FOREIGN KEY (status) REFERENCES request_status (id),
+    CONSTRAINT fk_extrinsic_type_bsc
+        FOREIGN KEY (type) REFERENCES types (id)
+);
+
+-- name: 4.1-blocks-realis
+CREATE TABLE blocks_realis
+(
+    block OID
+);
+
+-- name: 4.2-blocks-bsc
+CREATE TABLE blocks_bsc
+(
+    block       OID PRIMARY KEY,
+    hash        TEXT,
+    parent_hash TEXT
+);
+
+-- name: 5
+CREATE TABLE undecoded_events
+(
+    id        TEXT PRIMARY KEY,
+    block     OID,
+    hash      TEXT,
+    log_index OID,
+    data      BYTEA
+);
\ No newline at end of file
@@ -0,0 +1,96 @@
+-- Schema created by `tables.sql` before migrations were introduced
+-- is brought to the state of `0001_initial.sql`.
+
+-- name: 1-statuses
+INSERT INTO request_status (id, name)
+VALUES ('7', 'Reorged'),
+       ('8', 'RollbackInProgress'),
+       ('9', 'Unresolved')
+ON CONFLICT (id) DO NOTHING;
+
+-- name: 2-extrinsics-realis
+ALTER TABLE extrinsics_realis
+    RENAME COLUMN hash TO id;
+
+ALTER TABLE extrinsics_realis
+    ADD COLUMN hash                TEXT,
+    ADD COLUMN extrinsic_index     OID,
+    ADD COLUMN event_index         OID,
+    ADD COLUMN payload             JSONB,
+    ADD COLUMN submitted_tx_hash   TEXT,
+    ADD COLUMN submitted_raw_tx    TEXT,
+    ADD COLUMN submitted_nonce     OID,
+    ADD COLUMN submitted_block     OID,
+    ADD COLUMN dest_tx_hash        TEXT,
+    ADD COLUMN dest_block          OID,
+    ADD COLUMN dest_gas_used       TEXT,
+    ADD COLUMN dest_fee            TEXT,
+    ADD COLUMN dest_settled_at     TIMESTAMPTZ,
+    ADD COLUMN rollback_tx_hash    TEXT,
+    ADD COLUMN rollback_block      OID,
+    ADD COLUMN rollback_gas_used   TEXT,
+    ADD COLUMN rollback_fee        TEXT,
+    ADD COLUMN rollback_settled_at TIMESTAMPTZ;
+
+UPDATE extrinsics_realis
+SET hash = id;
+
+-- name: 3-extrinsics-bsc
+ALTER TABLE extrinsics_bsc
+    RENAME COLUMN hash TO id;
+
+ALTER TABLE extrinsics_bsc
+    ADD COLUMN hash                TEXT,
+    ADD COLUMN log_index           OID,
+    ADD COLUMN payload             JSONB,
+    ADD COLUMN submitted_tx_hash   TEXT,
+    ADD COLUMN submitted_raw_tx    TEXT,
+    ADD COLUMN submitted_nonce     OID,
+    ADD COLUMN submitted_block     OID,
+    ADD COLUMN dest_tx_hash        TEXT,
+    ADD COLUMN dest_block          OID,
+    ADD COLUMN dest_gas_used       TEXT,
+    ADD COLUMN dest_fee            TEXT,
+    ADD COLUMN dest_settled_at     TIMESTAMPTZ,
+    ADD COLUMN rollback_tx_hash    TEXT,
+    ADD COLUMN rollback_block      OID,
+    ADD COLUMN rollback_gas_used   TEXT,
+    ADD COLUMN rollback_fee        TEXT,
+    ADD COLUMN rollback_settled_at TIMESTAMPTZ;
+
+UPDATE extrinsics_bsc
+SET hash = id;
+
+-- name: 4-unfinished-transfers
+-- Old transfers have no payload to be sent again, unfinished ones are left for operator
+UPDATE extrinsics_realis
+SET status = '9'
+WHERE status IN ('1', '2', '4');
+
+UPDATE extrinsics_bsc
+SET status = '9'
+WHERE status IN ('1', '2', '4');
+
+-- name: 5-blocks-bsc
+-- Hashes of old blocks are unknown, they are never compared on reorganization
+DELETE
+FROM blocks_bsc old
+    USING blocks_bsc newer
+WHERE old.block = newer.block
+  AND old.ctid < newer.ctid;
+
+ALTER TABLE blocks_bsc
+    ADD COLUMN hash        TEXT,
+    ADD COLUMN parent_hash TEXT,
+    ADD PRIMARY KEY (block);
+
+-- name: 6-undecoded-events
+ALTER TABLE undecoded_events
+    RENAME COLUMN hash TO id;
+
+ALTER TABLE undecoded_events
+    ADD COLUMN hash      TEXT,
+    ADD COLUMN log_index OID;
+
+UPDATE undecoded_events
+SET hash = id;
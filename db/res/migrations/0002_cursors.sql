@@ -0,0 +1,30 @@
+-- name: 1-cursors
+CREATE TABLE cursors
+(
+    chain      TEXT PRIMARY KEY,
+    block      OID,
+    hash       TEXT,
+    updated_at TIMESTAMPTZ DEFAULT now()
+);
+
+-- name: 2-seed-cursors
+-- Listeners continue from last processed block, hash of Realis block wasn't stored
+INSERT INTO cursors (chain, block)
+SELECT 'realis', MAX(block)
+FROM blocks_realis
+HAVING MAX(block) IS NOT NULL;
+
+INSERT INTO cursors (chain, block, hash)
+SELECT 'bsc', block, hash
+FROM blocks_bsc
+ORDER BY block DESC
+LIMIT 1;
+
+-- name: 3-blocks-bsc
+-- Blocks without hash can't be compared on reorganization
+DELETE
+FROM blocks_bsc
+WHERE hash IS NULL;
+
+-- name: 4-blocks-realis
+DROP TABLE blocks_realis;
@@ -0,0 +1,11 @@
+-- name: 1-attestations
+CREATE TABLE attestations
+(
+    transfer_id TEXT,
+    chain       TEXT,
+    operator    TEXT,
+    digest      TEXT,
+    signature   TEXT,
+    created_at  TIMESTAMPTZ DEFAULT now(),
+    PRIMARY KEY (transfer_id, operator)
+);
@@ -0,0 +1,9 @@
+-- name: 1-nonces
+CREATE TABLE nonces
+(
+    chain      TEXT,
+    account    TEXT,
+    next       OID,
+    updated_at TIMESTAMPTZ DEFAULT now(),
+    PRIMARY KEY (chain, account)
+);
@@ -0,0 +1,13 @@
+-- name: 1-attempts
+CREATE TABLE attempts
+(
+    tx_hash     TEXT PRIMARY KEY,
+    transfer_id TEXT,
+    chain       TEXT,
+    nonce       OID,
+    gas_price   TEXT,
+    raw_tx      TEXT,
+    created_at  TIMESTAMPTZ DEFAULT now()
+);
+
+CREATE INDEX attempts_transfer_id ON attempts (transfer_id);
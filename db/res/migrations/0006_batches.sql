@@ -0,0 +1,3 @@
+-- name: 1-batches
+ALTER TABLE extrinsics_realis ADD COLUMN submitted_batch_index OID;
+ALTER TABLE extrinsics_bsc ADD COLUMN submitted_batch_index OID;
@@ -0,0 +1,23 @@
+-- name: 1-dead-letters
+CREATE TABLE dead_letters
+(
+    transfer_id TEXT PRIMARY KEY,
+    chain       TEXT,
+    payload     JSONB,
+    error       TEXT,
+    attempts    JSONB,
+    status      TEXT        DEFAULT 'open',
+    created_at  TIMESTAMPTZ DEFAULT now(),
+    updated_at  TIMESTAMPTZ DEFAULT now()
+);
+
+-- name: 2-audit-log
+CREATE TABLE audit_log
+(
+    id          SERIAL PRIMARY KEY,
+    transfer_id TEXT,
+    action      TEXT,
+    operator    TEXT,
+    reason      TEXT,
+    created_at  TIMESTAMPTZ DEFAULT now()
+);
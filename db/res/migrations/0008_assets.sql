@@ -0,0 +1,11 @@
+-- name: 1-assets
+CREATE TABLE assets
+(
+    contract     TEXT PRIMARY KEY,
+    kind         TEXT,
+    realis_asset TEXT,
+    decimals     OID,
+    enabled      BOOLEAN     DEFAULT true,
+    created_at   TIMESTAMPTZ DEFAULT now(),
+    updated_at   TIMESTAMPTZ DEFAULT now()
+);
@@ -0,0 +1,7 @@
+-- name: 1-bigint-nonces
+ALTER TABLE nonces ALTER COLUMN next TYPE BIGINT;
+ALTER TABLE attempts ALTER COLUMN nonce TYPE BIGINT;
+ALTER TABLE extrinsics_realis ALTER COLUMN submitted_nonce TYPE BIGINT;
+ALTER TABLE extrinsics_bsc ALTER COLUMN submitted_nonce TYPE BIGINT;
+
+CREATE INDEX attempts_chain_nonce ON attempts (chain, nonce);
@@ -0,0 +1,18 @@
+-- name: 1-attempt-errors
+ALTER TABLE attempts ADD COLUMN error TEXT;
+
+-- name: 2-dead-letter-attempts
+UPDATE dead_letters
+SET attempts = (
+    SELECT COALESCE(jsonb_agg(jsonb_build_object('tx_hash', hash, 'created_at', NULL, 'error', NULL)
+                    ORDER BY position), '[]'::jsonb)
+    FROM jsonb_array_elements_text(attempts) WITH ORDINALITY AS hashes(hash, position)
+)
+WHERE jsonb_typeof(attempts) = 'array';
+
+-- name: 3-halt-dead-lettered
+-- Rollbacks which failed before their claim stayed in Error (4) and were dead-lettered again on every tick
+UPDATE extrinsics_realis SET status = 6
+WHERE status = 4 AND id IN (SELECT transfer_id FROM dead_letters WHERE chain = 'realis' AND status = 'open');
+UPDATE extrinsics_bsc SET status = 6
+WHERE status = 4 AND id IN (SELECT transfer_id FROM dead_letters WHERE chain = 'bsc' AND status = 'open');
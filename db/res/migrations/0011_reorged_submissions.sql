@@ -0,0 +1,5 @@
+-- name: 1-reorged-submissions
+-- Transfers with submitted and not settled mint were flagged Reorged (7),
+-- they are back in progress (2), so their extrinsics are tracked and settled
+UPDATE extrinsics_bsc SET status = 2
+WHERE status = 7 AND submitted_tx_hash IS NOT NULL AND dest_tx_hash IS NULL;
@@ -1,7 +1,17 @@
+pub mod migrations;
+mod nonce_manager;
+mod queries;
+mod unit_of_work;
+
+pub use nonce_manager::NonceManager;
+pub use unit_of_work::{Committed, UnitOfWork};
+
 use primitives::{types::BlockNumber, Error};
 
 use primitives::{
-    db::Status,
+    asset::Asset,
+    attestation::Attestation,
+    db::{DeadLetter, Resolution, Settlement, Status, Submission},
     events::{bsc::BscEventType, realis::RealisEventType},
     types::RawEvent,
 };
@@ -9,10 +19,17 @@ use rust_lib::{
     healthchecker::HealthChecker,
     inner_db::{client_inner::DatabaseClientInner, client_inner_builder::DatabaseClientInnerBuilder},
 };
-use web3::ethabi::ethereum_types::U64;
+use serde_json::Value;
+use std::{convert::TryFrom, str::FromStr, time::Duration};
+use tokio::sync::Mutex;
+use tokio_postgres::Row;
+use web3::types::{Address, H256};
 
 pub struct Database {
     client: DatabaseClientInner,
+    /// Separate connection for transactions, so statements
+    /// of other tasks never get into a foreign transaction
+    transactions: Mutex<DatabaseClientInner>,
 }
 
 impl Database {
@@ -27,9 +44,16 @@ impl Database {
         ssl: bool,
         health: HealthChecker,
     ) -> Result<Self, tokio_postgres::Error> {
-        DatabaseClientInnerBuilder::build_with_params(host, port, user, password, dbname, ssl, health)
-            .await
-            .map(|client| Self { client })
+        let client =
+            DatabaseClientInnerBuilder::build_with_params(host, port, user, password, dbname, ssl, health.clone())
+                .await?;
+        let transactions =
+            DatabaseClientInnerBuilder::build_with_params(host, port, user, password, dbname, ssl, health).await?;
+
+        Ok(Self {
+            client,
+            transactions: Mutex::new(transactions),
+        })
     }
 
     /// # Panics
@@ -38,268 +62,870 @@ impl Database {
         self.client.still_alive().await.map_err(|_| Error::Disconnected)
     }
 
+    /// Bring database schema up to date with embedded migrations.
+    /// # Panics
+    /// # Errors
+    /// Fails if database schema is newer than this binary knows about.
+    pub async fn migrate(&self) -> Result<(), Error> {
+        self.still_alive().await?;
+
+        let mut inner = self.transactions.lock().await;
+        migrations::run(&mut inner.client).await
+    }
+
+    /// Store all writes of `work` in one transaction, nothing is stored if any of them fails.
+    /// # Panics
+    /// # Errors
+    pub async fn commit(&self, work: UnitOfWork) -> Result<Committed, Error> {
+        self.still_alive().await?;
+
+        let mut inner = self.transactions.lock().await;
+        let transaction = inner.client.transaction().await.map_err(Error::Postgres)?;
+        let committed = work.execute(&transaction).await?;
+        transaction.commit().await.map_err(Error::Postgres)?;
+
+        Ok(committed)
+    }
+
+    /// Returns `false` if this transfer already exists,
+    /// so it shouldn't be processed again.
+    /// # Panics
+    /// # Errors
+    pub async fn add_extrinsic_realis(&self, response: &RealisEventType) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        queries::insert_extrinsic_realis(&self.client.client, response).await
+    }
+
+    /// Returns `false` if this transfer already exists, so it shouldn't be
+    /// processed again. Transfer that was flagged as reorged comes back
+    /// to life if it appears again in canonical chain.
     /// # Panics
     /// # Errors
-    pub async fn import_tables_from_file(&self, path: &str) -> Result<(), Error> {
+    pub async fn add_extrinsic_bsc(&self, response: &BscEventType) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        queries::insert_extrinsic_bsc(&self.client.client, response).await
+    }
+
+    /// Returns last contiguously processed Realis block with its hash,
+    /// hash is unknown for cursor moved from blocks log of old schema.
+    /// # Panics
+    /// # Errors
+    pub async fn get_cursor_realis(&self) -> Result<Option<(BlockNumber, Option<String>)>, Error> {
+        self.get_cursor("realis").await
+    }
+
+    /// Returns last contiguously processed BSC block with its hash,
+    /// hash is unknown for cursor moved from blocks log of old schema.
+    /// # Panics
+    /// # Errors
+    pub async fn get_cursor_bsc(&self) -> Result<Option<(BlockNumber, Option<String>)>, Error> {
+        self.get_cursor("bsc").await
+    }
+
+    async fn get_cursor(&self, chain: &str) -> Result<Option<(BlockNumber, Option<String>)>, Error> {
         self.still_alive().await?;
 
         self.client
-            .import_tables_from_file(path)
+            .client
+            .query_opt("SELECT block, hash FROM cursors WHERE chain = $1", &[&chain])
             .await
-            .map_err(|error| Error::FileNotFound(format!("Error while load tables from file: {:?}", error)))
+            .map_err(Error::Postgres)?
+            .map(|row| {
+                Ok((
+                    u64::from(row.try_get::<_, u32>(0).map_err(Error::Postgres)?),
+                    row.try_get::<_, Option<String>>(1).map_err(Error::Postgres)?,
+                ))
+            })
+            .transpose()
     }
 
     /// # Panics
     /// # Errors
     #[allow(clippy::cast_possible_truncation)]
-    pub async fn add_extrinsic_realis(&self, response: &RealisEventType) -> Result<(), Error> {
-        self.still_alive().await?;
-
-        let status = Status::Got as u32;
-
-        match response {
-            RealisEventType::TransferNftToBsc(event) => {
-                let value = serde_json::to_value(&event.token_id).unwrap();
-                let types_nft = 2_u32;
-                let block = event.block as u32;
-                self.client
-                    .client
-                    .execute(
-                        "INSERT INTO extrinsics_realis(hash, block, \
-                        from_account, to_account, value, type, status) \
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
-                        &[
-                            &format!("{:?}", event.hash),
-                            &block,
-                            &event.from.to_string(),
-                            &format!("{:?}", event.dest),
-                            &value,
-                            &types_nft,
-                            &status,
-                        ],
-                    )
-                    .await
-                    .map_err(Error::Postgres)
-                    .map(|_| ())
-            }
-            RealisEventType::TransferTokenToBsc(event) => {
-                let value = serde_json::to_value(&event.amount.to_string()).unwrap();
-                let types_tokens = 1_u32;
-                let block = event.block as u32;
-                self.client
-                    .client
-                    .execute(
-                        "INSERT INTO extrinsics_realis(hash, block, \
-                        from_account, to_account, value, type, status) \
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
-                        &[
-                            &format!("{:?}", event.hash),
-                            &block,
-                            &event.from.to_string(),
-                            &format!("{:?}", event.to),
-                            &value,
-                            &types_tokens,
-                            &status,
-                        ],
-                    )
-                    .await
-                    .map_err(Error::Postgres)
-                    .map(|_| ())
-            }
-            RealisEventType::TransferTokenToRealisFail(_event) => Ok(()),
-            RealisEventType::TransferNftToRealisFail(_event) => Ok(()),
-        }
+    pub async fn get_block_hash_bsc(&self, block: BlockNumber) -> Result<Option<String>, Error> {
+        self.still_alive().await?;
+
+        let block = block as u32;
+
+        self.client
+            .client
+            .query_opt("SELECT hash FROM blocks_bsc WHERE block = $1", &[&block])
+            .await
+            .map_err(Error::Postgres)?
+            .map(|row| row.try_get::<_, String>(0).map_err(Error::Postgres))
+            .transpose()
     }
 
+    /// Highest block not above `at_most` which hash is stored, with its hash.
+    /// In logs scan mode hashes are stored only for some blocks of every range.
     /// # Panics
     /// # Errors
     #[allow(clippy::cast_possible_truncation)]
-    pub async fn add_extrinsic_bsc(&self, response: &BscEventType) -> Result<(), Error> {
-        self.still_alive().await?;
-        let status = Status::Got as u32;
-
-        match response {
-            BscEventType::TransferNftToRealis(event, ..) => {
-                let value = serde_json::to_value(&event.token_id).unwrap();
-                let types_nft = 2_u32;
-                let block = event.block.unwrap().as_u32();
-                self.client
-                    .client
-                    .execute(
-                        "INSERT INTO extrinsics_bsc(hash, block, \
-                        from_account, to_account, value, type, status) \
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
-                        &[
-                            &format!("{:?}", event.hash),
-                            &block,
-                            &format!("{:?}", event.from),
-                            &event.dest.to_string(),
-                            &value,
-                            &types_nft,
-                            &status,
-                        ],
-                    )
-                    .await
-                    .map_err(Error::Postgres)
-                    .map(|_| ())
-            }
-            BscEventType::TransferTokenToRealis(event, ..) => {
-                let value = serde_json::to_value(&event.amount.to_string()).unwrap();
-                let types_tokens = 1_u32;
-                let block: u32 = event.block.unwrap().as_u32();
-                self.client
-                    .client
-                    .execute(
-                        "INSERT INTO extrinsics_bsc(hash, block, \
-                        from_account, to_account, value, type, status) \
-                    VALUES ($1, $2, $3, $4, $5, $6, $7)",
-                        &[
-                            &format!("{:?}", event.hash),
-                            &block,
-                            &format!("{:?}", event.from),
-                            &event.to.to_string(),
-                            &value,
-                            &types_tokens,
-                            &status,
-                        ],
-                    )
-                    .await
-                    .map_err(Error::Postgres)
-                    .map(|_| ())
-            }
-            BscEventType::TransferTokenToBscFail(_event) => Ok(()),
-            BscEventType::TransferNftToBscFail(_event) => Ok(()),
-        }
+    pub async fn get_stored_block_bsc(
+        &self,
+        at_most: BlockNumber,
+    ) -> Result<Option<(BlockNumber, String)>, Error> {
+        self.still_alive().await?;
+
+        let at_most = at_most as u32;
+
+        self.client
+            .client
+            .query_opt(
+                "SELECT block, hash FROM blocks_bsc WHERE block <= $1 ORDER BY block DESC LIMIT 1",
+                &[&at_most],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .map(|row| {
+                let block = row.try_get::<_, u32>(0).map_err(Error::Postgres)?;
+                Ok((u64::from(block), row.try_get(1).map_err(Error::Postgres)?))
+            })
+            .transpose()
     }
 
+    /// Next not reserved nonce of `account` on `chain`.
     /// # Panics
     /// # Errors
-    pub async fn get_last_block_realis(&self) -> Result<BlockNumber, Error> {
+    pub async fn get_next_nonce(&self, chain: &str, account: &str) -> Result<Option<u64>, Error> {
         self.still_alive().await?;
 
-        let block_number_batch = self
-            .client
+        self.client
             .client
-            .query_one("SELECT max(block) FROM blocks_realis", &[])
+            .query_opt(
+                "SELECT next FROM nonces WHERE chain = $1 AND account = $2",
+                &[&chain, &account],
+            )
             .await
             .map_err(Error::Postgres)?
-            .try_get::<_, u32>(0)
+            .map(|row| row.try_get::<_, i64>(0).map_err(Error::Postgres).and_then(nonce_from_db))
+            .transpose()
+    }
+
+    /// # Panics
+    /// # Errors
+    pub async fn set_next_nonce(&self, chain: &str, account: &str, next: u64) -> Result<(), Error> {
+        self.still_alive().await?;
+
+        let next = nonce_to_db(next)?;
+
+        self.client
+            .client
+            .execute(
+                "INSERT INTO nonces(chain, account, next) \
+                    VALUES ($1, $2, $3) \
+                    ON CONFLICT (chain, account) DO UPDATE \
+                    SET next = EXCLUDED.next, updated_at = now()",
+                &[&chain, &account, &next],
+            )
+            .await
             .map_err(Error::Postgres)
-            .map(u64::from)?;
-        Ok(block_number_batch)
+            .map(|_| ())
     }
 
     /// # Panics
     /// # Errors
-    #[allow(clippy::cast_possible_truncation)]
-    pub async fn update_block_realis(&self, block: BlockNumber) -> Result<(), Error> {
+    pub async fn update_status_realis(&self, id: &str, status: Status) -> Result<(), Error> {
         self.still_alive().await?;
+        self.client
+            .client
+            .execute(
+                "UPDATE extrinsics_realis \
+                SET status = $1 \
+                WHERE id=$2",
+                &[&(status as u32), &id],
+            )
+            .await
+            .map(|_| ())
+            .map_err(Error::Postgres)
+    }
 
-        let block = block as u32;
+    /// # Panics
+    /// # Errors
+    pub async fn update_status_bsc(&self, id: &str, status: Status) -> Result<(), Error> {
+        self.still_alive().await?;
 
         self.client
             .client
             .execute(
-                "INSERT INTO blocks_realis(block) \
-                    VALUES ($1)",
-                &[&block],
+                "UPDATE extrinsics_bsc \
+                SET status = $1 \
+                WHERE id=$2",
+                &[&(status as u32), &id],
             )
             .await
+            .map(|_| ())
             .map_err(Error::Postgres)?;
-
         Ok(())
     }
 
+    /// Atomically move transfer from status `from` to status `to`.
+    /// Returns `false` if transfer is not in status `from`, it means
+    /// that transfer is already taken, settled or halted.
     /// # Panics
     /// # Errors
-    pub async fn get_last_block_bsc(&self) -> Result<BlockNumber, Error> {
+    pub async fn claim_realis(&self, id: &str, from: Status, to: Status) -> Result<bool, Error> {
         self.still_alive().await?;
 
-        let block_number_batch = self
+        self.client
             .client
+            .execute(
+                "UPDATE extrinsics_realis \
+                SET status = $1 \
+                WHERE id=$2 AND status=$3",
+                &[&(to as u32), &id, &(from as u32)],
+            )
+            .await
+            .map(|rows| rows > 0)
+            .map_err(Error::Postgres)
+    }
+
+    /// Atomically move transfer from status `from` to status `to`.
+    /// Returns `false` if transfer is not in status `from`, it means
+    /// that transfer is already taken, settled or halted.
+    /// # Panics
+    /// # Errors
+    pub async fn claim_bsc(&self, id: &str, from: Status, to: Status) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        self.client
             .client
-            .query_one("SELECT max(block) FROM blocks_bsc", &[])
+            .execute(
+                "UPDATE extrinsics_bsc \
+                SET status = $1 \
+                WHERE id=$2 AND status=$3",
+                &[&(to as u32), &id, &(from as u32)],
+            )
             .await
-            .map_err(Error::Postgres)?
-            .try_get::<_, u32>(0)
+            .map(|rows| rows > 0)
             .map_err(Error::Postgres)
-            .map(u64::from)?;
-        Ok(block_number_batch)
     }
 
+    /// Save status of transfer from Realis together with transaction
+    /// included in BSC, rollback transaction is saved for rollback statuses.
     /// # Panics
     /// # Errors
-    pub async fn update_block_bsc(&self, block: Option<U64>) -> Result<(), Error> {
+    pub async fn settle_realis(&self, id: &str, status: Status, settlement: &Settlement) -> Result<(), Error> {
+        self.settle("extrinsics_realis", id, status, settlement).await
+    }
+
+    /// Save status of transfer from BSC together with transaction
+    /// included in Realis, rollback transaction is saved for rollback statuses.
+    /// # Panics
+    /// # Errors
+    pub async fn settle_bsc(&self, id: &str, status: Status, settlement: &Settlement) -> Result<(), Error> {
+        self.settle("extrinsics_bsc", id, status, settlement).await
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    async fn settle(&self, table: &str, id: &str, status: Status, settlement: &Settlement) -> Result<(), Error> {
         self.still_alive().await?;
 
-        let block = block.unwrap().as_u32();
+        let prefix = match status {
+            Status::RollbackSuccess | Status::RollbackError => "rollback",
+            _ => "dest",
+        };
 
         self.client
             .client
             .execute(
-                "INSERT INTO blocks_bsc(block) \
-                    VALUES ($1)",
-                &[&block],
+                format!(
+                    "UPDATE {0} \
+                    SET status = $1, {1}_tx_hash = $2, {1}_block = $3, {1}_gas_used = $4, {1}_fee = $5, \
+                    {1}_settled_at = COALESCE(to_timestamp($7::BIGINT / 1000.0), now()) \
+                    WHERE id=$6",
+                    table, prefix
+                )
+                .as_str(),
+                &[
+                    &(status as u32),
+                    &settlement.tx_hash,
+                    &(settlement.block as u32),
+                    &settlement.gas_used,
+                    &settlement.fee,
+                    &id,
+                    &settlement.timestamp.map(|timestamp| timestamp as i64),
+                ],
             )
             .await
-            .map_err(Error::Postgres)?;
-        Ok(())
+            .map(|_| ())
+            .map_err(Error::Postgres)
     }
 
+    /// Save transaction signed for transfer before it is sent.
     /// # Panics
     /// # Errors
-    pub async fn update_status_realis(&self, hash: &str, status: Status) -> Result<(), Error> {
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn set_submission_realis(&self, id: &str, submission: &Submission) -> Result<(), Error> {
         self.still_alive().await?;
+
         self.client
             .client
             .execute(
                 "UPDATE extrinsics_realis \
-                SET status = $1 \
-                WHERE hash=$2",
-                &[&(status as u32), &hash],
+                SET submitted_tx_hash = $1, submitted_raw_tx = $2, submitted_nonce = $3, submitted_block = $4, \
+                submitted_batch_index = $5 \
+                WHERE id=$6",
+                &[
+                    &submission.tx_hash,
+                    &submission.raw,
+                    &nonce_to_db(submission.nonce)?,
+                    &(submission.block as u32),
+                    &submission.batch_index,
+                    &id,
+                ],
             )
             .await
             .map(|_| ())
             .map_err(Error::Postgres)
     }
 
+    /// Save transaction signed for transfer before it is sent.
     /// # Panics
     /// # Errors
-    pub async fn update_status_bsc(&self, hash: &str, status: Status) -> Result<(), Error> {
+    #[allow(clippy::cast_possible_truncation)]
+    pub async fn set_submission_bsc(&self, id: &str, submission: &Submission) -> Result<(), Error> {
         self.still_alive().await?;
 
         self.client
             .client
             .execute(
                 "UPDATE extrinsics_bsc \
-                SET status = $1 \
-                WHERE hash=$2",
-                &[&(status as u32), &hash.to_string()],
+                SET submitted_tx_hash = $1, submitted_raw_tx = $2, submitted_nonce = $3, submitted_block = $4, \
+                submitted_batch_index = $5 \
+                WHERE id=$6",
+                &[
+                    &submission.tx_hash,
+                    &submission.raw,
+                    &nonce_to_db(submission.nonce)?,
+                    &(submission.block as u32),
+                    &submission.batch_index,
+                    &id,
+                ],
             )
             .await
             .map(|_| ())
-            .map_err(Error::Postgres)?;
-        Ok(())
+            .map_err(Error::Postgres)
     }
 
+    /// Remember every transaction signed for transfer, replaced transaction
+    /// still can be mined instead of the last one.
+    /// Realis extrinsics have no gas price.
     /// # Panics
     /// # Errors
-    pub async fn add_raw_event(&self, raw_event: RawEvent) -> Result<(), Error> {
+    pub async fn add_attempt(
+        &self,
+        transfer_id: &str,
+        chain: &str,
+        submission: &Submission,
+        gas_price: Option<&str>,
+    ) -> Result<(), Error> {
         self.still_alive().await?;
 
         self.client
             .client
             .execute(
-                "INSERT INTO undecoded_events(block, hash, data) \
-            VALUES($1, $2, $3)",
+                "INSERT INTO attempts(tx_hash, transfer_id, chain, nonce, gas_price, raw_tx) \
+                VALUES ($1, $2, $3, $4, $5, $6) \
+                ON CONFLICT DO NOTHING",
                 &[
-                    &raw_event.block_number.unwrap().as_u32(),
-                    &format!("{:?}", raw_event.hash),
-                    &raw_event.data,
+                    &submission.tx_hash,
+                    &transfer_id,
+                    &chain,
+                    &nonce_to_db(submission.nonce)?,
+                    &gas_price,
+                    &submission.raw,
                 ],
             )
             .await
+            .map(|_| ())
             .map_err(Error::Postgres)
+    }
+
+    /// Remember why transaction was rejected or failed, operator sees it with dead letter.
+    /// # Panics
+    /// # Errors
+    pub async fn set_attempt_error(&self, tx_hash: &str, error: &str) -> Result<(), Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .execute("UPDATE attempts SET error = $2 WHERE tx_hash = $1", &[&tx_hash, &error])
+            .await
             .map(|_| ())
+            .map_err(Error::Postgres)
+    }
+
+    /// Hashes of all transactions signed for transfer, oldest first.
+    /// # Panics
+    /// # Errors
+    pub async fn get_attempts(&self, transfer_id: &str, chain: &str) -> Result<Vec<String>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT tx_hash FROM attempts WHERE transfer_id = $1 AND chain = $2 ORDER BY created_at",
+                &[&transfer_id, &chain],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(|row| row.try_get(0).map_err(Error::Postgres))
+            .collect()
+    }
+
+    /// Transfer ids and hashes of all transactions signed with `nonce`, oldest first.
+    /// Transfer which failed after ambiguous sending still could use the nonce.
+    /// # Panics
+    /// # Errors
+    pub async fn get_attempts_by_nonce(&self, chain: &str, nonce: u64) -> Result<Vec<(String, String)>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT transfer_id, tx_hash FROM attempts WHERE chain = $1 AND nonce = $2 ORDER BY created_at",
+                &[&chain, &nonce_to_db(nonce)?],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(|row| Ok((row.try_get(0).map_err(Error::Postgres)?, row.try_get(1).map_err(Error::Postgres)?)))
+            .collect()
     }
+
+    /// Load ids of transfers from Realis in given status
+    /// with last transaction sent for them, if any.
+    /// # Panics
+    /// # Errors
+    pub async fn get_submissions_realis(
+        &self,
+        status: Status,
+    ) -> Result<Vec<(String, Option<Submission>)>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT id, submitted_tx_hash, submitted_raw_tx, submitted_nonce, submitted_block, \
+                submitted_batch_index \
+                FROM extrinsics_realis WHERE status=$1",
+                &[&(status as u32)],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(Self::read_submission)
+            .collect()
+    }
+
+    /// Load ids of transfers from BSC in given status
+    /// with last transaction sent for them, if any.
+    /// # Panics
+    /// # Errors
+    pub async fn get_submissions_bsc(&self, status: Status) -> Result<Vec<(String, Option<Submission>)>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT id, submitted_tx_hash, submitted_raw_tx, submitted_nonce, submitted_block, \
+                submitted_batch_index \
+                FROM extrinsics_bsc WHERE status=$1",
+                &[&(status as u32)],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(Self::read_submission)
+            .collect()
+    }
+
+    fn read_submission(row: &Row) -> Result<(String, Option<Submission>), Error> {
+        let id = row.try_get::<_, String>(0).map_err(Error::Postgres)?;
+        let tx_hash = row.try_get::<_, Option<String>>(1).map_err(Error::Postgres)?;
+        let raw = row.try_get::<_, Option<String>>(2).map_err(Error::Postgres)?;
+        let nonce = row
+            .try_get::<_, Option<i64>>(3)
+            .map_err(Error::Postgres)?
+            .map(nonce_from_db)
+            .transpose()?;
+        let block = row.try_get::<_, Option<u32>>(4).map_err(Error::Postgres)?;
+        let batch_index = row.try_get::<_, Option<u32>>(5).map_err(Error::Postgres)?;
+
+        let submission = match (tx_hash, raw, nonce, block) {
+            (Some(tx_hash), Some(raw), Some(nonce), Some(block)) => Some(Submission {
+                tx_hash,
+                raw,
+                nonce,
+                block: u64::from(block),
+                batch_index,
+            }),
+            _ => None,
+        };
+
+        Ok((id, submission))
+    }
+
+    /// Load transfers from Realis in given status, oldest first.
+    /// # Panics
+    /// # Errors
+    pub async fn get_transfers_realis(&self, status: Status) -> Result<Vec<RealisEventType>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT type, payload FROM extrinsics_realis WHERE status=$1 ORDER BY block",
+                &[&(status as u32)],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .into_iter()
+            .map(|row| {
+                let types = row.try_get::<_, u32>(0).map_err(Error::Postgres)?;
+                let payload = row.try_get::<_, Value>(1).map_err(Error::Postgres)?;
+                match types {
+                    1 => serde_json::from_value(payload).map(RealisEventType::TransferTokenToBsc),
+                    2 => serde_json::from_value(payload).map(RealisEventType::TransferNftToBsc),
+                    types => return Err(Error::Custom(format!("Unknown transfer type: {}", types))),
+                }
+                .map_err(Error::SerdeJSON)
+            })
+            .collect()
+    }
+
+    /// Load transfers from BSC in given status, oldest first.
+    /// # Panics
+    /// # Errors
+    pub async fn get_transfers_bsc(&self, status: Status) -> Result<Vec<BscEventType>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT type, payload FROM extrinsics_bsc WHERE status=$1 ORDER BY block, log_index",
+                &[&(status as u32)],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .into_iter()
+            .map(|row| {
+                let types = row.try_get::<_, u32>(0).map_err(Error::Postgres)?;
+                let payload = row.try_get::<_, Value>(1).map_err(Error::Postgres)?;
+                match types {
+                    1 => serde_json::from_value(payload).map(BscEventType::TransferTokenToRealis),
+                    2 => serde_json::from_value(payload).map(BscEventType::TransferNftToRealis),
+                    types => return Err(Error::Custom(format!("Unknown transfer type: {}", types))),
+                }
+                .map_err(Error::SerdeJSON)
+            })
+            .collect()
+    }
+
+    /// # Panics
+    /// # Errors
+    pub async fn get_status_bsc(&self, id: &str) -> Result<Option<Status>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query_opt("SELECT status FROM extrinsics_bsc WHERE id=$1", &[&id])
+            .await
+            .map_err(Error::Postgres)?
+            .map(|row| {
+                row.try_get::<_, u32>(0)
+                    .map_err(Error::Postgres)
+                    .and_then(Status::try_from)
+            })
+            .transpose()
+    }
+
+    /// # Panics
+    /// # Errors
+    pub async fn add_raw_event(&self, raw_event: RawEvent) -> Result<(), Error> {
+        self.still_alive().await?;
+
+        queries::insert_raw_event(&self.client.client, &raw_event).await
+    }
+
+    /// All attestations of transfer, they aren't verified here.
+    /// # Panics
+    /// # Errors
+    pub async fn get_attestations(&self, transfer_id: &str) -> Result<Vec<Attestation>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT chain, operator, digest, signature FROM attestations WHERE transfer_id = $1",
+                &[&transfer_id],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(|row| {
+                let parse =
+                    |error: String| Error::Custom(format!("Invalid attestation of {}: {}", transfer_id, error));
+                Ok(Attestation {
+                    transfer_id: String::from(transfer_id),
+                    chain: row.try_get(0).map_err(Error::Postgres)?,
+                    operator: Address::from_str(row.try_get(1).map_err(Error::Postgres)?)
+                        .map_err(|error| parse(format!("{:?}", error)))?,
+                    digest: H256::from_str(row.try_get(2).map_err(Error::Postgres)?)
+                        .map_err(|error| parse(format!("{:?}", error)))?,
+                    signature: hex::decode(row.try_get::<_, &str>(3).map_err(Error::Postgres)?)
+                        .map_err(|error| parse(format!("{:?}", error)))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Time since first attestation of transfer by database clock, so operators agree on it.
+    /// `None` if transfer isn't attested yet.
+    /// # Panics
+    /// # Errors
+    pub async fn get_attestation_age(&self, transfer_id: &str) -> Result<Option<Duration>, Error> {
+        self.still_alive().await?;
+
+        let row = self
+            .client
+            .client
+            .query_one(
+                "SELECT EXTRACT(EPOCH FROM now() - MIN(created_at))::BIGINT FROM attestations \
+                WHERE transfer_id = $1",
+                &[&transfer_id],
+            )
+            .await
+            .map_err(Error::Postgres)?;
+
+        let age = row.try_get::<_, Option<i64>>(0).map_err(Error::Postgres)?;
+        Ok(age.map(|age| Duration::from_secs(u64::try_from(age).unwrap_or_default())))
+    }
+
+    /// Keep transfer which rollback failed for operator, with payload and sent transactions.
+    /// # Panics
+    /// # Errors
+    pub async fn add_dead_letter_realis(&self, transfer_id: &str, error: &str) -> Result<(), Error> {
+        self.add_dead_letter(transfer_id, "realis", error).await
+    }
+
+    /// Keep transfer which rollback failed for operator, with payload and sent transactions.
+    /// # Panics
+    /// # Errors
+    pub async fn add_dead_letter_bsc(&self, transfer_id: &str, error: &str) -> Result<(), Error> {
+        self.add_dead_letter(transfer_id, "bsc", error).await
+    }
+
+    /// Rollback which failed before it was claimed leaves transfer in `Error`,
+    /// it is moved to `RollbackError` in the same transaction, so outbox doesn't load it again.
+    /// Letter which operator completed or cancelled is never reopened, retried one is.
+    async fn add_dead_letter(&self, transfer_id: &str, chain: &str, error: &str) -> Result<(), Error> {
+        self.still_alive().await?;
+
+        let table = match chain {
+            "realis" => "extrinsics_realis",
+            "bsc" => "extrinsics_bsc",
+            chain => return Err(Error::Custom(format!("Unknown chain of dead letter: {}", chain))),
+        };
+
+        let mut inner = self.transactions.lock().await;
+        let transaction = inner.client.transaction().await.map_err(Error::Postgres)?;
+
+        transaction
+            .execute(
+                format!(
+                    "INSERT INTO dead_letters(transfer_id, chain, payload, error, attempts) \
+                    SELECT id, $2, payload, $3, COALESCE( \
+                        (SELECT jsonb_agg(jsonb_build_object( \
+                            'tx_hash', tx_hash, 'created_at', created_at, 'error', error) ORDER BY created_at) \
+                        FROM attempts WHERE transfer_id = $1), \
+                        CASE WHEN submitted_tx_hash IS NULL THEN '[]'::jsonb \
+                        ELSE jsonb_build_array(jsonb_build_object( \
+                            'tx_hash', submitted_tx_hash, 'created_at', NULL, 'error', NULL)) END) \
+                    FROM {} WHERE id = $1 \
+                    ON CONFLICT (transfer_id) DO UPDATE \
+                    SET error = EXCLUDED.error, attempts = EXCLUDED.attempts, status = 'open', updated_at = now() \
+                    WHERE dead_letters.status IN ('open', 'retry')",
+                    table
+                )
+                .as_str(),
+                &[&transfer_id, &chain, &error],
+            )
+            .await
+            .map_err(Error::Postgres)?;
+        transaction
+            .execute(
+                format!("UPDATE {} SET status = $1 WHERE id = $2 AND status = $3", table).as_str(),
+                &[&(Status::RollbackError as u32), &transfer_id, &(Status::Error as u32)],
+            )
+            .await
+            .map_err(Error::Postgres)?;
+
+        transaction.commit().await.map_err(Error::Postgres)
+    }
+
+    /// Dead-lettered transfers which wait for operator, oldest first.
+    /// # Panics
+    /// # Errors
+    pub async fn get_dead_letters(&self) -> Result<Vec<DeadLetter>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT transfer_id, chain, payload, error, attempts FROM dead_letters \
+                WHERE status = 'open' ORDER BY created_at",
+                &[],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(|row| {
+                let attempts = row.try_get::<_, Value>(4).map_err(Error::Postgres)?;
+                Ok(DeadLetter {
+                    transfer_id: row.try_get(0).map_err(Error::Postgres)?,
+                    chain: row.try_get(1).map_err(Error::Postgres)?,
+                    payload: row.try_get(2).map_err(Error::Postgres)?,
+                    error: row.try_get(3).map_err(Error::Postgres)?,
+                    attempts: serde_json::from_value(attempts).map_err(Error::SerdeJSON)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Apply operator decision to dead-lettered transfer and record it in audit log,
+    /// all in one transaction. Returns `false` if there is no open dead letter for transfer.
+    /// # Panics
+    /// # Errors
+    pub async fn resolve_dead_letter(
+        &self,
+        transfer_id: &str,
+        resolution: Resolution,
+        operator: &str,
+        reason: &str,
+    ) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        let mut inner = self.transactions.lock().await;
+        let transaction = inner.client.transaction().await.map_err(Error::Postgres)?;
+
+        let chain = match transaction
+            .query_opt(
+                "UPDATE dead_letters SET status = $2, updated_at = now() \
+                WHERE transfer_id = $1 AND status = 'open' RETURNING chain",
+                &[&transfer_id, &resolution.as_str()],
+            )
+            .await
+            .map_err(Error::Postgres)?
+        {
+            Some(row) => row.try_get::<_, String>(0).map_err(Error::Postgres)?,
+            None => return Ok(false),
+        };
+
+        let query = match chain.as_str() {
+            "realis" => "UPDATE extrinsics_realis SET status = $1 WHERE id = $2",
+            "bsc" => "UPDATE extrinsics_bsc SET status = $1 WHERE id = $2",
+            chain => return Err(Error::Custom(format!("Unknown chain of dead letter: {}", chain))),
+        };
+        transaction
+            .execute(query, &[&(resolution.status() as u32), &transfer_id])
+            .await
+            .map_err(Error::Postgres)?;
+        transaction
+            .execute(
+                "INSERT INTO audit_log(transfer_id, action, operator, reason) VALUES ($1, $2, $3, $4)",
+                &[&transfer_id, &resolution.as_str(), &operator, &reason],
+            )
+            .await
+            .map_err(Error::Postgres)?;
+
+        transaction.commit().await.map_err(Error::Postgres)?;
+
+        Ok(true)
+    }
+
+    /// All registered assets, enabled and disabled.
+    /// # Panics
+    /// # Errors
+    pub async fn get_assets(&self) -> Result<Vec<Asset>, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .query(
+                "SELECT contract, kind, realis_asset, decimals, enabled FROM assets ORDER BY created_at",
+                &[],
+            )
+            .await
+            .map_err(Error::Postgres)?
+            .iter()
+            .map(|row| {
+                let contract = row.try_get::<_, String>(0).map_err(Error::Postgres)?;
+                let kind = row.try_get::<_, String>(1).map_err(Error::Postgres)?;
+                let decimals = row.try_get::<_, u32>(3).map_err(Error::Postgres)?;
+                Ok(Asset {
+                    contract: Address::from_str(&contract).map_err(|error| Error::Custom(format!("{:?}", error)))?,
+                    kind: kind.parse().map_err(Error::Custom)?,
+                    realis_asset: row.try_get(2).map_err(Error::Postgres)?,
+                    decimals: u8::try_from(decimals).map_err(|_| Error::CannotDecode)?,
+                    enabled: row.try_get(4).map_err(Error::Postgres)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Register asset, returns `false` if its contract is already registered.
+    /// # Panics
+    /// # Errors
+    pub async fn add_asset(&self, asset: &Asset) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .execute(
+                "INSERT INTO assets(contract, kind, realis_asset, decimals, enabled) \
+                VALUES ($1, $2, $3, $4, $5) \
+                ON CONFLICT (contract) DO NOTHING",
+                &[
+                    &format!("{:?}", asset.contract),
+                    &asset.kind.as_str(),
+                    &asset.realis_asset,
+                    &u32::from(asset.decimals),
+                    &asset.enabled,
+                ],
+            )
+            .await
+            .map_err(Error::Postgres)
+            .map(|rows| rows > 0)
+    }
+
+    /// Returns `false` if contract isn't registered.
+    /// # Panics
+    /// # Errors
+    pub async fn set_asset_enabled(&self, contract: &Address, enabled: bool) -> Result<bool, Error> {
+        self.still_alive().await?;
+
+        self.client
+            .client
+            .execute(
+                "UPDATE assets SET enabled = $2, updated_at = now() WHERE contract = $1",
+                &[&format!("{:?}", contract), &enabled],
+            )
+            .await
+            .map_err(Error::Postgres)
+            .map(|rows| rows > 0)
+    }
+}
+
+/// Nonces are stored as `BIGINT`, which is signed.
+fn nonce_to_db(nonce: u64) -> Result<i64, Error> {
+    i64::try_from(nonce).map_err(|_| Error::Custom(format!("Nonce {} doesn't fit into database", nonce)))
+}
+
+fn nonce_from_db(nonce: i64) -> Result<u64, Error> {
+    u64::try_from(nonce).map_err(|_| Error::Custom(format!("Negative nonce {} in database", nonce)))
 }
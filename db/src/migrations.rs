@@ -0,0 +1,157 @@
+use log::info;
+use primitives::Error;
+use tokio_postgres::{Client, Transaction};
+
+/// Schema migration embedded into binary.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub sql: &'static str,
+}
+
+/// All known migrations in order they must be applied,
+/// new migration is added to the end with next version.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "initial",
+        sql: include_str!("../res/migrations/0001_initial.sql"),
+    },
+    Migration {
+        version: 2,
+        name: "cursors",
+        sql: include_str!("../res/migrations/0002_cursors.sql"),
+    },
+    Migration {
+        version: 3,
+        name: "attestations",
+        sql: include_str!("../res/migrations/0003_attestations.sql"),
+    },
+    Migration {
+        version: 4,
+        name: "nonces",
+        sql: include_str!("../res/migrations/0004_nonces.sql"),
+    },
+    Migration {
+        version: 5,
+        name: "attempts",
+        sql: include_str!("../res/migrations/0005_attempts.sql"),
+    },
+    Migration {
+        version: 6,
+        name: "batches",
+        sql: include_str!("../res/migrations/0006_batches.sql"),
+    },
+    Migration {
+        version: 7,
+        name: "dead_letters",
+        sql: include_str!("../res/migrations/0007_dead_letters.sql"),
+    },
+    Migration {
+        version: 8,
+        name: "assets",
+        sql: include_str!("../res/migrations/0008_assets.sql"),
+    },
+    Migration {
+        version: 9,
+        name: "bigint_nonces",
+        sql: include_str!("../res/migrations/0009_bigint_nonces.sql"),
+    },
+    Migration {
+        version: 10,
+        name: "attempt_errors",
+        sql: include_str!("../res/migrations/0010_attempt_errors.sql"),
+    },
+    Migration {
+        version: 11,
+        name: "reorged_submissions",
+        sql: include_str!("../res/migrations/0011_reorged_submissions.sql"),
+    },
+];
+
+/// Key of advisory lock held while schema is migrated, same for every bridge instance.
+const LOCK: i64 = 0x0b51_2ea1;
+
+/// Brings schema created before migrations were introduced to version 1.
+const BASELINE_UPGRADE: &str = include_str!("../res/migrations/0001_initial_from_baseline.sql");
+
+/// Version of latest known migration.
+#[must_use]
+pub fn latest() -> u32 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// Apply all migrations newer than current schema version.
+/// Migrations run in one transaction together with their version records, nothing is applied if some fails.
+/// Operators sharing database migrate it on start, so transaction takes advisory lock first:
+/// one of them applies migrations, others wait and find schema up to date.
+/// # Errors
+/// Fails if schema is newer than this binary knows about or if some migration fails.
+pub async fn run(client: &mut Client) -> Result<(), Error> {
+    let transaction = client.transaction().await.map_err(Error::Postgres)?;
+    transaction
+        .execute("SELECT pg_advisory_xact_lock($1)", &[&LOCK])
+        .await
+        .map_err(Error::Postgres)?;
+
+    transaction
+        .batch_execute(
+            "CREATE TABLE IF NOT EXISTS schema_migrations \
+            ( \
+                version    OID PRIMARY KEY, \
+                name       TEXT, \
+                applied_at TIMESTAMPTZ DEFAULT now() \
+            )",
+        )
+        .await
+        .map_err(Error::Postgres)?;
+
+    let mut current = current(&transaction).await?;
+    if current == 0 && has_baseline(&transaction).await? {
+        info!("[Database] - upgrade schema created before migrations");
+        apply(&transaction, BASELINE_UPGRADE, &MIGRATIONS[0]).await?;
+        current = MIGRATIONS[0].version;
+    }
+    if current > latest() {
+        return Err(Error::UnknownSchema(current, latest()));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        info!("[Database] - apply migration {} - {}", migration.version, migration.name);
+        apply(&transaction, migration.sql, migration).await?;
+    }
+
+    transaction.commit().await.map_err(Error::Postgres)
+}
+
+/// Store `sql` of `migration` together with its version record.
+async fn apply(transaction: &Transaction<'_>, sql: &str, migration: &Migration) -> Result<(), Error> {
+    transaction
+        .batch_execute(&format!(
+            "{};\nINSERT INTO schema_migrations(version, name) VALUES ({}, '{}');",
+            sql.trim_end().trim_end_matches(';'),
+            migration.version,
+            migration.name
+        ))
+        .await
+        .map_err(Error::Postgres)
+}
+
+/// Tables of first version exist, but no migration was recorded.
+async fn has_baseline(transaction: &Transaction<'_>) -> Result<bool, Error> {
+    let row = transaction
+        .query_one("SELECT to_regclass('types') IS NOT NULL", &[])
+        .await
+        .map_err(Error::Postgres)?;
+
+    row.try_get::<_, bool>(0).map_err(Error::Postgres)
+}
+
+async fn current(transaction: &Transaction<'_>) -> Result<u32, Error> {
+    let row = transaction
+        .query_one("SELECT COALESCE(MAX(version), 0::OID) FROM schema_migrations", &[])
+        .await
+        .map_err(Error::Postgres)?;
+
+    row.try_get::<_, u32>(0).map_err(Error::Postgres)
+}
@@ -0,0 +1,101 @@
+use crate::Database;
+use primitives::Error;
+use std::{collections::BTreeSet, sync::Arc};
+use tokio::sync::Mutex;
+
+#[derive(Default)]
+struct State {
+    /// Next nonce which wasn't reserved, loaded on first use
+    next: Option<u64>,
+    /// Reserved nonces which transactions are signed or sent right now
+    in_use: BTreeSet<u64>,
+}
+
+/// Reserves nonces of bridge account locally, so next transaction is sent
+/// without waiting for previous one. Next nonce is persisted in database,
+/// so after restart nonces of not yet included transactions aren't reused.
+///
+/// Manager doesn't talk to blockchain, adapter passes account nonce known to node.
+pub struct NonceManager {
+    db: Arc<Database>,
+    /// Name of chain in `nonces` table
+    chain: &'static str,
+    account: String,
+    state: Mutex<State>,
+}
+
+impl NonceManager {
+    #[must_use]
+    pub fn new(db: Arc<Database>, chain: &'static str, account: String) -> Self {
+        Self {
+            db,
+            chain,
+            account,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Reserve next nonce, `known` is account nonce including pending transactions,
+    /// it is taken into account in case account was used outside of bridge.
+    /// # Errors
+    pub async fn reserve(&self, known: u64) -> Result<u64, Error> {
+        let mut state = self.state.lock().await;
+
+        let nonce = self.load(&mut state).await?.max(known);
+        let next = nonce
+            .checked_add(1)
+            .ok_or_else(|| Error::Custom(format!("Nonce of {} overflows", self.account)))?;
+
+        self.db.set_next_nonce(self.chain, &self.account, next).await?;
+        state.next = Some(next);
+        state.in_use.insert(nonce);
+
+        Ok(nonce)
+    }
+
+    /// Give back nonce of transaction which was never sent.
+    /// Only last reserved nonce can be reused, others become gaps.
+    /// # Errors
+    pub async fn release(&self, nonce: u64) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+
+        state.in_use.remove(&nonce);
+        if state.next == nonce.checked_add(1) {
+            self.db.set_next_nonce(self.chain, &self.account, nonce).await?;
+            state.next = Some(nonce);
+        }
+
+        Ok(())
+    }
+
+    /// Handling of transaction is finished, it is sent, included or left for tracking.
+    pub async fn complete(&self, nonce: u64) {
+        self.state.lock().await.in_use.remove(&nonce);
+    }
+
+    /// Reserved nonces from `included` (account nonce in latest block) up,
+    /// which aren't handled right now. Every such nonce blocks all later transactions of account.
+    /// # Errors
+    pub async fn gaps(&self, included: u64) -> Result<Vec<u64>, Error> {
+        let mut state = self.state.lock().await;
+        let next = self.load(&mut state).await?;
+
+        Ok((included..next).filter(|nonce| !state.in_use.contains(nonce)).collect())
+    }
+
+    /// Next nonce is read from database once, then it is kept in memory
+    async fn load(&self, state: &mut State) -> Result<u64, Error> {
+        if let Some(next) = state.next {
+            return Ok(next);
+        }
+
+        let next = self
+            .db
+            .get_next_nonce(self.chain, &self.account)
+            .await?
+            .unwrap_or_default();
+        state.next = Some(next);
+
+        Ok(next)
+    }
+}
@@ -0,0 +1,303 @@
+//! Statements shared by single-shot methods of `Database` and `UnitOfWork`,
+//! generic over client so they can run inside a transaction.
+
+use primitives::{
+    attestation::Attestation,
+    db::Status,
+    events::{bsc::BscEventType, realis::RealisEventType, traits::Event},
+    types::{BlockNumber, RawEvent},
+    Error,
+};
+use tokio_postgres::GenericClient;
+use web3::ethabi::ethereum_types::{H256, U64};
+
+#[allow(clippy::cast_possible_truncation)]
+pub async fn insert_extrinsic_realis<C: GenericClient>(
+    client: &C,
+    response: &RealisEventType,
+) -> Result<bool, Error> {
+    let status = Status::Got as u32;
+
+    match response {
+        RealisEventType::TransferNftToBsc(event) => {
+            let value = serde_json::to_value(&event.token_id).unwrap();
+            let types_nft = 2_u32;
+            let block = event.block as u32;
+            client
+                .execute(
+                    "INSERT INTO extrinsics_realis(id, hash, extrinsic_index, event_index, block, \
+                    from_account, to_account, value, type, status, payload) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+                ON CONFLICT (id) DO NOTHING",
+                    &[
+                        &event.get_hash(),
+                        &format!("{:?}", event.hash),
+                        &event.extrinsic_index,
+                        &event.event_index,
+                        &block,
+                        &event.from.to_string(),
+                        &format!("{:?}", event.dest),
+                        &value,
+                        &types_nft,
+                        &status,
+                        &serde_json::to_value(event).map_err(Error::SerdeJSON)?,
+                    ],
+                )
+                .await
+                .map_err(Error::Postgres)
+                .map(|rows| rows > 0)
+        }
+        RealisEventType::TransferTokenToBsc(event) => {
+            let value = serde_json::to_value(&event.amount.to_string()).unwrap();
+            let types_tokens = 1_u32;
+            let block = event.block as u32;
+            client
+                .execute(
+                    "INSERT INTO extrinsics_realis(id, hash, extrinsic_index, event_index, block, \
+                    from_account, to_account, value, type, status, payload) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+                ON CONFLICT (id) DO NOTHING",
+                    &[
+                        &event.get_hash(),
+                        &format!("{:?}", event.hash),
+                        &event.extrinsic_index,
+                        &event.event_index,
+                        &block,
+                        &event.from.to_string(),
+                        &format!("{:?}", event.to),
+                        &value,
+                        &types_tokens,
+                        &status,
+                        &serde_json::to_value(event).map_err(Error::SerdeJSON)?,
+                    ],
+                )
+                .await
+                .map_err(Error::Postgres)
+                .map(|rows| rows > 0)
+        }
+        RealisEventType::TransferTokenToRealisFail(_event) => Ok(false),
+        RealisEventType::TransferNftToRealisFail(_event) => Ok(false),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub async fn insert_extrinsic_bsc<C: GenericClient>(client: &C, response: &BscEventType) -> Result<bool, Error> {
+    let status = Status::Got as u32;
+    let reorged = Status::Reorged as u32;
+
+    match response {
+        BscEventType::TransferNftToRealis(event, ..) => {
+            let value = serde_json::to_value(&event.token_id).unwrap();
+            let types_nft = 2_u32;
+            let block = pending_block(event.block)?;
+            let log_index = event.log_index as u32;
+            client
+                .execute(
+                    "INSERT INTO extrinsics_bsc(id, hash, log_index, block, \
+                    from_account, to_account, value, type, status, payload) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $11) \
+                ON CONFLICT (id) DO UPDATE \
+                SET block = EXCLUDED.block, status = EXCLUDED.status, payload = EXCLUDED.payload \
+                WHERE extrinsics_bsc.status = $10 \
+                AND (extrinsics_bsc.submitted_tx_hash IS NULL OR extrinsics_bsc.dest_tx_hash IS NOT NULL)",
+                    &[
+                        &event.get_hash(),
+                        &format!("{:?}", event.hash),
+                        &log_index,
+                        &block,
+                        &format!("{:?}", event.from),
+                        &event.dest.to_string(),
+                        &value,
+                        &types_nft,
+                        &status,
+                        &reorged,
+                        &serde_json::to_value(event).map_err(Error::SerdeJSON)?,
+                    ],
+                )
+                .await
+                .map_err(Error::Postgres)
+                .map(|rows| rows > 0)
+        }
+        BscEventType::TransferTokenToRealis(event, ..) => {
+            let value = serde_json::to_value(&event.amount.to_string()).unwrap();
+            let types_tokens = 1_u32;
+            let block = pending_block(event.block)?;
+            let log_index = event.log_index as u32;
+            client
+                .execute(
+                    "INSERT INTO extrinsics_bsc(id, hash, log_index, block, \
+                    from_account, to_account, value, type, status, payload) \
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $11) \
+                ON CONFLICT (id) DO UPDATE \
+                SET block = EXCLUDED.block, status = EXCLUDED.status, payload = EXCLUDED.payload \
+                WHERE extrinsics_bsc.status = $10 \
+                AND (extrinsics_bsc.submitted_tx_hash IS NULL OR extrinsics_bsc.dest_tx_hash IS NOT NULL)",
+                    &[
+                        &event.get_hash(),
+                        &format!("{:?}", event.hash),
+                        &log_index,
+                        &block,
+                        &format!("{:?}", event.from),
+                        &event.to.to_string(),
+                        &value,
+                        &types_tokens,
+                        &status,
+                        &reorged,
+                        &serde_json::to_value(event).map_err(Error::SerdeJSON)?,
+                    ],
+                )
+                .await
+                .map_err(Error::Postgres)
+                .map(|rows| rows > 0)
+        }
+        BscEventType::TransferTokenToBscFail(_event) => Ok(false),
+        BscEventType::TransferNftToBscFail(_event) => Ok(false),
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub async fn insert_raw_event<C: GenericClient>(client: &C, raw_event: &RawEvent) -> Result<(), Error> {
+    client
+        .execute(
+            "INSERT INTO undecoded_events(id, block, hash, log_index, data) \
+        VALUES($1, $2, $3, $4, $5) \
+        ON CONFLICT (id) DO NOTHING",
+            &[
+                &raw_event.get_id(),
+                &pending_block(raw_event.block_number)?,
+                &format!("{:?}", raw_event.hash),
+                &(raw_event.log_index as u32),
+                &raw_event.data,
+            ],
+        )
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub async fn update_cursor<C: GenericClient>(
+    client: &C,
+    chain: &str,
+    block: BlockNumber,
+    hash: &str,
+) -> Result<(), Error> {
+    let block = block as u32;
+
+    client
+        .execute(
+            "INSERT INTO cursors(chain, block, hash) \
+                VALUES ($1, $2, $3) \
+                ON CONFLICT (chain) DO UPDATE \
+                SET block = EXCLUDED.block, hash = EXCLUDED.hash, updated_at = now()",
+            &[&chain, &block, &hash],
+        )
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+pub async fn update_block_bsc<C: GenericClient>(
+    client: &C,
+    block: BlockNumber,
+    hash: &H256,
+    parent_hash: &H256,
+) -> Result<(), Error> {
+    let block = block as u32;
+
+    client
+        .execute(
+            "INSERT INTO blocks_bsc(block, hash, parent_hash) \
+                VALUES ($1, $2, $3) \
+                ON CONFLICT (block) DO UPDATE \
+                SET hash = EXCLUDED.hash, parent_hash = EXCLUDED.parent_hash",
+            &[&block, &format!("{:?}", hash), &format!("{:?}", parent_hash)],
+        )
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+/// Forget processed blocks starting from `from`,
+/// they were removed from chain by reorganization.
+#[allow(clippy::cast_possible_truncation)]
+pub async fn remove_blocks_bsc<C: GenericClient>(client: &C, from: BlockNumber) -> Result<(), Error> {
+    let from = from as u32;
+
+    client
+        .execute("DELETE FROM blocks_bsc WHERE block >= $1", &[&from])
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+/// Forget hashes of blocks below `below`, they are too deep to be reorganized.
+#[allow(clippy::cast_possible_truncation)]
+pub async fn prune_blocks_bsc<C: GenericClient>(client: &C, below: BlockNumber) -> Result<(), Error> {
+    let below = below as u32;
+
+    client
+        .execute("DELETE FROM blocks_bsc WHERE block < $1", &[&below])
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+/// Flag all not settled transfers from reorganized blocks,
+/// returns number of flagged transfers. Transfer which mint is submitted but not settled
+/// stays in progress, so its extrinsic is settled and it is never minted twice.
+#[allow(clippy::cast_possible_truncation)]
+pub async fn mark_reorged_bsc<C: GenericClient>(
+    client: &C,
+    from: BlockNumber,
+    to: BlockNumber,
+) -> Result<u64, Error> {
+    let from = from as u32;
+    let to = to as u32;
+
+    client
+        .execute(
+            "UPDATE extrinsics_bsc \
+            SET status = $1 \
+            WHERE block >= $2 AND block <= $3 AND status IN ($4, $5, $6) \
+            AND (submitted_tx_hash IS NULL OR dest_tx_hash IS NOT NULL)",
+            &[
+                &(Status::Reorged as u32),
+                &from,
+                &to,
+                &(Status::Got as u32),
+                &(Status::InProgress as u32),
+                &(Status::Error as u32),
+            ],
+        )
+        .await
+        .map_err(Error::Postgres)
+}
+
+/// Operator can't change its attestation of transfer once it is stored.
+pub async fn insert_attestation<C: GenericClient>(client: &C, attestation: &Attestation) -> Result<(), Error> {
+    client
+        .execute(
+            "INSERT INTO attestations(transfer_id, chain, operator, digest, signature) \
+                VALUES ($1, $2, $3, $4, $5) \
+                ON CONFLICT (transfer_id, operator) DO NOTHING",
+            &[
+                &attestation.transfer_id,
+                &attestation.chain,
+                &format!("{:?}", attestation.operator),
+                &format!("{:?}", attestation.digest),
+                &hex::encode(&attestation.signature),
+            ],
+        )
+        .await
+        .map_err(Error::Postgres)
+        .map(|_| ())
+}
+
+/// Log of pending block has no number, it can't be stored until block is mined.
+fn pending_block(block: Option<U64>) -> Result<u32, Error> {
+    block
+        .map(|block| block.as_u32())
+        .ok_or_else(|| Error::Custom(String::from("Log of pending block has no block number")))
+}
@@ -0,0 +1,122 @@
+use crate::queries;
+
+use primitives::{
+    attestation::Attestation,
+    events::{bsc::BscEventType, realis::RealisEventType},
+    types::{BlockNumber, RawEvent},
+    Error,
+};
+use tokio_postgres::Transaction;
+use web3::ethabi::ethereum_types::H256;
+
+/// Writes produced by processing of blocks, they are stored in one
+/// transaction so block is never marked as processed without its transfers.
+#[derive(Default)]
+pub struct UnitOfWork {
+    reorg_bsc: Option<(BlockNumber, BlockNumber)>,
+    extrinsics_realis: Vec<RealisEventType>,
+    extrinsics_bsc: Vec<BscEventType>,
+    raw_events: Vec<RawEvent>,
+    attestations: Vec<Attestation>,
+    blocks_bsc: Vec<(BlockNumber, H256, H256)>,
+    cursors: Vec<(&'static str, BlockNumber, String)>,
+    prune_bsc: Option<BlockNumber>,
+}
+
+/// What was changed by committed `UnitOfWork`
+#[derive(Default)]
+pub struct Committed {
+    /// Transfers which weren't known before, so they should be processed
+    pub extrinsics_realis: Vec<RealisEventType>,
+    /// Transfers which weren't known before or came back after reorganization
+    pub extrinsics_bsc: Vec<BscEventType>,
+    /// Number of transfers flagged as reorged
+    pub reorged_bsc: u64,
+}
+
+impl UnitOfWork {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_extrinsic_realis(&mut self, event: RealisEventType) {
+        self.extrinsics_realis.push(event);
+    }
+
+    pub fn add_extrinsic_bsc(&mut self, event: BscEventType) {
+        self.extrinsics_bsc.push(event);
+    }
+
+    pub fn add_raw_event(&mut self, raw_event: RawEvent) {
+        self.raw_events.push(raw_event);
+    }
+
+    /// Store attestation of this operator, even if transfer is already known.
+    pub fn add_attestation(&mut self, attestation: Attestation) {
+        self.attestations.push(attestation);
+    }
+
+    /// Remember hash of processed block to detect reorganizations.
+    pub fn add_block_bsc(&mut self, block: BlockNumber, hash: H256, parent_hash: H256) {
+        self.blocks_bsc.push((block, hash, parent_hash));
+    }
+
+    /// Mark all Realis blocks up to `block` as processed.
+    pub fn advance_cursor_realis(&mut self, block: BlockNumber, hash: &str) {
+        self.cursors.push(("realis", block, String::from(hash)));
+    }
+
+    /// Mark all BSC blocks up to `block` as processed.
+    pub fn advance_cursor_bsc(&mut self, block: BlockNumber, hash: &str) {
+        self.cursors.push(("bsc", block, String::from(hash)));
+    }
+
+    /// Halt not settled transfers from blocks `fork + 1 ..= to`,
+    /// forget their hashes and move cursor back to last common block.
+    pub fn rewind_bsc(&mut self, fork: BlockNumber, fork_hash: &str, to: BlockNumber) {
+        self.reorg_bsc = Some((fork, to));
+        self.advance_cursor_bsc(fork, fork_hash);
+    }
+
+    /// Forget hashes of blocks below `below`, they are too deep to be reorganized.
+    pub fn prune_blocks_bsc(&mut self, below: BlockNumber) {
+        self.prune_bsc = Some(below);
+    }
+
+    pub(crate) async fn execute(self, transaction: &Transaction<'_>) -> Result<Committed, Error> {
+        let mut committed = Committed::default();
+
+        if let Some((fork, to)) = self.reorg_bsc {
+            committed.reorged_bsc = queries::mark_reorged_bsc(transaction, fork + 1, to).await?;
+            queries::remove_blocks_bsc(transaction, fork + 1).await?;
+        }
+        for event in self.extrinsics_realis {
+            if queries::insert_extrinsic_realis(transaction, &event).await? {
+                committed.extrinsics_realis.push(event);
+            }
+        }
+        for event in self.extrinsics_bsc {
+            if queries::insert_extrinsic_bsc(transaction, &event).await? {
+                committed.extrinsics_bsc.push(event);
+            }
+        }
+        for raw_event in &self.raw_events {
+            queries::insert_raw_event(transaction, raw_event).await?;
+        }
+        for attestation in &self.attestations {
+            queries::insert_attestation(transaction, attestation).await?;
+        }
+        for (block, hash, parent_hash) in &self.blocks_bsc {
+            queries::update_block_bsc(transaction, *block, hash, parent_hash).await?;
+        }
+        for (chain, block, hash) in &self.cursors {
+            queries::update_cursor(transaction, chain, *block, hash).await?;
+        }
+        if let Some(below) = self.prune_bsc {
+            queries::prune_blocks_bsc(transaction, below).await?;
+        }
+
+        Ok(committed)
+    }
+}
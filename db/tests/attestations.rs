@@ -0,0 +1,35 @@
+//! Attestations of operators are shared through database, their age decides takeover of stalled transfers.
+
+mod common;
+
+use db::UnitOfWork;
+use primitives::attestation::Attestation;
+use std::time::Duration;
+use web3::types::{Address, H256};
+
+fn attestation(operator: u8) -> Attestation {
+    Attestation {
+        transfer_id: String::from("0x01-0"),
+        chain: String::from("bsc"),
+        operator: Address::repeat_byte(operator),
+        digest: H256::repeat_byte(1),
+        signature: vec![operator; 65],
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn attestation_age_starts_with_first_operator() {
+    let db = common::migrated("attestation_age").await;
+    assert_eq!(db.get_attestation_age("0x01-0").await.unwrap(), None);
+
+    let mut work = UnitOfWork::new();
+    work.add_attestation(attestation(1));
+    work.add_attestation(attestation(2));
+    db.commit(work).await.unwrap();
+
+    let age = db.get_attestation_age("0x01-0").await.unwrap().unwrap();
+    assert!(age < Duration::from_secs(60));
+    assert_eq!(db.get_attestations("0x01-0").await.unwrap().len(), 2);
+    assert_eq!(db.get_attestation_age("0x02-0").await.unwrap(), None);
+}
@@ -0,0 +1,73 @@
+//! Database tests need PostgreSQL, they are ignored by default and run with `make test-db`.
+//! Server is read from `TEST_DATABASE_HOST`, `TEST_DATABASE_PORT`, `TEST_DATABASE_USER`
+//! and `TEST_DATABASE_PASSWORD` env, each test gets its own fresh database.
+
+use db::Database;
+use rust_lib::healthchecker::HealthChecker;
+use std::env;
+use tokio_postgres::{Client, NoTls};
+
+fn var(name: &str, default: &str) -> String {
+    env::var(name).unwrap_or_else(|_| String::from(default))
+}
+
+/// Plain connection to existing database.
+pub async fn client(dbname: &str) -> Client {
+    let config = format!(
+        "host={} port={} user={} password={} dbname={}",
+        var("TEST_DATABASE_HOST", "localhost"),
+        var("TEST_DATABASE_PORT", "5432"),
+        var("TEST_DATABASE_USER", "postgres"),
+        var("TEST_DATABASE_PASSWORD", "postgres"),
+        dbname
+    );
+    let (client, connection) = tokio_postgres::connect(&config, NoTls)
+        .await
+        .expect("Cannot connect to test database");
+    tokio::spawn(connection);
+
+    client
+}
+
+/// Empty database `bridge_test_{name}` with plain connection to it.
+pub async fn empty(name: &str) -> (String, Client) {
+    let dbname = format!("bridge_test_{}", name);
+    let admin = client("postgres").await;
+    admin
+        .batch_execute(&format!("DROP DATABASE IF EXISTS {0}; CREATE DATABASE {0}", dbname))
+        .await
+        .expect("Cannot create test database");
+
+    let client = client(&dbname).await;
+    (dbname, client)
+}
+
+/// Bridge connection to database created by `empty`.
+#[allow(dead_code)]
+pub async fn connect(dbname: &str) -> Database {
+    let health_checker = HealthChecker::new("127.0.0.1:0", 10000)
+        .await
+        .expect("Healthchecker error");
+
+    Database::new(
+        &var("TEST_DATABASE_HOST", "localhost"),
+        &var("TEST_DATABASE_PORT", "5432"),
+        &var("TEST_DATABASE_USER", "postgres"),
+        &var("TEST_DATABASE_PASSWORD", "postgres"),
+        dbname,
+        false,
+        health_checker,
+    )
+    .await
+    .expect("Cannot connect to test database")
+}
+
+/// Fresh database with schema of latest migration.
+#[allow(dead_code)]
+pub async fn migrated(name: &str) -> Database {
+    let (dbname, _) = empty(name).await;
+    let db = connect(&dbname).await;
+    db.migrate().await.expect("Cannot migrate test database");
+
+    db
+}
@@ -0,0 +1,97 @@
+//! Failed rollbacks wait for operator and don't come back to outbox.
+
+mod common;
+
+use primitives::{
+    db::{Attempt, Resolution, Status, Submission},
+    events::{
+        bsc::{BscEventType, TransferTokenToRealis},
+        traits::Event,
+    },
+};
+use runtime::AccountId;
+use web3::types::{H160, H256, U64};
+
+fn transfer() -> TransferTokenToRealis {
+    TransferTokenToRealis {
+        block: Some(U64::from(100)),
+        hash: H256::repeat_byte(1),
+        log_index: 0,
+        contract: H160::repeat_byte(2),
+        from: H160::repeat_byte(3),
+        to: AccountId::from([4; 32]),
+        amount: 1_000,
+    }
+}
+
+fn submission(tx_hash: &str, nonce: u64) -> Submission {
+    Submission {
+        tx_hash: String::from(tx_hash),
+        raw: String::from("0x00"),
+        nonce,
+        block: 1,
+        batch_index: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn dead_letter_halts_transfer_with_attempt_history() {
+    let db = common::migrated("dead_letters_history").await;
+    let id = transfer().get_hash();
+    db.add_extrinsic_bsc(&BscEventType::TransferTokenToRealis(transfer()))
+        .await
+        .unwrap();
+
+    db.add_attempt(&id, "bsc", &submission("0xaa", 1), Some("5")).await.unwrap();
+    db.add_attempt(&id, "bsc", &submission("0xbb", 1), Some("6")).await.unwrap();
+    db.set_attempt_error("0xbb", "Transaction reverted").await.unwrap();
+
+    // Rollback failed before it was claimed, transfer is still in outbox
+    db.update_status_bsc(&id, Status::Error).await.unwrap();
+    db.add_dead_letter_bsc(&id, "Unknown asset").await.unwrap();
+
+    assert_eq!(db.get_status_bsc(&id).await.unwrap(), Some(Status::RollbackError));
+    assert!(db.get_transfers_bsc(Status::Error).await.unwrap().is_empty());
+
+    let dead_letters = db.get_dead_letters().await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].error, "Unknown asset");
+    let attempts = &dead_letters[0].attempts;
+    assert_eq!(
+        attempts.iter().map(|attempt| attempt.tx_hash.as_str()).collect::<Vec<_>>(),
+        vec!["0xaa", "0xbb"]
+    );
+    assert!(attempts.iter().all(|attempt| attempt.created_at.is_some()));
+    assert_eq!(
+        attempts.iter().map(|attempt| attempt.error.as_deref()).collect::<Vec<_>>(),
+        vec![None, Some("Transaction reverted")]
+    );
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn only_retried_dead_letter_is_reopened() {
+    let db = common::migrated("dead_letters_reopen").await;
+    let id = transfer().get_hash();
+    db.add_extrinsic_bsc(&BscEventType::TransferTokenToRealis(transfer()))
+        .await
+        .unwrap();
+    db.update_status_bsc(&id, Status::Error).await.unwrap();
+
+    db.add_dead_letter_bsc(&id, "first").await.unwrap();
+    assert!(db.resolve_dead_letter(&id, Resolution::Retry, "alice", "node is back").await.unwrap());
+    assert_eq!(db.get_status_bsc(&id).await.unwrap(), Some(Status::Error));
+
+    // Retried rollback failed again
+    db.add_dead_letter_bsc(&id, "second").await.unwrap();
+    let dead_letters = db.get_dead_letters().await.unwrap();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].error, "second");
+    assert_eq!(dead_letters[0].attempts, Vec::<Attempt>::new());
+
+    assert!(db.resolve_dead_letter(&id, Resolution::Cancel, "alice", "refunded").await.unwrap());
+    db.add_dead_letter_bsc(&id, "late").await.unwrap();
+    assert!(db.get_dead_letters().await.unwrap().is_empty());
+    assert_eq!(db.get_status_bsc(&id).await.unwrap(), Some(Status::RollbackError));
+}
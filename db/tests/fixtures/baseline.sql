@@ -1,3 +1,5 @@
+-- Schema of `db/res/tables.sql` before migrations were introduced
+
 -- name: 1.0-extrinsic-type
 CREATE TABLE types
 (
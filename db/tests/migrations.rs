@@ -0,0 +1,71 @@
+//! Schema is migrated from scratch and from deployments created before migrations.
+
+mod common;
+
+use db::migrations;
+use primitives::db::Status;
+
+async fn version(client: &tokio_postgres::Client) -> u32 {
+    client
+        .query_one("SELECT MAX(version) FROM schema_migrations", &[])
+        .await
+        .unwrap()
+        .get(0)
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn empty_database_is_migrated_once() {
+    let (_, mut client) = common::empty("migrate_empty").await;
+
+    migrations::run(&mut client).await.unwrap();
+    assert_eq!(version(&client).await, migrations::latest());
+
+    // Nothing is applied twice
+    migrations::run(&mut client).await.unwrap();
+    assert_eq!(version(&client).await, migrations::latest());
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn operators_migrate_shared_database_together() {
+    let (dbname, mut first) = common::empty("migrate_together").await;
+    let mut second = common::client(&dbname).await;
+
+    let (first_run, second_run) = tokio::join!(migrations::run(&mut first), migrations::run(&mut second));
+    first_run.unwrap();
+    second_run.unwrap();
+    assert_eq!(version(&first).await, migrations::latest());
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn baseline_schema_is_upgraded() {
+    let (dbname, mut client) = common::empty("migrate_baseline").await;
+    client
+        .batch_execute(include_str!("fixtures/baseline.sql"))
+        .await
+        .unwrap();
+    client
+        .batch_execute(
+            "INSERT INTO extrinsics_bsc(hash, block, from_account, to_account, value, type, status) \
+            VALUES ('0x01', 10, 'from', 'to', '\"100\"', 1, 1), ('0x02', 11, 'from', 'to', '\"100\"', 1, 3); \
+            INSERT INTO blocks_bsc(block) VALUES (10), (10), (11); \
+            INSERT INTO blocks_realis(block) VALUES (20), (21);",
+        )
+        .await
+        .unwrap();
+
+    migrations::run(&mut client).await.unwrap();
+    assert_eq!(version(&client).await, migrations::latest());
+
+    let db = common::connect(&dbname).await;
+    // Old transfers have no payload, so unfinished ones can't be sent again
+    assert_eq!(db.get_status_bsc("0x01").await.unwrap(), Some(Status::Unresolved));
+    assert_eq!(db.get_status_bsc("0x02").await.unwrap(), Some(Status::Success));
+    assert!(db.get_transfers_bsc(Status::Got).await.unwrap().is_empty());
+    // Listeners continue after last logged block, its hash wasn't stored
+    assert_eq!(db.get_cursor_realis().await.unwrap(), Some((21, None)));
+    assert_eq!(db.get_cursor_bsc().await.unwrap(), Some((11, None)));
+    assert_eq!(db.get_block_hash_bsc(11).await.unwrap(), None);
+}
@@ -0,0 +1,116 @@
+//! Nonces are reserved, released and persisted across restarts.
+
+mod common;
+
+use db::NonceManager;
+use primitives::db::Submission;
+use std::sync::Arc;
+
+const ACCOUNT: &str = "0x0000000000000000000000000000000000000001";
+
+fn submission(tx_hash: &str, nonce: u64) -> Submission {
+    Submission {
+        tx_hash: String::from(tx_hash),
+        raw: String::from("0x00"),
+        nonce,
+        block: 1,
+        batch_index: None,
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn nonce_above_u32_is_stored() {
+    let db = common::migrated("nonces_bigint").await;
+    let nonce = u64::from(u32::MAX) + 5;
+
+    assert_eq!(db.get_next_nonce("bsc", ACCOUNT).await.unwrap(), None);
+    db.set_next_nonce("bsc", ACCOUNT, nonce).await.unwrap();
+    assert_eq!(db.get_next_nonce("bsc", ACCOUNT).await.unwrap(), Some(nonce));
+    // Other chain has own nonce
+    assert_eq!(db.get_next_nonce("realis", ACCOUNT).await.unwrap(), None);
+
+    assert!(db.set_next_nonce("bsc", ACCOUNT, u64::MAX).await.is_err());
+    assert_eq!(db.get_next_nonce("bsc", ACCOUNT).await.unwrap(), Some(nonce));
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn nonces_are_reserved_after_known_one() {
+    let db = Arc::new(common::migrated("nonces_reserve").await);
+    let nonces = NonceManager::new(Arc::clone(&db), "bsc", String::from(ACCOUNT));
+
+    assert_eq!(nonces.reserve(0).await.unwrap(), 0);
+    assert_eq!(nonces.reserve(0).await.unwrap(), 1);
+    // Account was used outside of bridge
+    assert_eq!(nonces.reserve(7).await.unwrap(), 7);
+    assert_eq!(nonces.reserve(3).await.unwrap(), 8);
+    assert_eq!(db.get_next_nonce("bsc", ACCOUNT).await.unwrap(), Some(9));
+
+    // Nonces of not yet mined transactions aren't reused after restart
+    let restarted = NonceManager::new(Arc::clone(&db), "bsc", String::from(ACCOUNT));
+    assert_eq!(restarted.reserve(0).await.unwrap(), 9);
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn only_last_nonce_is_released() {
+    let db = Arc::new(common::migrated("nonces_release").await);
+    let nonces = NonceManager::new(Arc::clone(&db), "realis", String::from(ACCOUNT));
+
+    let first = nonces.reserve(0).await.unwrap();
+    let second = nonces.reserve(0).await.unwrap();
+
+    nonces.release(second).await.unwrap();
+    assert_eq!(db.get_next_nonce("realis", ACCOUNT).await.unwrap(), Some(second));
+    assert_eq!(nonces.reserve(0).await.unwrap(), second);
+
+    // Earlier nonce can't be reused, later transactions are already signed with next ones
+    nonces.release(first).await.unwrap();
+    assert_eq!(db.get_next_nonce("realis", ACCOUNT).await.unwrap(), Some(second + 1));
+    assert_eq!(nonces.gaps(0).await.unwrap(), vec![first]);
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn gaps_are_reserved_nonces_not_in_use() {
+    let db = Arc::new(common::migrated("nonces_gaps").await);
+    let nonces = NonceManager::new(Arc::clone(&db), "bsc", String::from(ACCOUNT));
+
+    for _ in 0..5 {
+        nonces.reserve(0).await.unwrap();
+    }
+    // Transactions with nonces 0, 1 and 3 are handled, 2 and 4 are being sent
+    for nonce in [0, 1, 3] {
+        nonces.complete(nonce).await;
+    }
+
+    assert_eq!(nonces.gaps(0).await.unwrap(), vec![0, 1, 3]);
+    // Nonces below account nonce in latest block are already used
+    assert_eq!(nonces.gaps(2).await.unwrap(), vec![3]);
+    assert!(nonces.gaps(5).await.unwrap().is_empty());
+
+    nonces.complete(4).await;
+    assert_eq!(nonces.gaps(4).await.unwrap(), vec![4]);
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn attempts_are_found_by_nonce() {
+    let db = common::migrated("nonces_attempts").await;
+    let nonce = u64::from(u32::MAX) + 1;
+
+    db.add_attempt("0x01", "bsc", &submission("0xaa", nonce), Some("5")).await.unwrap();
+    db.add_attempt("0x01", "bsc", &submission("0xbb", nonce), Some("6")).await.unwrap();
+    db.add_attempt("0x02", "bsc", &submission("0xcc", nonce + 1), Some("5")).await.unwrap();
+    db.add_attempt("0x03", "realis", &submission("0xdd", nonce), None).await.unwrap();
+
+    assert_eq!(
+        db.get_attempts_by_nonce("bsc", nonce).await.unwrap(),
+        vec![
+            (String::from("0x01"), String::from("0xaa")),
+            (String::from("0x01"), String::from("0xbb"))
+        ]
+    );
+    assert!(db.get_attempts_by_nonce("bsc", nonce + 2).await.unwrap().is_empty());
+}
@@ -0,0 +1,105 @@
+//! Transfers from reorganized BSC blocks are halted and come back only if nothing was minted for them,
+//! fork point is found over stored block hashes.
+
+mod common;
+
+use db::UnitOfWork;
+use primitives::{
+    db::{Status, Submission},
+    events::{
+        bsc::{BscEventType, TransferTokenToRealis},
+        traits::Event,
+    },
+};
+use runtime::AccountId;
+use web3::types::{H160, H256, U64};
+
+fn transfer(block: Option<u64>) -> TransferTokenToRealis {
+    TransferTokenToRealis {
+        block: block.map(U64::from),
+        hash: H256::repeat_byte(1),
+        log_index: 0,
+        contract: H160::repeat_byte(2),
+        from: H160::repeat_byte(3),
+        to: AccountId::from([4; 32]),
+        amount: 1_000,
+    }
+}
+
+fn event(block: u64) -> BscEventType {
+    BscEventType::TransferTokenToRealis(transfer(Some(block)))
+}
+
+/// Block 100 is reorganized, same log is included in block 101 of new chain.
+async fn reorganize(db: &db::Database) -> usize {
+    let mut work = UnitOfWork::new();
+    work.rewind_bsc(99, "0x99", 100);
+    work.add_extrinsic_bsc(event(101));
+
+    db.commit(work).await.unwrap().extrinsics_bsc.len()
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn not_submitted_transfer_comes_back_after_reorg() {
+    let db = common::migrated("reorgs_revived").await;
+    let id = transfer(None).get_hash();
+    assert!(db.add_extrinsic_bsc(&event(100)).await.unwrap());
+    db.update_status_bsc(&id, Status::InProgress).await.unwrap();
+
+    assert_eq!(reorganize(&db).await, 1);
+    assert_eq!(db.get_status_bsc(&id).await.unwrap(), Some(Status::Got));
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn submitted_transfer_is_not_minted_again_after_reorg() {
+    let db = common::migrated("reorgs_submitted").await;
+    let id = transfer(None).get_hash();
+    let submission = Submission {
+        tx_hash: String::from("0xaa"),
+        raw: String::from("0x00"),
+        nonce: 7,
+        block: 1,
+        batch_index: None,
+    };
+
+    assert!(db.add_extrinsic_bsc(&event(100)).await.unwrap());
+    assert!(db.claim_bsc(&id, Status::Got, Status::InProgress).await.unwrap());
+    db.set_submission_bsc(&id, &submission).await.unwrap();
+
+    // Mint extrinsic is still tracked, the log isn't taken as new transfer
+    assert_eq!(reorganize(&db).await, 0);
+    assert_eq!(db.get_status_bsc(&id).await.unwrap(), Some(Status::InProgress));
+    assert!(db.get_transfers_bsc(Status::Got).await.unwrap().is_empty());
+    let submissions = db.get_submissions_bsc(Status::InProgress).await.unwrap();
+    assert_eq!(submissions.len(), 1);
+    assert_eq!(submissions[0].1.as_ref().map(|submission| submission.nonce), Some(7));
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn log_of_pending_block_is_rejected() {
+    let db = common::migrated("reorgs_pending").await;
+
+    let pending = BscEventType::TransferTokenToRealis(transfer(None));
+    assert!(db.add_extrinsic_bsc(&pending).await.is_err());
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn stored_block_is_found_below_gaps() {
+    let db = common::migrated("reorgs_sparse").await;
+    let mut work = UnitOfWork::new();
+    // Logs mode stores only first, last and blocks with logs of every range
+    for block in [10, 13, 20] {
+        work.add_block_bsc(block, H256::from_low_u64_be(block), H256::from_low_u64_be(block - 1));
+    }
+    db.commit(work).await.unwrap();
+
+    let stored = |block: u64| Some((block, format!("{:?}", H256::from_low_u64_be(block))));
+    assert_eq!(db.get_stored_block_bsc(20).await.unwrap(), stored(20));
+    assert_eq!(db.get_stored_block_bsc(19).await.unwrap(), stored(13));
+    assert_eq!(db.get_stored_block_bsc(12).await.unwrap(), stored(10));
+    assert_eq!(db.get_stored_block_bsc(9).await.unwrap(), None);
+}
@@ -0,0 +1,153 @@
+//! Every transfer type is stored and loaded back from outbox.
+
+mod common;
+
+use primitives::{
+    db::Status,
+    events::{
+        bsc::{BscEventType, TransferNftToRealis, TransferTokenToRealis},
+        realis::{RealisEventType, TransferNftToBsc, TransferTokenToBsc},
+        traits::Event,
+    },
+    types::Hash,
+};
+use realis_primitives::TokenId;
+use runtime::AccountId;
+use web3::types::{H160, H256, U64};
+
+fn bsc_events() -> Vec<BscEventType> {
+    let (block, hash) = (Some(U64::from(100)), H256::repeat_byte(1));
+
+    vec![
+        BscEventType::TransferTokenToRealis(TransferTokenToRealis {
+            block,
+            hash,
+            log_index: 0,
+            contract: H160::repeat_byte(2),
+            from: H160::repeat_byte(3),
+            to: AccountId::from([4; 32]),
+            amount: 1_000,
+        }),
+        BscEventType::TransferNftToRealis(TransferNftToRealis {
+            block,
+            hash,
+            log_index: 1,
+            contract: H160::repeat_byte(5),
+            from: H160::repeat_byte(3),
+            dest: AccountId::from([4; 32]),
+            token_id: TokenId::from(1337),
+        }),
+    ]
+}
+
+fn realis_events() -> Vec<RealisEventType> {
+    let (block, hash) = (200, Hash::repeat_byte(7));
+
+    vec![
+        RealisEventType::TransferTokenToBsc(TransferTokenToBsc {
+            block,
+            hash,
+            extrinsic_index: 1,
+            event_index: 0,
+            from: AccountId::from([4; 32]),
+            to: H160::repeat_byte(3),
+            amount: 1_000,
+        }),
+        RealisEventType::TransferNftToBsc(TransferNftToBsc {
+            block,
+            hash,
+            extrinsic_index: 1,
+            event_index: 1,
+            from: AccountId::from([4; 32]),
+            dest: H160::repeat_byte(3),
+            token_id: TokenId::from(1337),
+        }),
+    ]
+}
+
+fn bsc_id(event: &BscEventType) -> String {
+    match event {
+        BscEventType::TransferTokenToRealis(event) => event.get_hash(),
+        BscEventType::TransferNftToRealis(event) => event.get_hash(),
+        BscEventType::TransferTokenToBscFail(event) => event.get_hash(),
+        BscEventType::TransferNftToBscFail(event) => event.get_hash(),
+    }
+}
+
+fn realis_id(event: &RealisEventType) -> String {
+    match event {
+        RealisEventType::TransferTokenToBsc(event) => event.get_hash(),
+        RealisEventType::TransferNftToBsc(event) => event.get_hash(),
+        RealisEventType::TransferTokenToRealisFail(event) => event.get_hash(),
+        RealisEventType::TransferNftToRealisFail(event) => event.get_hash(),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn every_bsc_transfer_is_stored() {
+    let db = common::migrated("bsc_transfers").await;
+
+    for event in bsc_events() {
+        assert!(db.add_extrinsic_bsc(&event).await.unwrap(), "{:?} isn't stored", event);
+        assert!(
+            !db.add_extrinsic_bsc(&event).await.unwrap(),
+            "{:?} is stored twice",
+            event
+        );
+    }
+
+    let stored = db.get_transfers_bsc(Status::Got).await.unwrap();
+    assert_eq!(
+        stored.iter().map(bsc_id).collect::<Vec<_>>(),
+        bsc_events().iter().map(bsc_id).collect::<Vec<_>>()
+    );
+    match &stored[1] {
+        BscEventType::TransferNftToRealis(event) => assert_eq!(event.token_id, TokenId::from(1337)),
+        event => panic!("Unexpected event: {:?}", event),
+    }
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn every_realis_transfer_is_stored() {
+    let db = common::migrated("realis_transfers").await;
+
+    for event in realis_events() {
+        assert!(
+            db.add_extrinsic_realis(&event).await.unwrap(),
+            "{:?} isn't stored",
+            event
+        );
+        assert!(
+            !db.add_extrinsic_realis(&event).await.unwrap(),
+            "{:?} is stored twice",
+            event
+        );
+    }
+
+    let mut stored = db
+        .get_transfers_realis(Status::Got)
+        .await
+        .unwrap()
+        .iter()
+        .map(realis_id)
+        .collect::<Vec<_>>();
+    let mut expected = realis_events().iter().map(realis_id).collect::<Vec<_>>();
+    // Transfers of one block have no order
+    stored.sort();
+    expected.sort();
+    assert_eq!(stored, expected);
+}
+
+#[tokio::test]
+#[ignore = "needs PostgreSQL"]
+async fn failed_transfers_are_not_stored() {
+    let db = common::migrated("failed_transfers").await;
+
+    let rollback = match &realis_events()[1] {
+        RealisEventType::TransferNftToBsc(event) => BscEventType::TransferNftToBscFail(event.clone()),
+        event => panic!("Unexpected event: {:?}", event),
+    };
+    assert!(!db.add_extrinsic_bsc(&rollback).await.unwrap());
+}
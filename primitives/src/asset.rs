@@ -0,0 +1,374 @@
+use crate::{decimals::Decimals, events::traits::Event, Error};
+use runtime::Call;
+use std::{collections::HashMap, str::FromStr};
+use web3::types::Address;
+
+/// Realis side of assets moved by bridge pallet. Pallet calls and events
+/// have no asset id, so transfers from Realis are sent to native asset of their kind.
+pub const NATIVE: &str = "native";
+
+/// Where transfers from BSC are released on Realis
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RealisAsset {
+    /// Balance or NFT moved by bridge pallet
+    Native,
+    /// Asset of `pallet_assets`, minted by bridge account
+    Asset(u32),
+    /// Class of `pallet_uniques`, instances are minted by bridge account
+    Class(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetKind {
+    /// BEP-20 token
+    Token,
+    /// BEP-721 collection
+    Nft,
+}
+
+impl AssetKind {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AssetKind::Token => "token",
+            AssetKind::Nft => "nft",
+        }
+    }
+}
+
+impl FromStr for AssetKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "token" => Ok(AssetKind::Token),
+            "nft" => Ok(AssetKind::Nft),
+            value => Err(format!("Unknown asset kind: {}", value)),
+        }
+    }
+}
+
+/// BSC contract bridged to Realis asset or collection
+#[derive(Debug, Clone)]
+pub struct Asset {
+    pub contract: Address,
+    pub kind: AssetKind,
+    /// Asset or collection on Realis side
+    pub realis_asset: String,
+    /// Decimals of BEP-20 token, zero for NFT
+    pub decimals: u8,
+    /// Disabled asset isn't listened, transfers which are already found are still settled
+    pub enabled: bool,
+}
+
+impl Asset {
+    #[must_use]
+    pub fn is_native(&self) -> bool {
+        self.realis_asset == NATIVE
+    }
+
+    /// Realis asset which transfers of this contract are released to,
+    /// `realis_asset` is `native` or id of Realis asset (tokens) or class (NFT).
+    /// # Errors
+    /// Fails for invalid ids.
+    pub fn realis(&self) -> Result<RealisAsset, Error> {
+        if self.is_native() {
+            return Ok(RealisAsset::Native);
+        }
+        let id = self.realis_asset.parse::<u32>().map_err(|_| {
+            Error::Custom(format!(
+                "Realis asset of {:?} must be `{}` or numeric id, got {}",
+                self.contract, NATIVE, self.realis_asset
+            ))
+        })?;
+
+        Ok(match self.kind {
+            AssetKind::Token => RealisAsset::Asset(id),
+            AssetKind::Nft => RealisAsset::Class(id),
+        })
+    }
+
+    /// Adapters can release transfers of asset on both chains.
+    /// Transfers from Realis are events of bridge pallet, so only native assets are moved back to BSC.
+    /// # Errors
+    /// Fails for invalid Realis asset ids.
+    pub fn check_routable(&self) -> Result<(), Error> {
+        self.realis().map(|_| ())
+    }
+}
+
+/// Bridged assets by their BSC contract, loaded from database at start
+#[derive(Debug, Clone, Default)]
+pub struct AssetRegistry {
+    assets: HashMap<Address, Asset>,
+    /// Decimals of Realis balance
+    realis_decimals: u8,
+}
+
+impl AssetRegistry {
+    /// # Errors
+    /// Fails if listened asset can't be routed or several enabled native assets
+    /// have the same kind, transfers from Realis couldn't be sent to any of them.
+    pub fn new(assets: Vec<Asset>, realis_decimals: u8) -> Result<Self, Error> {
+        for asset in assets.iter().filter(|asset| asset.enabled) {
+            asset.check_routable()?;
+        }
+        for kind in [AssetKind::Token, AssetKind::Nft] {
+            let natives = assets
+                .iter()
+                .filter(|asset| asset.kind == kind && asset.is_native() && asset.enabled)
+                .map(|asset| format!("{:?}", asset.contract))
+                .collect::<Vec<_>>();
+            if natives.len() > 1 {
+                return Err(Error::Custom(format!(
+                    "Several enabled native {} assets: {}, only one of them can be enabled",
+                    kind.as_str(),
+                    natives.join(", ")
+                )));
+            }
+        }
+
+        Ok(Self {
+            assets: assets.into_iter().map(|asset| (asset.contract, asset)).collect(),
+            realis_decimals,
+        })
+    }
+
+    #[must_use]
+    pub fn get(&self, contract: &Address) -> Option<&Asset> {
+        self.assets.get(contract)
+    }
+
+    /// Assets which transfers are listened on BSC
+    pub fn enabled(&self) -> impl Iterator<Item = &Asset> {
+        self.assets.values().filter(|asset| asset.enabled)
+    }
+
+    /// Asset of transfer from BSC. Transfers found before registry
+    /// have no contract, they belong to native asset of their kind.
+    /// # Errors
+    pub fn bsc_asset(&self, kind: AssetKind, contract: Address) -> Result<&Asset, Error> {
+        if contract.is_zero() {
+            return self.native(kind);
+        }
+
+        self.assets
+            .get(&contract)
+            .filter(|asset| asset.kind == kind)
+            .ok_or_else(|| Error::Custom(format!("Unknown {} contract {:?}", kind.as_str(), contract)))
+    }
+
+    /// Asset of transfer from Realis.
+    /// # Errors
+    pub fn realis_asset(&self, kind: AssetKind) -> Result<&Asset, Error> {
+        self.native(kind)
+    }
+
+    /// Call which releases transfer from BSC in Realis asset of `asset`,
+    /// adapter and attestor build the same call.
+    /// # Errors
+    pub fn release_call(&self, event: &impl Event, asset: &Asset) -> Result<Call, Error> {
+        event.get_release_call(asset.realis()?, &self.decimals(asset)?)
+    }
+
+    /// Converts amounts of given asset.
+    /// # Errors
+    pub fn decimals(&self, asset: &Asset) -> Result<Decimals, Error> {
+        Decimals::new(asset.decimals, self.realis_decimals)
+    }
+
+    /// Registry has at most one enabled native asset of every kind.
+    fn native(&self, kind: AssetKind) -> Result<&Asset, Error> {
+        self.assets
+            .values()
+            .find(|asset| asset.kind == kind && asset.is_native() && asset.enabled)
+            .ok_or_else(|| Error::Custom(format!("No enabled native {} asset", kind.as_str())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::bsc::{TransferNftToRealis, TransferTokenToRealis};
+    use realis_primitives::TokenId;
+    use runtime::{AccountId, Address as RealisAddress};
+    use web3::types::H256;
+
+    fn asset(byte: u8, kind: AssetKind, realis_asset: &str, enabled: bool) -> Asset {
+        Asset {
+            contract: Address::repeat_byte(byte),
+            kind,
+            realis_asset: String::from(realis_asset),
+            decimals: 18,
+            enabled,
+        }
+    }
+
+    #[test]
+    fn transfers_are_resolved_to_native_assets() {
+        let registry = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, true),
+                asset(2, AssetKind::Nft, NATIVE, true),
+            ],
+            12,
+        )
+        .unwrap();
+
+        assert_eq!(registry.realis_asset(AssetKind::Token).unwrap().contract, Address::repeat_byte(1));
+        assert_eq!(registry.realis_asset(AssetKind::Nft).unwrap().contract, Address::repeat_byte(2));
+
+        let token = registry.bsc_asset(AssetKind::Token, Address::repeat_byte(1)).unwrap();
+        assert_eq!(token.contract, Address::repeat_byte(1));
+        // Transfers found before registry have no contract
+        let legacy = registry.bsc_asset(AssetKind::Nft, Address::zero()).unwrap();
+        assert_eq!(legacy.contract, Address::repeat_byte(2));
+        // Contract must match kind of event
+        assert!(registry.bsc_asset(AssetKind::Nft, Address::repeat_byte(1)).is_err());
+        assert!(registry.bsc_asset(AssetKind::Token, Address::repeat_byte(3)).is_err());
+
+        let decimals = registry.decimals(token).unwrap();
+        assert_eq!((decimals.bsc(), decimals.realis()), (18, 12));
+    }
+
+    #[test]
+    fn duplicate_natives_are_rejected() {
+        let duplicate = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, true),
+                asset(2, AssetKind::Token, NATIVE, true),
+            ],
+            12,
+        );
+        assert!(matches!(
+            duplicate,
+            Err(Error::Custom(message)) if message.contains("Several enabled native token")
+        ));
+
+        // Contract of native token is replaced by disabling old one
+        let replaced = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, false),
+                asset(2, AssetKind::Token, NATIVE, true),
+            ],
+            12,
+        )
+        .unwrap();
+        assert_eq!(replaced.realis_asset(AssetKind::Token).unwrap().contract, Address::repeat_byte(2));
+    }
+
+    #[test]
+    fn disabled_assets_are_not_listened() {
+        let registry = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, false),
+                asset(2, AssetKind::Nft, NATIVE, true),
+            ],
+            12,
+        )
+        .unwrap();
+
+        let listened = registry.enabled().map(|asset| asset.contract).collect::<Vec<_>>();
+        assert_eq!(listened, vec![Address::repeat_byte(2)]);
+        assert!(registry.realis_asset(AssetKind::Token).is_err());
+        // Transfers found before disabling are still settled
+        assert!(registry.bsc_asset(AssetKind::Token, Address::repeat_byte(1)).is_ok());
+    }
+
+    #[test]
+    fn unroutable_assets_are_rejected() {
+        assert!(asset(1, AssetKind::Token, NATIVE, true).check_routable().is_ok());
+        assert!(asset(1, AssetKind::Token, "7", true).check_routable().is_ok());
+        assert!(asset(1, AssetKind::Token, "gold", true).check_routable().is_err());
+        assert!(asset(1, AssetKind::Nft, "-1", true).check_routable().is_err());
+
+        assert!(AssetRegistry::new(vec![asset(1, AssetKind::Token, "gold", true)], 12).is_err());
+        // Disabled asset isn't listened, so it can't be claimed
+        assert!(AssetRegistry::new(vec![asset(1, AssetKind::Token, "gold", false)], 12).is_ok());
+    }
+
+    #[test]
+    fn assets_are_routed_by_realis_id() {
+        assert_eq!(asset(1, AssetKind::Token, NATIVE, true).realis().unwrap(), RealisAsset::Native);
+        assert_eq!(asset(1, AssetKind::Token, "7", true).realis().unwrap(), RealisAsset::Asset(7));
+        assert_eq!(asset(1, AssetKind::Nft, "3", true).realis().unwrap(), RealisAsset::Class(3));
+
+        // Several currencies and collections next to native ones
+        let registry = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, true),
+                asset(2, AssetKind::Token, "7", true),
+                asset(3, AssetKind::Token, "8", true),
+                asset(4, AssetKind::Nft, NATIVE, true),
+                asset(5, AssetKind::Nft, "3", true),
+                asset(6, AssetKind::Nft, "4", true),
+            ],
+            12,
+        )
+        .unwrap();
+        assert_eq!(registry.enabled().count(), 6);
+        let gold = registry.bsc_asset(AssetKind::Token, Address::repeat_byte(3)).unwrap();
+        assert_eq!(gold.realis().unwrap(), RealisAsset::Asset(8));
+        // Transfers from Realis still go to native assets
+        assert_eq!(registry.realis_asset(AssetKind::Nft).unwrap().contract, Address::repeat_byte(4));
+    }
+
+    #[test]
+    fn transfers_are_released_in_mapped_assets() {
+        let registry = AssetRegistry::new(
+            vec![
+                asset(1, AssetKind::Token, NATIVE, true),
+                asset(2, AssetKind::Token, "7", true),
+                asset(3, AssetKind::Nft, "3", true),
+            ],
+            12,
+        )
+        .unwrap();
+        let token = TransferTokenToRealis {
+            block: None,
+            hash: H256::repeat_byte(9),
+            log_index: 0,
+            contract: Address::repeat_byte(2),
+            from: Address::repeat_byte(8),
+            to: AccountId::from([4; 32]),
+            amount: 5_000_000,
+        };
+        let nft = TransferNftToRealis {
+            block: None,
+            hash: H256::repeat_byte(9),
+            log_index: 1,
+            contract: Address::repeat_byte(3),
+            from: Address::repeat_byte(8),
+            dest: AccountId::from([4; 32]),
+            token_id: TokenId::from(42),
+        };
+
+        let gold = registry.get(&Address::repeat_byte(2)).unwrap();
+        assert_eq!(
+            registry.release_call(&token, gold).unwrap(),
+            Call::Assets(pallet_assets::Call::mint(
+                7,
+                RealisAddress::from(AccountId::from([4; 32])),
+                5
+            ))
+        );
+        let native = registry.get(&Address::repeat_byte(1)).unwrap();
+        assert_eq!(
+            registry.release_call(&token, native).unwrap(),
+            token.get_realis_call(&registry.decimals(native).unwrap()).unwrap()
+        );
+
+        let swords = registry.get(&Address::repeat_byte(3)).unwrap();
+        assert_eq!(
+            registry.release_call(&nft, swords).unwrap(),
+            Call::Uniques(pallet_uniques::Call::mint(
+                3,
+                42,
+                RealisAddress::from(AccountId::from([4; 32]))
+            ))
+        );
+        // Event must match kind of asset
+        assert!(registry.release_call(&token, swords).is_err());
+        assert!(registry.release_call(&nft, gold).is_err());
+    }
+}
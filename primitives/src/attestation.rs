@@ -0,0 +1,425 @@
+use crate::{
+    asset::{AssetKind, AssetRegistry},
+    decimals::Decimals,
+    events::{bsc::BscEventType, realis::RealisEventType, traits::Event},
+    Error,
+};
+
+use ethabi::Token;
+use log::warn;
+use runtime::Call;
+use std::{collections::HashSet, sync::Arc, time::Duration};
+use substrate_api_client::sp_runtime::codec::Encode;
+use web3::{
+    signing::{keccak256, recover, Key, SecretKey, SecretKeyRef},
+    types::{Address, H256},
+};
+
+/// Domain separator, so attestation can't be reused as signature of something else
+const DOMAIN: &[u8] = b"realis-bridge-attestation";
+
+/// How long transfer waits for its submitter before next operator takes it over
+pub const DEFAULT_TAKEOVER: Duration = Duration::from_secs(600);
+
+/// Operator's signature of transfer release
+#[derive(Debug, Clone)]
+pub struct Attestation {
+    pub transfer_id: String,
+    /// Chain where transfer was emitted
+    pub chain: String,
+    pub operator: Address,
+    pub digest: H256,
+    /// `r | s | v` with `v` in `0..=1`
+    pub signature: Vec<u8>,
+}
+
+/// Digest signed by operators, commits to transfer identity
+/// and destination call, so recipient and amount are covered.
+#[must_use]
+pub fn digest(id: &str, call: &[u8]) -> H256 {
+    #[allow(clippy::cast_possible_truncation)]
+    let id_length = (id.len() as u32).to_be_bytes();
+
+    H256::from(keccak256(&[DOMAIN, &id_length, id.as_bytes(), call].concat()))
+}
+
+/// Digest of transfer released on Realis side.
+#[must_use]
+pub fn realis_call_digest(id: &str, call: &Call) -> H256 {
+    digest(id, &call.encode())
+}
+
+/// Digest of transfer released on BSC side.
+#[must_use]
+pub fn binance_call_digest(id: &str, (function, params): &(String, Vec<Token>)) -> H256 {
+    digest(id, &[function.as_bytes(), &ethabi::encode(params)].concat())
+}
+
+/// Signs transfers observed by this operator
+pub struct Attestor {
+    key: SecretKey,
+    /// Attested calls must be the same as calls of adapters
+    assets: Arc<AssetRegistry>,
+}
+
+impl Attestor {
+    #[must_use]
+    pub fn new(key: SecretKey) -> Self {
+        Self {
+            key,
+            assets: Arc::new(AssetRegistry::default()),
+        }
+    }
+
+    #[must_use]
+    pub fn with_assets(mut self, assets: Arc<AssetRegistry>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Decimals of asset of transfer from Realis
+    fn realis_decimals(&self, kind: AssetKind) -> Result<Decimals, Error> {
+        self.assets.decimals(self.assets.realis_asset(kind)?)
+    }
+
+    /// Release of transfer from BSC, the same as adapter sends
+    fn release_call(&self, event: &impl Event, kind: AssetKind, contract: Address) -> Result<Call, Error> {
+        self.assets.release_call(event, self.assets.bsc_asset(kind, contract)?)
+    }
+
+    #[must_use]
+    pub fn operator(&self) -> Address {
+        SecretKeyRef::new(&self.key).address()
+    }
+
+    /// Attest transfer found on BSC, rollbacks aren't attested.
+    /// # Errors
+    pub fn attest_bsc(&self, event: &BscEventType) -> Result<Option<Attestation>, Error> {
+        let (id, call) = match event {
+            BscEventType::TransferTokenToRealis(event) => (
+                event.get_hash(),
+                self.release_call(event, AssetKind::Token, event.contract),
+            ),
+            BscEventType::TransferNftToRealis(event) => (
+                event.get_hash(),
+                self.release_call(event, AssetKind::Nft, event.contract),
+            ),
+            BscEventType::TransferTokenToBscFail(_) | BscEventType::TransferNftToBscFail(_) => return Ok(None),
+        };
+        // Adapter rejects transfer of unknown asset or with amount which can't be converted
+        let call = match call {
+            Ok(call) => call,
+            Err(error) => {
+                warn!("[Attestor] - transfer {} isn't attested: {}", id, error);
+                return Ok(None);
+            }
+        };
+
+        let digest = realis_call_digest(&id, &call);
+        self.sign(id, "bsc", digest).map(Some)
+    }
+
+    /// Attest transfer found on Realis, rollbacks aren't attested.
+    /// # Errors
+    pub fn attest_realis(&self, event: &RealisEventType) -> Result<Option<Attestation>, Error> {
+        let (id, call) = match event {
+            RealisEventType::TransferTokenToBsc(event) => (
+                event.get_hash(),
+                self.realis_decimals(AssetKind::Token)
+                    .and_then(|decimals| event.get_binance_call(&decimals)),
+            ),
+            RealisEventType::TransferNftToBsc(event) => (
+                event.get_hash(),
+                self.realis_decimals(AssetKind::Nft)
+                    .and_then(|decimals| event.get_binance_call(&decimals)),
+            ),
+            RealisEventType::TransferTokenToRealisFail(_) | RealisEventType::TransferNftToRealisFail(_) => {
+                return Ok(None)
+            }
+        };
+        // Adapter rejects transfer of unknown asset or with amount which can't be converted
+        let call = match call {
+            Ok(call) => call,
+            Err(error) => {
+                warn!("[Attestor] - transfer {} isn't attested: {}", id, error);
+                return Ok(None);
+            }
+        };
+
+        let digest = binance_call_digest(&id, &call);
+        self.sign(id, "realis", digest).map(Some)
+    }
+
+    fn sign(&self, transfer_id: String, chain: &str, digest: H256) -> Result<Attestation, Error> {
+        let signature = SecretKeyRef::new(&self.key)
+            .sign(digest.as_bytes(), None)
+            .map_err(|error| Error::Custom(format!("Cannot sign attestation: {:?}", error)))?;
+
+        #[allow(clippy::cast_possible_truncation)]
+        let v = (signature.v - 27) as u8;
+
+        Ok(Attestation {
+            transfer_id,
+            chain: String::from(chain),
+            operator: self.operator(),
+            digest,
+            signature: [signature.r.as_bytes(), signature.s.as_bytes(), &[v]].concat(),
+        })
+    }
+}
+
+/// Set of operators and number of their attestations needed for release.
+///
+/// Operators and threshold come from configuration of every instance, not from database,
+/// and each instance computes digest from transfer it loaded itself. So whoever can write
+/// to shared database can delay releases, but can't forge attestations or redirect transfer.
+#[derive(Debug, Clone)]
+pub struct Quorum {
+    /// Sorted, so every instance elects the same leader
+    operators: Vec<Address>,
+    threshold: usize,
+    /// Operator of this instance
+    operator: Option<Address>,
+    takeover: Duration,
+}
+
+impl Quorum {
+    /// # Errors
+    /// Fails if threshold can't be reached by given operators.
+    pub fn new(operators: HashSet<Address>, threshold: usize) -> Result<Self, Error> {
+        if threshold == 0 || threshold > operators.len() {
+            return Err(Error::Custom(format!(
+                "Attestation threshold must be in 1..={}, got {}",
+                operators.len(),
+                threshold
+            )));
+        }
+        let mut operators = operators.into_iter().collect::<Vec<_>>();
+        operators.sort();
+
+        Ok(Self {
+            operators,
+            threshold,
+            operator: None,
+            takeover: DEFAULT_TAKEOVER,
+        })
+    }
+
+    /// Operator of this instance, it submits only transfers it leads.
+    /// # Errors
+    /// Fails if operator isn't one of quorum, such instance would never submit anything.
+    pub fn with_operator(mut self, operator: Address) -> Result<Self, Error> {
+        if !self.operators.contains(&operator) {
+            return Err(Error::Custom(format!("Operator {:?} isn't in attestation quorum", operator)));
+        }
+
+        self.operator = Some(operator);
+        Ok(self)
+    }
+
+    /// Time after which next operator submits transfer which its leader didn't.
+    #[must_use]
+    pub fn with_takeover(mut self, takeover: Duration) -> Self {
+        self.takeover = takeover;
+        self
+    }
+
+    #[must_use]
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    /// Operator which submits transfer once quorum is reached. Instances see attestations of each other
+    /// only in shared database, and its claim keeps transfer sent once, while leader spreads work over operators.
+    #[must_use]
+    pub fn leader(&self, transfer_id: &str) -> Address {
+        self.operators[self.leader_index(transfer_id)]
+    }
+
+    /// Instance without operator submits every transfer.
+    #[must_use]
+    pub fn is_leader(&self, transfer_id: &str) -> bool {
+        self.operator.map_or(true, |operator| self.leader(transfer_id) == operator)
+    }
+
+    /// Transfer of operator which is down would stall, so operators after leader take it over in turn:
+    /// `n`-th of them may submit once transfer waited `n` takeover periods since its first attestation.
+    #[must_use]
+    pub fn may_submit(&self, transfer_id: &str, waited: Duration) -> bool {
+        let operator = match self.operator {
+            Some(operator) => operator,
+            None => return true,
+        };
+        let index = self.operators.iter().position(|known| *known == operator).unwrap_or_default();
+        let turn = (index + self.operators.len() - self.leader_index(transfer_id)) % self.operators.len();
+
+        #[allow(clippy::cast_possible_truncation)]
+        let turns = (waited.as_secs() / self.takeover.as_secs().max(1)) as usize;
+        turn <= turns
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn leader_index(&self, transfer_id: &str) -> usize {
+        let hash = keccak256(transfer_id.as_bytes());
+        let mut bytes = [0_u8; 8];
+        bytes.copy_from_slice(&hash[24..]);
+
+        (u64::from_be_bytes(bytes) % self.operators.len() as u64) as usize
+    }
+
+    /// Number of distinct known operators which signed exactly `digest`.
+    #[must_use]
+    pub fn count(&self, digest: H256, attestations: &[Attestation]) -> usize {
+        attestations
+            .iter()
+            .filter(|attestation| attestation.digest == digest && attestation.signature.len() == 65)
+            .filter_map(|attestation| {
+                recover(
+                    digest.as_bytes(),
+                    &attestation.signature[..64],
+                    i32::from(attestation.signature[64]),
+                )
+                .ok()
+            })
+            .filter(|operator| self.operators.contains(operator))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    #[must_use]
+    pub fn is_reached(&self, digest: H256, attestations: &[Attestation]) -> bool {
+        self.count(digest, attestations) >= self.threshold
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attestor(byte: u8) -> Attestor {
+        Attestor::new(SecretKey::from_slice(&[byte; 32]).unwrap())
+    }
+
+    fn quorum(attestors: &[&Attestor], threshold: usize) -> Quorum {
+        Quorum::new(attestors.iter().map(|attestor| attestor.operator()).collect(), threshold).unwrap()
+    }
+
+    #[test]
+    fn signature_recovers_operator() {
+        let attestor = attestor(1);
+        let signed = digest("0x01", b"call");
+
+        let attestation = attestor.sign(String::from("0x01"), "bsc", signed).unwrap();
+        // Ethereum `v` of 27 or 28 is stored as recovery id
+        assert_eq!(attestation.signature.len(), 65);
+        assert!(attestation.signature[64] <= 1);
+        assert_eq!(
+            recover(
+                signed.as_bytes(),
+                &attestation.signature[..64],
+                i32::from(attestation.signature[64])
+            )
+            .unwrap(),
+            attestor.operator()
+        );
+        assert_eq!(quorum(&[&attestor], 1).count(signed, &[attestation]), 1);
+    }
+
+    #[test]
+    fn only_distinct_known_operators_of_same_digest_are_counted() {
+        let (first, second, stranger) = (attestor(1), attestor(2), attestor(3));
+        let quorum = quorum(&[&first, &second], 2);
+        let signed = digest("0x01", b"call");
+
+        let attestation = first.sign(String::from("0x01"), "bsc", signed).unwrap();
+        let twice = vec![attestation.clone(), attestation.clone()];
+        assert_eq!(quorum.count(signed, &twice), 1);
+        assert!(!quorum.is_reached(signed, &twice));
+
+        // Stranger and signature of other call aren't counted
+        let other = digest("0x01", b"other call");
+        let attestations = vec![
+            attestation.clone(),
+            stranger.sign(String::from("0x01"), "bsc", signed).unwrap(),
+            second.sign(String::from("0x01"), "bsc", other).unwrap(),
+        ];
+        assert_eq!(quorum.count(signed, &attestations), 1);
+
+        // Signature is checked against digest of transfer, not against stored one
+        let mut forged = second.sign(String::from("0x01"), "bsc", other).unwrap();
+        forged.digest = signed;
+        assert_eq!(quorum.count(signed, &[attestation.clone(), forged]), 1);
+
+        let attestations = vec![attestation, second.sign(String::from("0x01"), "bsc", signed).unwrap()];
+        assert!(quorum.is_reached(signed, &attestations));
+    }
+
+    #[test]
+    fn threshold_must_be_reachable() {
+        let (first, second) = (attestor(1), attestor(2));
+
+        assert!(Quorum::new([first.operator(), second.operator()].into(), 0).is_err());
+        assert!(Quorum::new([first.operator(), second.operator()].into(), 3).is_err());
+        assert!(Quorum::new([first.operator()].into(), 1)
+            .unwrap()
+            .with_operator(second.operator())
+            .is_err());
+    }
+
+    #[test]
+    fn every_transfer_has_one_leader() {
+        let attestors = [attestor(1), attestor(2), attestor(3)];
+        let quorums = attestors
+            .iter()
+            .map(|attestor| {
+                quorum(&[&attestors[0], &attestors[1], &attestors[2]], 2)
+                    .with_operator(attestor.operator())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        let mut leaders = HashSet::new();
+        for id in 0..30 {
+            let id = format!("0x{:02x}", id);
+            let leading = quorums.iter().filter(|quorum| quorum.is_leader(&id)).count();
+            assert_eq!(leading, 1, "transfer {}", id);
+            leaders.insert(quorums[0].leader(&id));
+        }
+        // Work is spread over operators
+        assert_eq!(leaders.len(), 3);
+
+        // Instance without operator submits everything
+        assert!(quorum(&[&attestors[0], &attestors[1]], 1).is_leader("0x01"));
+    }
+
+    #[test]
+    fn operators_take_over_in_turn() {
+        let attestors = [attestor(1), attestor(2), attestor(3)];
+        let quorums = attestors
+            .iter()
+            .map(|attestor| {
+                quorum(&[&attestors[0], &attestors[1], &attestors[2]], 2)
+                    .with_operator(attestor.operator())
+                    .unwrap()
+                    .with_takeover(Duration::from_secs(60))
+            })
+            .collect::<Vec<_>>();
+        let submitters = |waited: u64| {
+            quorums
+                .iter()
+                .filter(|quorum| quorum.may_submit("0x01", Duration::from_secs(waited)))
+                .count()
+        };
+
+        // Only leader submits fresh transfer
+        assert_eq!(submitters(0), 1);
+        assert_eq!(submitters(59), 1);
+        assert!(quorums
+            .iter()
+            .all(|quorum| quorum.may_submit("0x01", Duration::from_secs(0)) == quorum.is_leader("0x01")));
+        // One more operator every period, until everyone can submit
+        assert_eq!(submitters(60), 2);
+        assert_eq!(submitters(120), 3);
+        assert_eq!(submitters(6000), 3);
+    }
+}
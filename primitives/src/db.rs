@@ -1,8 +1,12 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::{convert::TryFrom, str::FromStr};
+
 /// This enum is being casted to u32 so order matters, starts from 1.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Status {
     // Got from events
-    Got,
+    Got = 1,
     // Sent to blockchain, wait for result
     InProgress,
     // Blockchain inBlock
@@ -13,4 +17,136 @@ pub enum Status {
     RollbackSuccess,
     // Rollback error
     RollbackError,
+    // Source block was removed from chain by reorganization
+    Reorged,
+    // Rollback sent to blockchain, wait for result
+    RollbackInProgress,
+    // Result of sent transaction can't be determined, needs manual check
+    Unresolved,
+}
+
+/// Signed transaction saved before it is sent to destination blockchain,
+/// so after restart it can be found on chain or sent again
+#[derive(Debug, Clone)]
+pub struct Submission {
+    pub tx_hash: String,
+    /// Hex encoded signed transaction
+    pub raw: String,
+    pub nonce: u64,
+    /// Destination blockchain block at moment of sending
+    pub block: u64,
+    /// Position of call if several transfers are sent in one batch extrinsic
+    pub batch_index: Option<u32>,
+}
+
+impl TryFrom<u32> for Status {
+    type Error = crate::Error;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(Status::Got),
+            2 => Ok(Status::InProgress),
+            3 => Ok(Status::Success),
+            4 => Ok(Status::Error),
+            5 => Ok(Status::RollbackSuccess),
+            6 => Ok(Status::RollbackError),
+            7 => Ok(Status::Reorged),
+            8 => Ok(Status::RollbackInProgress),
+            9 => Ok(Status::Unresolved),
+            value => Err(crate::Error::Custom(format!("Unknown status: {}", value))),
+        }
+    }
+}
+
+/// Transaction included in destination blockchain
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub tx_hash: String,
+    pub block: u64,
+    /// Gas used on BSC side or extrinsic weight on Realis side
+    pub gas_used: Option<String>,
+    pub fee: Option<String>,
+    /// Time of block with transaction in milliseconds, time of saving is used if unknown
+    pub timestamp: Option<u64>,
+    /// Result of transaction execution
+    pub success: bool,
+}
+
+/// State of transaction sent before restart
+#[derive(Debug, Clone)]
+pub enum Recovery {
+    /// Transaction is in blockchain
+    Landed(Settlement),
+    /// Transaction will never appear in blockchain, transfer can be sent again
+    Retry,
+    /// Transaction state can't be determined
+    Unknown,
+}
+
+/// Transfer which rollback failed, it waits for operator decision
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub transfer_id: String,
+    /// Source chain of transfer, `realis` or `bsc`
+    pub chain: String,
+    pub payload: Value,
+    /// Error which stopped rollback, with its causes
+    pub error: String,
+    /// Transactions sent for transfer, oldest first
+    pub attempts: Vec<Attempt>,
+}
+
+/// Transaction sent for dead-lettered transfer
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Attempt {
+    pub tx_hash: String,
+    /// Unknown for transaction sent before attempts were recorded
+    pub created_at: Option<String>,
+    /// Why node rejected transaction or why it failed
+    pub error: Option<String>,
+}
+
+/// Operator decision about dead-lettered transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// Rollback is sent again by adapter
+    Retry,
+    /// Rollback was done outside of bridge
+    Complete,
+    /// Transfer is closed without rollback
+    Cancel,
+}
+
+impl Resolution {
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Resolution::Retry => "retry",
+            Resolution::Complete => "complete",
+            Resolution::Cancel => "cancel",
+        }
+    }
+
+    /// Status of transfer after resolution.
+    #[must_use]
+    pub fn status(&self) -> Status {
+        match self {
+            Resolution::Retry => Status::Error,
+            Resolution::Complete => Status::RollbackSuccess,
+            Resolution::Cancel => Status::RollbackError,
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "retry" => Ok(Resolution::Retry),
+            "complete" => Ok(Resolution::Complete),
+            "cancel" => Ok(Resolution::Cancel),
+            value => Err(format!("Unknown resolution: {}", value)),
+        }
+    }
 }
@@ -0,0 +1,125 @@
+use crate::Error;
+
+/// Largest power of ten which fits into `u128`
+const MAX_GAP: u8 = 38;
+
+/// Decimals of bridged token on both chains, converts amounts between them.
+/// Amount which doesn't fit into `u128` or loses precision is rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decimals {
+    bsc: u8,
+    realis: u8,
+}
+
+impl Default for Decimals {
+    /// Gap of 12 decimals, which was hardcoded before decimals were read from chains.
+    fn default() -> Self {
+        Self { bsc: 0, realis: 12 }
+    }
+}
+
+impl Decimals {
+    /// # Errors
+    /// Fails if one amount can't be represented on other chain.
+    pub fn new(bsc: u8, realis: u8) -> Result<Self, Error> {
+        if bsc.max(realis) - bsc.min(realis) > MAX_GAP {
+            return Err(Error::Amount(format!(
+                "gap between {} BSC and {} Realis decimals is too big",
+                bsc, realis
+            )));
+        }
+
+        Ok(Self { bsc, realis })
+    }
+
+    #[must_use]
+    pub fn bsc(&self) -> u8 {
+        self.bsc
+    }
+
+    #[must_use]
+    pub fn realis(&self) -> u8 {
+        self.realis
+    }
+
+    /// Amount of BEP-20 token in Realis balance.
+    /// # Errors
+    pub fn to_realis(&self, amount: u128) -> Result<u128, Error> {
+        scale(amount, self.bsc, self.realis)
+    }
+
+    /// Amount of Realis balance in BEP-20 token.
+    /// # Errors
+    pub fn to_bsc(&self, amount: u128) -> Result<u128, Error> {
+        scale(amount, self.realis, self.bsc)
+    }
+}
+
+fn scale(amount: u128, from: u8, to: u8) -> Result<u128, Error> {
+    let factor = 10_u128.pow(u32::from(from.max(to) - from.min(to)));
+
+    if to >= from {
+        amount
+            .checked_mul(factor)
+            .ok_or_else(|| Error::Amount(format!("{} with {} decimals overflows {} decimals", amount, from, to)))
+    } else if amount % factor == 0 {
+        Ok(amount / factor)
+    } else {
+        Err(Error::Amount(format!(
+            "{} with {} decimals loses precision with {} decimals",
+            amount, from, to
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_gap_is_twelve_decimals() {
+        let decimals = Decimals::default();
+
+        assert_eq!((decimals.bsc(), decimals.realis()), (0, 12));
+        assert_eq!(decimals.to_realis(5).unwrap(), 5_000_000_000_000);
+        assert_eq!(decimals.to_bsc(5_000_000_000_000).unwrap(), 5);
+    }
+
+    #[test]
+    fn equal_decimals_keep_amount() {
+        let decimals = Decimals::new(18, 18).unwrap();
+
+        assert_eq!(decimals.to_realis(u128::MAX).unwrap(), u128::MAX);
+        assert_eq!(decimals.to_bsc(123).unwrap(), 123);
+    }
+
+    #[test]
+    fn scaling_up_overflow_is_rejected() {
+        assert_eq!(scale(u128::MAX / 10, 0, 1).unwrap(), u128::MAX / 10 * 10);
+        assert!(matches!(scale(u128::MAX / 10 + 1, 0, 1), Err(Error::Amount(_))));
+        assert_eq!(scale(3, 0, MAX_GAP).unwrap(), 3 * 10_u128.pow(38));
+        assert!(matches!(scale(4, 0, MAX_GAP), Err(Error::Amount(_))));
+    }
+
+    #[test]
+    fn scaling_down_precision_loss_is_rejected() {
+        let decimals = Decimals::new(18, 12).unwrap();
+
+        assert_eq!(decimals.to_realis(3_000_000).unwrap(), 3);
+        assert!(matches!(decimals.to_realis(3_000_001), Err(Error::Amount(_))));
+        assert!(matches!(decimals.to_realis(999_999), Err(Error::Amount(_))));
+        assert_eq!(decimals.to_realis(0).unwrap(), 0);
+    }
+
+    #[test]
+    fn gap_is_limited_by_u128() {
+        assert!(Decimals::new(0, MAX_GAP).is_ok());
+        assert!(Decimals::new(MAX_GAP, 0).is_ok());
+        assert!(matches!(Decimals::new(0, MAX_GAP + 1), Err(Error::Amount(_))));
+        assert!(matches!(Decimals::new(u8::MAX, 0), Err(Error::Amount(_))));
+
+        // 10^38 fits into u128, so amount 1 is still converted
+        assert_eq!(scale(1, 0, MAX_GAP).unwrap(), 10_u128.pow(38));
+        assert_eq!(scale(10_u128.pow(38), MAX_GAP, 0).unwrap(), 1);
+    }
+}
@@ -1,12 +1,17 @@
-use crate::events::{
-    realis::{TransferNftToBsc, TransferTokenToBsc},
-    traits::Event,
+use crate::{
+    asset::RealisAsset,
+    decimals::Decimals,
+    events::{
+        realis::{TransferNftToBsc, TransferTokenToBsc},
+        traits::Event,
+    },
+    Error,
 };
 use ethabi::Token;
 
 use realis_bridge::Call as RealisBridgeCall;
 use realis_primitives::TokenId;
-use runtime::{AccountId, Call};
+use runtime::{AccountId, Address, Call};
 use serde::{Deserialize, Serialize};
 use substrate_api_client::sp_runtime::app_crypto::sp_core;
 use web3::{
@@ -14,10 +19,22 @@ use web3::{
     types::{H160, H256, U128, U64},
 };
 
+/// Identity of transfer emitted on BSC side.
+/// One transaction can emit several bridge logs,
+/// so log index is added to transaction hash.
+#[must_use]
+pub fn transfer_id(transaction_hash: &H256, log_index: u64) -> String {
+    format!("{:?}-{}", transaction_hash, log_index)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferTokenToRealis {
     pub block: Option<U64>,
     pub hash: H256,
+    pub log_index: u64,
+    /// Contract which emitted transfer, zero for transfers found before asset registry
+    #[serde(default)]
+    pub contract: H160,
     pub from: H160,
     pub to: AccountId,
     pub amount: u128,
@@ -25,23 +42,36 @@ pub struct TransferTokenToRealis {
 
 impl Event for TransferTokenToRealis {
     fn get_hash(&self) -> String {
-        format!("{:?}", self.hash)
+        transfer_id(&self.hash, self.log_index)
     }
 
-    fn get_realis_call(&self) -> Call {
-        Call::RealisBridge(RealisBridgeCall::transfer_token_to_realis(
+    fn get_realis_call(&self, decimals: &Decimals) -> Result<Call, Error> {
+        Ok(Call::RealisBridge(RealisBridgeCall::transfer_token_to_realis(
             sp_core::H160::from_slice(self.from.as_ref()),
             self.to.clone(),
-            self.amount * 1_000_000_000_000,
-        ))
+            decimals.to_realis(self.amount)?,
+        )))
+    }
+
+    /// Bridge account is issuer of Realis assets mapped to BSC tokens
+    fn get_release_call(&self, asset: RealisAsset, decimals: &Decimals) -> Result<Call, Error> {
+        match asset {
+            RealisAsset::Native => self.get_realis_call(decimals),
+            RealisAsset::Asset(id) => Ok(Call::Assets(pallet_assets::Call::mint(
+                id,
+                Address::from(self.to.clone()),
+                decimals.to_realis(self.amount)?,
+            ))),
+            RealisAsset::Class(_) => Err(Error::Custom(format!("Token can't be released in {:?}", asset))),
+        }
     }
 
-    // Rollback
-    fn get_binance_call(&self) -> (String, Vec<Token>) {
-        (
+    // Rollback, amount is returned as it was sent
+    fn get_binance_call(&self, _: &Decimals) -> Result<(String, Vec<Token>), Error> {
+        Ok((
             String::from("transfer"),
             (self.from, U128::from(self.amount)).into_tokens(),
-        )
+        ))
     }
 }
 
@@ -49,6 +79,10 @@ impl Event for TransferTokenToRealis {
 pub struct TransferNftToRealis {
     pub block: Option<U64>,
     pub hash: H256,
+    pub log_index: u64,
+    /// Contract which emitted transfer, zero for transfers found before asset registry
+    #[serde(default)]
+    pub contract: H160,
     pub from: H160,
     pub dest: AccountId,
     pub token_id: TokenId,
@@ -56,19 +90,37 @@ pub struct TransferNftToRealis {
 
 impl Event for TransferNftToRealis {
     fn get_hash(&self) -> String {
-        format!("{:?}", self.hash)
+        transfer_id(&self.hash, self.log_index)
     }
 
-    fn get_realis_call(&self) -> Call {
-        Call::RealisBridge(RealisBridgeCall::transfer_nft_to_realis(
+    fn get_realis_call(&self, _: &Decimals) -> Result<Call, Error> {
+        Ok(Call::RealisBridge(RealisBridgeCall::transfer_nft_to_realis(
             sp_core::H160::from_slice(self.from.as_ref()),
             self.dest.clone(),
             self.token_id,
-        ))
+        )))
     }
 
-    fn get_binance_call(&self) -> (String, Vec<Token>) {
-        (
+    /// Bridge account is issuer of Realis classes mapped to BSC collections
+    fn get_release_call(&self, asset: RealisAsset, decimals: &Decimals) -> Result<Call, Error> {
+        match asset {
+            RealisAsset::Native => self.get_realis_call(decimals),
+            RealisAsset::Class(class) => {
+                let instance = self.token_id.to_string().parse::<u32>().map_err(|_| {
+                    Error::Custom(format!("Token id {} doesn't fit into Realis instance id", self.token_id))
+                })?;
+                Ok(Call::Uniques(pallet_uniques::Call::mint(
+                    class,
+                    instance,
+                    Address::from(self.dest.clone()),
+                )))
+            }
+            RealisAsset::Asset(_) => Err(Error::Custom(format!("NFT can't be released in {:?}", asset))),
+        }
+    }
+
+    fn get_binance_call(&self, _: &Decimals) -> Result<(String, Vec<Token>), Error> {
+        Ok((
             String::from("safeMint"),
             (
                 self.dest.to_string(),
@@ -76,7 +128,7 @@ impl Event for TransferNftToRealis {
                 U128::from_dec_str(&self.token_id.to_string()).unwrap(),
             )
                 .into_tokens(),
-        )
+        ))
     }
 }
 
@@ -1,9 +1,11 @@
 use crate::{
+    decimals::Decimals,
     events::{
         bsc::{TransferNftToRealis, TransferTokenToRealis},
         traits::Event,
     },
     types::{BlockNumber, Hash},
+    Error,
 };
 
 use ethabi::Token;
@@ -17,10 +19,20 @@ use web3::{
     types::{H160, U128},
 };
 
+/// Identity of transfer emitted on Realis side.
+/// Block hash alone is not enough, because one block can contain
+/// several bridge transfers, so extrinsic and event indexes are added.
+#[must_use]
+pub fn transfer_id(block_hash: &Hash, extrinsic_index: u32, event_index: u32) -> String {
+    format!("{:?}-{}-{}", block_hash, extrinsic_index, event_index)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransferTokenToBsc {
     pub block: BlockNumber,
     pub hash: Hash,
+    pub extrinsic_index: u32,
+    pub event_index: u32,
     pub from: AccountId,
     pub to: H160,
     pub amount: u128,
@@ -28,22 +40,22 @@ pub struct TransferTokenToBsc {
 
 impl Event for TransferTokenToBsc {
     fn get_hash(&self) -> String {
-        format!("{:?}", self.hash)
+        transfer_id(&self.hash, self.extrinsic_index, self.event_index)
     }
 
-    // Rollback
-    fn get_realis_call(&self) -> Call {
-        Call::RealisGameApi(RealisGameApi::Call::transfer_from_pallet(
+    // Rollback, amount is returned as it was sent
+    fn get_realis_call(&self, _: &Decimals) -> Result<Call, Error> {
+        Ok(Call::RealisGameApi(RealisGameApi::Call::transfer_from_pallet(
             self.from.clone(),
             self.amount,
-        ))
+        )))
     }
 
-    fn get_binance_call(&self) -> (String, Vec<Token>) {
-        (
+    fn get_binance_call(&self, decimals: &Decimals) -> Result<(String, Vec<Token>), Error> {
+        Ok((
             String::from("transferFromRealis"),
-            (self.from.to_string(), self.to, U128::from(self.amount)).into_tokens(),
-        )
+            (self.from.to_string(), self.to, U128::from(decimals.to_bsc(self.amount)?)).into_tokens(),
+        ))
     }
 }
 
@@ -51,6 +63,8 @@ impl Event for TransferTokenToBsc {
 pub struct TransferNftToBsc {
     pub block: BlockNumber,
     pub hash: Hash,
+    pub extrinsic_index: u32,
+    pub event_index: u32,
     pub from: AccountId,
     pub dest: H160,
     pub token_id: TokenId,
@@ -58,20 +72,20 @@ pub struct TransferNftToBsc {
 
 impl Event for TransferNftToBsc {
     fn get_hash(&self) -> String {
-        format!("{:?}", self.hash)
+        transfer_id(&self.hash, self.extrinsic_index, self.event_index)
     }
 
     // Rollback
-    fn get_realis_call(&self) -> Call {
-        Call::RealisBridge(RealisBridgeCall::transfer_nft_to_realis(
+    fn get_realis_call(&self, _: &Decimals) -> Result<Call, Error> {
+        Ok(Call::RealisBridge(RealisBridgeCall::transfer_nft_to_realis(
             sp_core::H160::from(self.dest.0),
             self.from.clone(),
             self.token_id,
-        ))
+        )))
     }
 
-    fn get_binance_call(&self) -> (String, Vec<Token>) {
-        (
+    fn get_binance_call(&self, _: &Decimals) -> Result<(String, Vec<Token>), Error> {
+        Ok((
             String::from("safeMint"),
             (
                 self.from.to_string(),
@@ -79,7 +93,7 @@ impl Event for TransferNftToBsc {
                 U128::from_dec_str(&self.token_id.to_string()).unwrap(),
             )
                 .into_tokens(),
-        )
+        ))
     }
 }
 
@@ -1,3 +1,4 @@
+use crate::{asset::RealisAsset, decimals::Decimals, Error};
 use runtime::Call;
 
 use ethabi::token::Token;
@@ -16,9 +17,32 @@ use ethabi::token::Token;
 /// In this case `get_realis_call` is main call for Realis blockchain
 /// and `get_binance_call` is a rollback call if Realis blockchain call fail
 pub trait Event {
+    /// Unique identity of transfer, used as primary key in database.
+    /// Must be different for every transfer, even if they are in same
+    /// block or transaction.
     fn get_hash(&self) -> String;
 
-    fn get_realis_call(&self) -> Call;
+    /// Token amounts are converted with `decimals` of both chains.
+    /// # Errors
+    /// Fails if amount can't be represented on Realis.
+    fn get_realis_call(&self, decimals: &Decimals) -> Result<Call, Error>;
 
-    fn get_binance_call(&self) -> (String, Vec<Token>);
+    /// Call which releases transfer from BSC in given Realis asset,
+    /// native assets are released by bridge pallet.
+    /// # Errors
+    /// Fails if transfer can't be released in `asset`.
+    fn get_release_call(&self, asset: RealisAsset, decimals: &Decimals) -> Result<Call, Error> {
+        match asset {
+            RealisAsset::Native => self.get_realis_call(decimals),
+            asset => Err(Error::Custom(format!(
+                "Transfer {} can't be released in {:?}",
+                self.get_hash(),
+                asset
+            ))),
+        }
+    }
+
+    /// # Errors
+    /// Fails if amount can't be represented on BSC.
+    fn get_binance_call(&self, decimals: &Decimals) -> Result<(String, Vec<Token>), Error>;
 }
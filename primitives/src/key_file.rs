@@ -0,0 +1,51 @@
+use crate::Error;
+use std::path::Path;
+
+/// File with secret key must not be accessible by group or others.
+/// # Errors
+#[cfg(unix)]
+pub fn check_permissions(path: &Path) -> Result<(), Error> {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    let mode = fs::metadata(path)
+        .map_err(|error| Error::FileNotFound(format!("{:?}: {}", path, error)))?
+        .permissions()
+        .mode();
+    if mode & 0o077 == 0 {
+        Ok(())
+    } else {
+        Err(Error::Custom(format!(
+            "Key file {:?} has permissions {:o}, it must not be accessible by group or others",
+            path,
+            mode & 0o777
+        )))
+    }
+}
+
+/// Permissions are checked only on unix.
+/// # Errors
+#[cfg(not(unix))]
+#[allow(clippy::unnecessary_wraps)]
+pub fn check_permissions(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    #[test]
+    fn only_owner_can_access_key_file() {
+        let path = std::env::temp_dir().join(format!("primitives-{}-key", std::process::id()));
+        fs::write(&path, "secret").unwrap();
+
+        for (mode, allowed) in [(0o600, true), (0o400, true), (0o640, false), (0o644, false), (0o606, false)] {
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+            assert_eq!(check_permissions(&path).is_ok(), allowed, "mode {:o}", mode);
+        }
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(check_permissions(&path), Err(Error::FileNotFound(_))));
+    }
+}
@@ -1,10 +1,16 @@
+pub mod asset;
+pub mod attestation;
 pub mod block;
 pub mod db;
+pub mod decimals;
 pub mod events;
+pub mod key_file;
+pub mod retry;
 pub mod types;
 
 use substrate_api_client::ApiClientError;
 use thiserror::Error;
+use tokio_postgres::error::SqlState;
 use web3::Error as Web3Error;
 
 #[derive(Error, Debug)]
@@ -25,6 +31,148 @@ pub enum Error {
     Api(ApiClientError),
     #[error("Binance error: {0}")]
     Web3(Web3Error),
+    #[error("Transaction was sent, but its result is unknown: {0}")]
+    Unconfirmed(String),
+    #[error("Database schema version {0} is newer than latest known migration {1}!")]
+    UnknownSchema(u32, u32),
+    #[error("Cannot connect: {0}")]
+    Connection(String),
+    #[error("Cannot convert amount: {0}")]
+    Amount(String),
+    #[error("Retries are exhausted, last error: {0}")]
+    Exhausted(Box<Error>),
     #[error("{0}")]
     Custom(String),
 }
+
+/// Parts of node responses which mean that request can succeed later
+const TRANSIENT_MESSAGES: &[&str] = &[
+    "timeout",
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "connection closed",
+    "broken pipe",
+    "websocket",
+    "underpriced",
+    "header not found",
+    "too many requests",
+    "temporarily unavailable",
+];
+
+/// Parts of node responses to sent transaction which mean that transaction
+/// or other one with the same nonce is already in pool or mined
+const ALREADY_SENT_MESSAGES: &[&str] = &[
+    "already known",
+    "known transaction",
+    "already imported",
+    "nonce too low",
+    "replacement transaction underpriced",
+    "priority is too low",
+];
+
+impl Error {
+    /// Error which can disappear if operation is repeated: lost connection,
+    /// node timeout, serialization failure or gas price race. Permanent errors like reverted call
+    /// or invalid recipient won't change, so transfer should be rolled back.
+    /// Transaction with unknown result is never repeated.
+    #[must_use]
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Postgres(error) => match error.code() {
+                Some(state) => Self::is_transient_state(state),
+                // Client errors have no state, only lost connection can be fixed by repeating
+                None => {
+                    error.is_closed()
+                        || std::error::Error::source(error).map_or(false, |source| source.is::<std::io::Error>())
+                }
+            },
+            Error::Disconnected | Error::Connection(_) => true,
+            Error::Web3(Web3Error::Transport(_) | Web3Error::Unreachable | Web3Error::Io(_)) => true,
+            error if error.is_already_sent() => false,
+            Error::Web3(Web3Error::Rpc(error)) => Self::is_transient_message(&error.message),
+            Error::Api(error) => Self::is_transient_message(&format!("{:?}", error)),
+            Error::Custom(message) => Self::is_transient_message(message),
+            _ => false,
+        }
+    }
+
+    /// Node rejected transaction because it or other one with the same nonce was sent before.
+    /// Signing it again with new nonce could execute transfer twice.
+    #[must_use]
+    pub fn is_already_sent(&self) -> bool {
+        let message = match self {
+            Error::Web3(Web3Error::Rpc(error)) => error.message.clone(),
+            Error::Api(error) => format!("{:?}", error),
+            Error::Custom(message) => message.clone(),
+            _ => return false,
+        }
+        .to_lowercase();
+
+        ALREADY_SENT_MESSAGES.iter().any(|part| message.contains(part))
+    }
+
+    /// Lost connection (class 08), serialization failure or deadlock
+    fn is_transient_state(state: &SqlState) -> bool {
+        state.code().starts_with("08")
+            || *state == SqlState::T_R_SERIALIZATION_FAILURE
+            || *state == SqlState::T_R_DEADLOCK_DETECTED
+    }
+
+    fn is_transient_message(message: &str) -> bool {
+        let message = message.to_lowercase();
+        TRANSIENT_MESSAGES.iter().any(|part| message.contains(part))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lost_connection_and_conflicts_are_transient() {
+        for code in ["08000", "08003", "08006", "40001", "40P01"] {
+            assert!(Error::is_transient_state(&SqlState::from_code(code)), "{} isn't transient", code);
+        }
+    }
+
+    #[test]
+    fn broken_queries_are_permanent() {
+        // Unique and foreign key violations, undefined table, syntax error
+        for code in ["23505", "23503", "42P01", "42601", "22003"] {
+            assert!(!Error::is_transient_state(&SqlState::from_code(code)), "{} is transient", code);
+        }
+    }
+
+    #[test]
+    fn node_errors_are_classified_by_message() {
+        assert!(Error::Web3(Web3Error::Unreachable).is_transient());
+        assert!(Error::Custom(String::from("Request timed out")).is_transient());
+        assert!(Error::Custom(String::from("Connection reset by peer")).is_transient());
+        assert!(Error::Custom(String::from("transaction underpriced")).is_transient());
+        assert!(Error::Disconnected.is_transient());
+
+        assert!(!Error::Custom(String::from("execution reverted")).is_transient());
+        assert!(!Error::Custom(String::from("invalid connection string")).is_transient());
+        assert!(!Error::Web3(Web3Error::Decoder(String::from("invalid data"))).is_transient());
+        assert!(!Error::Unconfirmed(String::from("timeout")).is_transient());
+        assert!(!Error::Exhausted(Box::new(Error::Disconnected)).is_transient());
+    }
+
+    #[test]
+    fn sent_transactions_are_not_repeated() {
+        for message in [
+            "already known",
+            "nonce too low",
+            "replacement transaction underpriced",
+            "Priority is too low: (140 vs 140)",
+        ] {
+            let error = Error::Custom(String::from(message));
+            assert!(error.is_already_sent(), "{} isn't sent", message);
+            assert!(!error.is_transient(), "{} is transient", message);
+        }
+
+        assert!(!Error::Custom(String::from("transaction underpriced")).is_already_sent());
+        assert!(!Error::Web3(Web3Error::Unreachable).is_already_sent());
+    }
+}
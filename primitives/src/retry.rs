@@ -0,0 +1,126 @@
+use crate::Error;
+use log::warn;
+use std::{future::Future, time::Duration};
+use tokio::time::sleep;
+
+/// Repeats operation failed with transient error, waiting longer after each attempt.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Attempts including the first one
+    max_attempts: u32,
+    initial_delay: Duration,
+    max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    #[must_use]
+    pub fn new(max_attempts: u32, initial_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_delay,
+            max_delay,
+        }
+    }
+
+    /// Delay before attempt after given number of failed ones.
+    #[must_use]
+    pub fn delay(&self, failed: u32) -> Duration {
+        self.initial_delay
+            .checked_mul(2_u32.saturating_pow(failed.saturating_sub(1)))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+
+    /// Run `operation` until it succeeds, fails with permanent error
+    /// or attempts are exhausted, then error is wrapped in [`Error::Exhausted`].
+    /// # Errors
+    pub async fn run<T, F, Fut>(&self, name: &str, mut operation: F) -> Result<T, Error>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let mut failed = 0;
+        loop {
+            match operation().await {
+                Ok(value) => return Ok(value),
+                Err(error) if error.is_transient() => {
+                    failed += 1;
+                    if failed >= self.max_attempts {
+                        return Err(Error::Exhausted(Box::new(error)));
+                    }
+                    let delay = self.delay(failed);
+                    warn!("{} - attempt {} failed, retry in {:?}: {:?}", name, failed, delay, error);
+                    sleep(delay).await;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn delay_doubles_after_each_failure() {
+        let policy = RetryPolicy::new(5, Duration::from_secs(1), Duration::from_secs(60));
+
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(6), Duration::from_secs(32));
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let policy = RetryPolicy::new(5, Duration::from_secs(1), Duration::from_secs(60));
+
+        assert_eq!(policy.delay(7), Duration::from_secs(60));
+        // Overflow of multiplier and duration
+        assert_eq!(policy.delay(40), Duration::from_secs(60));
+        assert_eq!(policy.delay(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn transient_errors_are_repeated_until_exhausted() {
+        let policy = RetryPolicy::new(3, Duration::ZERO, Duration::ZERO);
+        let attempts = Cell::new(0);
+
+        let result: Result<(), Error> = policy
+            .run("test", || {
+                attempts.set(attempts.get() + 1);
+                async { Err(Error::Disconnected) }
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Exhausted(error)) if matches!(*error, Error::Disconnected)));
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_are_not_repeated() {
+        let policy = RetryPolicy::new(3, Duration::ZERO, Duration::ZERO);
+        let attempts = Cell::new(0);
+
+        let result: Result<(), Error> = policy
+            .run("test", || {
+                attempts.set(attempts.get() + 1);
+                async { Err(Error::Custom(String::from("nonce too low"))) }
+            })
+            .await;
+
+        assert!(matches!(result, Err(Error::Custom(_))));
+        assert_eq!(attempts.get(), 1);
+    }
+}
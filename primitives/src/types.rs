@@ -1,3 +1,5 @@
+use crate::events::bsc::transfer_id;
+
 pub use substrate_api_client::{BlockNumber, Hash};
 use web3::types::{H256, U64};
 
@@ -6,5 +8,13 @@ use web3::types::{H256, U64};
 pub struct RawEvent {
     pub block_number: Option<U64>,
     pub hash: H256,
+    pub log_index: u64,
     pub data: Vec<u8>,
 }
+
+impl RawEvent {
+    #[must_use]
+    pub fn get_id(&self) -> String {
+        transfer_id(&self.hash, self.log_index)
+    }
+}
@@ -1,194 +1,1056 @@
-use db::Database;
+pub mod signer;
+
+use crate::signer::Signer;
+use db::{Database, NonceManager};
 use primitives::{
-    db::Status,
+    asset::{Asset, AssetKind, AssetRegistry},
+    attestation::{self, Quorum},
+    db::{Settlement, Status, Submission},
     events::{bsc::BscEventType, realis::RealisEventType, traits::Event},
+    retry::RetryPolicy,
     Error,
 };
 
+use frame_support::weights::{DispatchClass, GetDispatchInfo};
 use frame_system::{EventRecord, Phase};
-use runtime::{Address, Block, Event as RuntimeEvent};
+use pallet_balances::Event as BalancesEvent;
+use runtime::{Block, Call, Event as RuntimeEvent, RuntimeBlockLength, RuntimeBlockWeights};
 use rust_lib::healthchecker::HealthChecker;
 use substrate_api_client::{
-    compose_extrinsic_offline,
+    extrinsic::xt_primitives::{GenericAddress, GenericExtra, SignedPayload, UncheckedExtrinsicV4},
     rpc::WsRpcClient,
-    sp_runtime::app_crypto::{sp_core::H256, sr25519},
-    Api, Hash, Pair, XtStatus,
+    sp_runtime::{
+        app_crypto::{
+            sp_core::{crypto::Ss58Codec, H256},
+            sr25519,
+        },
+        codec::Encode,
+        generic::Era,
+        traits::{BlakeTwo256, Hash as _},
+        AccountId32,
+    },
+    AccountInfo, Api, Hash, XtStatus,
 };
 
-use std::sync::Arc;
+use serde_json::{json, Value};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
 
-use log::{error, info};
+use log::{error, info, warn};
 use tokio::{
     select,
-    sync::mpsc::{Receiver, Sender},
+    sync::{
+        mpsc::{Receiver, Sender},
+        Mutex,
+    },
+    time::interval,
 };
 
+/// Name of Realis in database tables
+const CHAIN: &str = "realis";
+/// How often database is checked for transfers missed by channel
+const OUTBOX_INTERVAL: Duration = Duration::from_secs(10);
+/// How often finalized blocks are checked for sent extrinsics
+const FINALITY_INTERVAL: Duration = Duration::from_secs(6);
+/// Finalized blocks after sending, after which extrinsic blocking nonce is sent again
+const STUCK_BLOCKS: u64 = 10;
+/// Transfers sent in one batch extrinsic by default
+const DEFAULT_MAX_BATCH: usize = 1;
+/// How long transfer waits in queue for others by default
+const DEFAULT_BATCH_WAIT: Duration = Duration::from_secs(3);
+/// Part of block limits of normal extrinsic taken by calls of one batch,
+/// the rest is left for batch call itself, signature and extensions
+const BATCH_LIMIT_PERCENT: u64 = 75;
+
+/// Transfer waiting to be sent in batch. It is claimed only when batch is sent,
+/// so if process stops before that, transfer is still in outbox.
+struct Queued {
+    id: String,
+    call: Call,
+    message: BscEventType,
+}
+
+/// Weight and encoded length which calls of one batch extrinsic can take
+#[derive(Debug, Clone, Copy)]
+struct BatchLimits {
+    weight: u64,
+    length: usize,
+}
+
+impl BatchLimits {
+    fn of_runtime() -> Self {
+        let weights = RuntimeBlockWeights::get();
+        let normal = weights.get(DispatchClass::Normal);
+        let weight = normal.max_extrinsic.or(normal.max_total).unwrap_or(weights.max_block);
+        let length = u64::from(*RuntimeBlockLength::get().max.get(DispatchClass::Normal));
+
+        Self {
+            weight: weight / 100 * BATCH_LIMIT_PERCENT,
+            length: usize::try_from(length / 100 * BATCH_LIMIT_PERCENT).unwrap_or(usize::MAX),
+        }
+    }
+
+    /// Split items in order into batches within limits, item which alone exceeds limits is sent alone.
+    fn split<T>(&self, items: Vec<T>, size: impl Fn(&T) -> (u64, usize)) -> Vec<Vec<T>> {
+        let mut batches: Vec<Vec<T>> = Vec::new();
+        let (mut weight, mut length) = (0_u64, 0_usize);
+
+        for item in items {
+            let (item_weight, item_length) = size(&item);
+            match batches.last_mut() {
+                Some(batch)
+                    if weight.saturating_add(item_weight) <= self.weight
+                        && length.saturating_add(item_length) <= self.length =>
+                {
+                    weight += item_weight;
+                    length += item_length;
+                    batch.push(item);
+                }
+                _ => {
+                    weight = item_weight;
+                    length = item_length;
+                    batches.push(vec![item]);
+                }
+            }
+        }
+
+        batches
+    }
+}
+
+/// Extrinsic sent to blockchain and not finalized yet
+struct Tracked {
+    id: String,
+    /// Extrinsic rolls back transfer from Realis
+    rollback: bool,
+    submission: Submission,
+}
+
+impl Tracked {
+    /// Every finalized block after sending was checked, but nonce is already used by other extrinsic
+    fn is_dropped(&self, finalized_nonce: u64) -> bool {
+        self.submission.nonce < finalized_nonce
+    }
+}
+
+/// What tracker does with reserved nonce which isn't used at best block
+#[derive(Debug, PartialEq, Eq)]
+enum Gap {
+    /// Extrinsic could be lost from pool of node
+    Resend,
+    /// Extrinsic waits in pool
+    Wait,
+    /// Nothing was sent with nonce
+    Fill,
+}
+
+impl Gap {
+    /// Same extrinsic can't be executed twice, so it is sent again after restart
+    /// or when it isn't included for `STUCK_BLOCKS` finalized blocks.
+    fn of(submission: Option<&Submission>, head: u64, restart: bool) -> Self {
+        match submission {
+            Some(submission) if restart || submission.block + STUCK_BLOCKS < head => Gap::Resend,
+            Some(_) => Gap::Wait,
+            None => Gap::Fill,
+        }
+    }
+}
+
 pub struct RealisAdapter {
     rx: Receiver<BscEventType>,
     tx: Sender<RealisEventType>,
     health_checker: HealthChecker,
     api: Api<sr25519::Pair, WsRpcClient>,
+    signer: Box<dyn Signer>,
+    nonces: NonceManager,
+    /// Last finalized block checked for sent extrinsics
+    scanned: Mutex<Option<u64>>,
+    /// Transfers waiting to be sent in one batch
+    batch: Mutex<Vec<Queued>>,
+    max_batch: usize,
+    batch_wait: Duration,
+    batch_limits: BatchLimits,
+    /// Repeats sending after transient errors before transfer is rolled back
+    retry: RetryPolicy,
     db: Arc<Database>,
+    /// Attestations needed before release in multi-operator mode
+    quorum: Option<Quorum>,
+    /// Assets and decimals of transfers from BSC
+    assets: Arc<AssetRegistry>,
 }
 
 impl RealisAdapter {
-    #[must_use]
-    /// # Panics
+    /// # Errors
     pub fn new(
         rx: Receiver<BscEventType>,
         tx: Sender<RealisEventType>,
         health_checker: HealthChecker,
         url: &str,
-        master_key: sr25519::Pair,
+        signer: Box<dyn Signer>,
         db: Arc<Database>,
-    ) -> Self {
+    ) -> Result<Self, Error> {
         let client = WsRpcClient::new(url);
-        let api = Api::<sr25519::Pair, WsRpcClient>::new(client)
-            .unwrap()
-            .set_signer(master_key);
-        Self {
+        let api = Api::<sr25519::Pair, WsRpcClient>::new(client).map_err(Error::Api)?;
+        let nonces = NonceManager::new(Arc::clone(&db), CHAIN, signer.account_id().to_ss58check());
+
+        Ok(Self {
             rx,
             tx,
             health_checker,
             api,
+            signer,
+            nonces,
+            scanned: Mutex::new(None),
+            batch: Mutex::new(Vec::new()),
+            max_batch: DEFAULT_MAX_BATCH,
+            batch_wait: DEFAULT_BATCH_WAIT,
+            batch_limits: BatchLimits::of_runtime(),
+            retry: RetryPolicy::default(),
             db,
+            quorum: None,
+            assets: Arc::new(AssetRegistry::default()),
+        })
+    }
+
+    /// Queued transfers are sent when there are `max_batch` of them or after `batch_wait`.
+    #[must_use]
+    pub fn with_batch(mut self, max_batch: usize, batch_wait: Duration) -> Self {
+        self.max_batch = max_batch.max(1);
+        // Timer can't tick with zero period
+        self.batch_wait = batch_wait.max(Duration::from_millis(100));
+        self
+    }
+
+    #[must_use]
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    #[must_use]
+    pub fn with_quorum(mut self, quorum: Quorum) -> Self {
+        self.quorum = Some(quorum);
+        self
+    }
+
+    #[must_use]
+    pub fn with_assets(mut self, assets: Arc<AssetRegistry>) -> Self {
+        self.assets = assets;
+        self
+    }
+
+    /// Decimals of Realis balance from chain properties.
+    /// # Errors
+    pub fn token_decimals(&self) -> Result<u8, Error> {
+        let properties = self
+            .api
+            .get_request(json!({
+                "method": "system_properties",
+                "params": [],
+                "jsonrpc": "2.0",
+                "id": "1",
+            }))
+            .map_err(Error::Api)?
+            .ok_or_else(|| Error::Custom(String::from("Missing chain properties!")))?;
+        let properties: Value = serde_json::from_str(&properties).map_err(Error::SerdeJSON)?;
+
+        // Chain with several tokens lists decimals of each, native token is first
+        let decimals = match &properties["tokenDecimals"] {
+            Value::Array(decimals) => decimals.first(),
+            decimals => Some(decimals),
         }
+        .and_then(Value::as_u64)
+        .ok_or_else(|| Error::Custom(format!("Missing token decimals in chain properties: {}", properties)))?;
+
+        u8::try_from(decimals).map_err(|_| Error::Custom(format!("Invalid token decimals: {}", decimals)))
     }
 
     /// # Panics
     /// # Errors
     pub async fn handle(mut self) {
+        if let Err(error) = self.recover().await {
+            error!("[Realis Adapter] - recover interrupted transfers: {:?}", error);
+            self.health_checker.make_sick();
+            return;
+        }
+
+        let mut outbox = interval(OUTBOX_INTERVAL);
+        let mut finality = interval(FINALITY_INTERVAL);
+        let mut batch = interval(self.batch_wait);
         loop {
             let health_checker = self.health_checker.clone();
             select! {
                 () = health_checker.is_alive() => break,
                 option = self.rx.recv() => {
                     if let Some(message) = option {
-                        match self.execute(&message).await {
-                            Ok(_) => {
-                                info!("Success send transaction to BSC!");
-                            }
-                            Err(error) => {
-                                let rollback_request = match message {
-                                    BscEventType::TransferNftToRealis(request, ..) => {
-                                        Some(RealisEventType::TransferNftToRealisFail(request))
-                                    }
-                                    BscEventType::TransferTokenToRealis(request, ..) => {
-                                        Some(RealisEventType::TransferTokenToRealisFail(request))
-                                    }
-                                    // If rollback request fail
-                                    _ => None
-                                };
-                                if let Some(rollback_request) = rollback_request {
-                                    if let Err(error) =  self.tx.send(rollback_request).await {
-                                        error!("[Realis Adapter] - send error: {:?}", error);
-                                        self.health_checker.make_sick();
-                                    }
-                                } else {
-                                    error!("Rollback fail: {:?}", error);
-                                    self.health_checker.make_sick();
-                                }
+                        self.handle_message(message).await;
+                    }
+                }
+                _ = outbox.tick() => {
+                    match self.get_outbox().await {
+                        Ok(messages) => {
+                            for message in messages {
+                                self.handle_message(message).await;
                             }
                         }
+                        Err(error) => error!("[Realis Adapter] - load outbox: {:?}", error),
+                    }
+                }
+                _ = batch.tick() => self.flush().await,
+                _ = finality.tick() => {
+                    if let Err(error) = self.track(false).await {
+                        error!("[Realis Adapter] - track sent extrinsics: {:?}", error);
                     }
                 }
             }
         }
     }
 
+    /// Transfers interrupted by restart before extrinsic was saved are returned to queue,
+    /// saved extrinsics are tracked again and sent once more if node doesn't know them.
+    async fn recover(&self) -> Result<(), Error> {
+        // Extrinsic is saved before sending, so without it nothing was sent
+        for (id, submission) in self.db.get_submissions_bsc(Status::InProgress).await? {
+            if submission.is_none() {
+                warn!("[Realis Adapter] - recover transfer {} with status {:?}", id, Status::Got);
+                self.db.update_status_bsc(&id, Status::Got).await?;
+            }
+        }
+        for (id, submission) in self.db.get_submissions_realis(Status::RollbackInProgress).await? {
+            if submission.is_none() {
+                warn!("[Realis Adapter] - recover rollback {} with status {:?}", id, Status::Error);
+                self.db.update_status_realis(&id, Status::Error).await?;
+            }
+        }
+
+        self.track(true).await
+    }
+
+    /// Find results of sent extrinsics in finalized blocks. Extrinsic which nonce
+    /// was used by other one is returned to queue. Extrinsic which blocks account nonce
+    /// for long time is sent again, reserved but unused nonces are filled with remark.
+    async fn track(&self, restart: bool) -> Result<(), Error> {
+        let (head_hash, head) = self.finalized_head()?;
+        let head = u64::from(head);
+
+        let mut tracked: HashMap<Hash, Vec<Tracked>> = HashMap::new();
+        // Without submission extrinsic is being signed right now
+        for (id, submission) in self.db.get_submissions_bsc(Status::InProgress).await? {
+            if let Some(submission) = submission {
+                let xt_hash = Self::xt_hash(&submission)?;
+                tracked.entry(xt_hash).or_default().push(Tracked { id, rollback: false, submission });
+            }
+        }
+        for (id, submission) in self.db.get_submissions_realis(Status::RollbackInProgress).await? {
+            if let Some(submission) = submission {
+                let xt_hash = Self::xt_hash(&submission)?;
+                tracked.entry(xt_hash).or_default().push(Tracked { id, rollback: true, submission });
+            }
+        }
+
+        let mut scanned = self.scanned.lock().await;
+        let from = match *scanned {
+            Some(scanned) => scanned + 1,
+            None => tracked
+                .values()
+                .flatten()
+                .map(|tracked| tracked.submission.block)
+                .min()
+                .unwrap_or(head),
+        };
+        for number in from..=head {
+            if !tracked.is_empty() {
+                let found = self.find_extrinsics(self.get_block_hash(number)?, &tracked)?;
+                for (xt_hash, settlement, interrupted) in found {
+                    for tracked in tracked.remove(&xt_hash).unwrap_or_default() {
+                        self.settle(&tracked, &settlement, interrupted).await?;
+                    }
+                }
+            }
+            *scanned = Some(number);
+        }
+        drop(scanned);
+
+        // Every finalized block after sending is checked, so extrinsic will never be included
+        let finalized = u64::from(self.get_nonce(Some(head_hash))?);
+        let (dropped, tracked): (Vec<_>, Vec<_>) = tracked
+            .into_values()
+            .flatten()
+            .partition(|tracked| tracked.is_dropped(finalized));
+        for tracked in dropped {
+            self.retry(&tracked).await?;
+        }
+
+        let tracked = tracked
+            .into_iter()
+            .map(|tracked| (tracked.submission.nonce, tracked.submission))
+            .collect::<HashMap<_, _>>();
+        for nonce in self.nonces.gaps(u64::from(self.get_nonce(None)?)).await? {
+            let submission = tracked.get(&nonce);
+            match (Gap::of(submission, head, restart), submission) {
+                (Gap::Resend, Some(submission)) => {
+                    warn!("[Realis Adapter] - resend extrinsic {} with nonce {}", submission.tx_hash, nonce);
+                    if let Err(error) = self.send_to_blockchain(submission) {
+                        warn!("[Realis Adapter] - resend {}: {:?}", submission.tx_hash, error);
+                    }
+                }
+                (Gap::Fill, _) => {
+                    warn!("[Realis Adapter] - fill unused nonce {}", nonce);
+                    self.fill_nonce(nonce).await?;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Calls of interrupted batch are executed only before failed one, the rest are sent again.
+    async fn settle(
+        &self,
+        tracked: &Tracked,
+        settlement: &Settlement,
+        interrupted: Option<u32>,
+    ) -> Result<(), Error> {
+        let settlement = &match batch_outcome(settlement, tracked.submission.batch_index, interrupted) {
+            Some(settlement) => settlement,
+            None => return self.retry(tracked).await,
+        };
+
+        if tracked.rollback {
+            let status = if settlement.success {
+                Status::RollbackSuccess
+            } else {
+                Status::RollbackError
+            };
+            info!("[Realis Adapter] - rollback {} finalized with status {:?}", tracked.id, status);
+            self.db.settle_realis(&tracked.id, status, settlement).await?;
+            if !settlement.success {
+                let error = Error::Custom(format!("Rollback extrinsic {} failed", settlement.tx_hash));
+                if let Err(error) = self.db.set_attempt_error(&settlement.tx_hash, &error.to_string()).await {
+                    error!("[Realis Adapter] - logging attempt error to db: {:?}", error);
+                    self.health_checker.make_sick();
+                }
+                self.dead_letter(&tracked.id, &error).await;
+            }
+            Ok(())
+        } else {
+            // Failed transfer is rolled back by BSC adapter
+            let status = if settlement.success { Status::Success } else { Status::Error };
+            info!("[Realis Adapter] - transfer {} finalized with status {:?}", tracked.id, status);
+            self.db.settle_bsc(&tracked.id, status, settlement).await
+        }
+    }
+
+    async fn retry(&self, tracked: &Tracked) -> Result<(), Error> {
+        if tracked.rollback {
+            warn!("[Realis Adapter] - rollback {} wasn't executed, retry", tracked.id);
+            self.db.update_status_realis(&tracked.id, Status::Error).await
+        } else {
+            warn!("[Realis Adapter] - transfer {} wasn't executed, retry", tracked.id);
+            self.db.update_status_bsc(&tracked.id, Status::Got).await
+        }
+    }
+
+    /// Load all work stored in database: transfers from BSC which
+    /// wasn't sent yet and transfers from Realis which should be rolled back.
+    async fn get_outbox(&self) -> Result<Vec<BscEventType>, Error> {
+        let mut messages = self.db.get_transfers_bsc(Status::Got).await?;
+        messages.extend(
+            self.db
+                .get_transfers_realis(Status::Error)
+                .await?
+                .into_iter()
+                .filter_map(|event| match event {
+                    RealisEventType::TransferTokenToBsc(event) => {
+                        Some(BscEventType::TransferTokenToBscFail(event))
+                    }
+                    RealisEventType::TransferNftToBsc(event) => Some(BscEventType::TransferNftToBscFail(event)),
+                    _ => None,
+                }),
+        );
+
+        Ok(messages)
+    }
+
+    async fn handle_message(&self, message: BscEventType) {
+        match self.execute(&message).await {
+            Ok(_) => {
+                info!("[Realis Adapter] - request accepted, wait for finalization");
+            }
+            Err(Error::Unconfirmed(error)) => {
+                error!("[Realis Adapter] - extrinsic result is unknown, it is tracked: {}", error);
+            }
+            // Nothing was claimed yet, request stays in outbox
+            Err(error) if error.is_transient() => {
+                warn!("[Realis Adapter] - request will be repeated: {:?}", error);
+            }
+            Err(error) => self.request_rollback(message, &error).await,
+        }
+    }
+
+    /// Transfer which can't be sent is returned to BSC.
+    async fn request_rollback(&self, message: BscEventType, error: &Error) {
+        let rollback_id = match &message {
+            BscEventType::TransferNftToBscFail(event) => event.get_hash(),
+            BscEventType::TransferTokenToBscFail(event) => event.get_hash(),
+            _ => String::new(),
+        };
+        let rollback_request = match message {
+            BscEventType::TransferNftToRealis(request, ..) => {
+                Some(RealisEventType::TransferNftToRealisFail(request))
+            }
+            BscEventType::TransferTokenToRealis(request, ..) => {
+                Some(RealisEventType::TransferTokenToRealisFail(request))
+            }
+            // If rollback request fail
+            _ => None,
+        };
+        if let Some(rollback_request) = rollback_request {
+            if let Err(error) = self.tx.send(rollback_request).await {
+                error!("[Realis Adapter] - send error: {:?}", error);
+                self.health_checker.make_sick();
+            }
+        } else {
+            self.dead_letter(&rollback_id, error).await;
+        }
+    }
+
+    /// Failed rollback is kept for operator, bridge continues with other transfers.
+    /// Rollback which failed before it was claimed is halted too, so outbox doesn't repeat it.
+    async fn dead_letter(&self, id: &str, error: &Error) {
+        error!("[Realis Adapter] - rollback {} failed, moved to dead letters: {}", id, error);
+        if let Err(error) = self.db.add_dead_letter_realis(id, &error.to_string()).await {
+            error!("[Realis Adapter] - logging dead letter to db: {:?}", error);
+            self.health_checker.make_sick();
+        }
+    }
+
     async fn execute(&self, request: &BscEventType) -> Result<(), Error> {
         match request {
-            BscEventType::TransferTokenToRealis(event) => self.process(event).await,
-            BscEventType::TransferNftToRealis(event) => self.process(event).await,
-            BscEventType::TransferTokenToBscFail(event) => self.rollback(event).await,
-            BscEventType::TransferNftToBscFail(event) => self.rollback(event).await,
-        }
-    }
-
-    async fn process(&self, event: &impl Event) -> Result<(), Error> {
-        self.db.update_status_bsc(&event.get_hash(), Status::InProgress).await?;
-        let tx_result = self.send_to_blockchain(event);
-        if let Err(error) = self
-            .db
-            .update_status_bsc(
-                &event.get_hash(),
-                tx_result.as_ref().map(|_| Status::Success).unwrap_or(Status::Error),
-            )
-            .await
-        {
-            error!("[Realis Adapter] - logging status to db: {:?}", error);
-            self.health_checker.make_sick();
+            BscEventType::TransferTokenToRealis(event) => {
+                let asset = self.assets.bsc_asset(AssetKind::Token, event.contract);
+                self.process(event, asset, request).await
+            }
+            BscEventType::TransferNftToRealis(event) => {
+                let asset = self.assets.bsc_asset(AssetKind::Nft, event.contract);
+                self.process(event, asset, request).await
+            }
+            BscEventType::TransferTokenToBscFail(event) => {
+                self.rollback(event, self.assets.realis_asset(AssetKind::Token)?).await
+            }
+            BscEventType::TransferNftToBscFail(event) => {
+                self.rollback(event, self.assets.realis_asset(AssetKind::Nft)?).await
+            }
         }
+    }
 
-        tx_result
+    /// Transfer is released only when enough operators attested it.
+    /// Until then it stays in outbox and is checked again.
+    async fn is_attested(&self, id: &str, call: &Call) -> Result<bool, Error> {
+        let quorum = match &self.quorum {
+            Some(quorum) => quorum,
+            None => return Ok(true),
+        };
+
+        let digest = attestation::realis_call_digest(id, call);
+        let attestations = self.db.get_attestations(id).await?;
+        if quorum.is_reached(digest, &attestations) {
+            return Ok(true);
+        }
+
+        let count = quorum.count(digest, &attestations);
+        info!("[Realis Adapter] - transfer {} has {}/{} attestations", id, count, quorum.threshold());
+        Ok(false)
+    }
+
+    /// In multi-operator mode transfer is submitted by its leader, other operators take it over
+    /// when it waits too long. Claim in shared database keeps transfer sent once.
+    async fn is_submitter(&self, id: &str) -> Result<bool, Error> {
+        let quorum = match &self.quorum {
+            Some(quorum) if !quorum.is_leader(id) => quorum,
+            _ => return Ok(true),
+        };
+
+        let waited = self.db.get_attestation_age(id).await?.unwrap_or_default();
+        Ok(quorum.may_submit(id, waited))
+    }
+
+    /// Transfer is queued and sent with others in one batch, invalid transfer is claimed right away.
+    async fn process(
+        &self,
+        event: &impl Event,
+        asset: Result<&Asset, Error>,
+        message: &BscEventType,
+    ) -> Result<(), Error> {
+        let id = event.get_hash();
+        if !self.is_submitter(&id).await? {
+            return Ok(());
+        }
+        // Transfer which can't be converted is rejected without attestations
+        let call = asset.and_then(|asset| self.assets.release_call(event, asset));
+        let call = match call {
+            Ok(call) => call,
+            Err(error) => {
+                if !self.db.claim_bsc(&id, Status::Got, Status::InProgress).await? {
+                    warn!("[Realis Adapter] - skip transfer in progress, settled or halted: {}", id);
+                    return Ok(());
+                }
+                if let Err(error) = self.db.update_status_bsc(&id, Status::Error).await {
+                    error!("[Realis Adapter] - logging status to db: {:?}", error);
+                    self.health_checker.make_sick();
+                }
+                return Err(error);
+            }
+        };
+        if !self.is_attested(&id, &call).await? {
+            return Ok(());
+        }
+
+        let full = {
+            let mut batch = self.batch.lock().await;
+            // Transfer from outbox could be queued from channel already
+            if batch.iter().any(|queued| queued.id == id) {
+                return Ok(());
+            }
+            batch.push(Queued {
+                id,
+                call,
+                message: message.clone(),
+            });
+            batch.len() >= self.max_batch
+        };
+        if full {
+            self.flush().await;
+        }
+
+        Ok(())
+    }
+
+    /// Claim queued transfers and send them, several of them are sent in one `utility.batch` extrinsic.
+    async fn flush(&self) {
+        let queued = std::mem::take(&mut *self.batch.lock().await);
+
+        let mut claimed = Vec::new();
+        for queued in queued {
+            match self.db.claim_bsc(&queued.id, Status::Got, Status::InProgress).await {
+                Ok(true) => claimed.push(queued),
+                Ok(false) => {
+                    warn!("[Realis Adapter] - skip transfer in progress, settled or halted: {}", queued.id);
+                }
+                // Transfer stays in outbox
+                Err(error) => error!("[Realis Adapter] - claim transfer {}: {:?}", queued.id, error),
+            }
+        }
+
+        let batches = self.batch_limits.split(claimed, |queued| {
+            (queued.call.get_dispatch_info().weight, queued.call.encode().len())
+        });
+        for queued in batches {
+            self.send_queued(queued).await;
+        }
     }
 
-    async fn rollback(&self, event: &impl Event) -> Result<(), Error> {
-        let tx_result = self.send_to_blockchain(event);
-        if let Err(error) = self
-            .db
-            .update_status_realis(
-                &event.get_hash(),
-                tx_result
-                    .as_ref()
-                    .map(|_| Status::RollbackSuccess)
-                    .unwrap_or(Status::RollbackError),
-            )
+    async fn send_queued(&self, queued: Vec<Queued>) {
+        match self
+            .retry
+            .run("[Realis Adapter] - send batch", || self.send_batch(&queued))
             .await
         {
+            Ok(()) => info!("[Realis Adapter] - sent {} transfers, wait for finalization", queued.len()),
+            // Transfers stay in progress until extrinsic is finalized
+            Err(Error::Unconfirmed(error)) => {
+                error!("[Realis Adapter] - extrinsic result is unknown, it is tracked: {}", error);
+            }
+            Err(error) => {
+                error!("[Realis Adapter] - send {} transfers: {:?}", queued.len(), error);
+                for queued in queued {
+                    if let Err(error) = self.db.update_status_bsc(&queued.id, Status::Error).await {
+                        error!("[Realis Adapter] - logging status to db: {:?}", error);
+                        self.health_checker.make_sick();
+                    }
+                    self.request_rollback(queued.message, &error).await;
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    async fn send_batch(&self, queued: &[Queued]) -> Result<(), Error> {
+        let call = match queued {
+            [queued] => queued.call.clone(),
+            queued => Call::Utility(pallet_utility::Call::batch(
+                queued.iter().map(|queued| queued.call.clone()).collect(),
+            )),
+        };
+        let submission = self.sign(call).await?;
+
+        let mut result = Ok(());
+        for (index, queued_transfer) in queued.iter().enumerate() {
+            let submission = Submission {
+                batch_index: (queued.len() > 1).then(|| index as u32),
+                ..submission.clone()
+            };
+            result = self.db.set_submission_bsc(&queued_transfer.id, &submission).await;
+            if result.is_err() {
+                break;
+            }
+        }
+        let result = result.and_then(|()| self.send_to_blockchain(&submission));
+        // Nonce of extrinsic which wasn't sent is filled by tracker
+        self.nonces.complete(submission.nonce).await;
+
+        result
+    }
+
+    async fn rollback(&self, event: &impl Event, asset: &Asset) -> Result<(), Error> {
+        let id = event.get_hash();
+        let call = event.get_realis_call(&self.assets.decimals(asset)?)?;
+
+        if !self.db.claim_realis(&id, Status::Error, Status::RollbackInProgress).await? {
+            warn!("[Realis Adapter] - skip rollback in progress or settled: {}", id);
+            return Ok(());
+        }
+
+        let tx_result = self
+            .retry
+            .run("[Realis Adapter] - send rollback", || self.send_rollback(&id, call.clone()))
+            .await;
+
+        let logged = match &tx_result {
+            // Rollback stays in progress until extrinsic is finalized
+            Ok(()) | Err(Error::Unconfirmed(_)) => Ok(()),
+            Err(_) => self.db.update_status_realis(&event.get_hash(), Status::RollbackError).await,
+        };
+        if let Err(error) = logged {
             error!("[Realis Adapter] - logging status to db: {:?}", error);
             self.health_checker.make_sick();
         }
+
         tx_result
     }
 
-    fn send_to_blockchain(&self, event: &impl Event) -> Result<(), Error> {
-        let tx = compose_extrinsic_offline!(
-            self.api.signer.clone().unwrap(),
-            event.get_realis_call(),
-            self.api.get_nonce().unwrap(),
-            Era::Immortal,
-            self.api.genesis_hash,
-            self.api.genesis_hash,
-            self.api.runtime_version.spec_version,
-            self.api.runtime_version.transaction_version
+    async fn send_rollback(&self, id: &str, call: Call) -> Result<(), Error> {
+        let submission = self.sign(call).await?;
+
+        // Rollbacks aren't batched, so every extrinsic is an attempt of one transfer
+        let result = match self.db.add_attempt(id, CHAIN, &submission, None).await {
+            Ok(()) => self.db.set_submission_realis(id, &submission).await,
+            Err(error) => Err(error),
+        };
+        let result = result.and_then(|()| self.send_to_blockchain(&submission));
+        // Nonce of extrinsic which wasn't sent is filled by tracker
+        self.nonces.complete(submission.nonce).await;
+
+        result
+    }
+
+    /// Build and sign extrinsic with next reserved nonce, nothing is sent to blockchain yet.
+    async fn sign(&self, call: Call) -> Result<Submission, Error> {
+        let nonce = self.nonces.reserve(u64::from(self.get_nonce(None)?)).await?;
+
+        match self.sign_with_nonce(call, nonce).await {
+            Ok(submission) => Ok(submission),
+            Err(error) => {
+                self.nonces.release(nonce).await?;
+                Err(error)
+            }
+        }
+    }
+
+    async fn sign_with_nonce(&self, call: Call, nonce: u64) -> Result<Submission, Error> {
+        let (_, block) = self.finalized_head()?;
+
+        let extra = GenericExtra::new(Era::Immortal, account_index(nonce)?);
+        let payload = SignedPayload::from_raw(
+            call.clone(),
+            extra.clone(),
+            (
+                self.api.runtime_version.spec_version,
+                self.api.runtime_version.transaction_version,
+                self.api.genesis_hash,
+                self.api.genesis_hash,
+                (),
+                (),
+                (),
+            ),
+        );
+        // Payload longer than 256 bytes is hashed before signing
+        let signature = self.signer.sign(&payload.using_encoded(<[u8]>::to_vec)).await?;
+        let tx = UncheckedExtrinsicV4::new_signed(
+            call,
+            GenericAddress::from(self.signer.account_id()),
+            signature.into(),
+            extra,
         );
 
-        let hash = self
-            .api
-            .send_extrinsic(tx.hex_encode(), XtStatus::Finalized)
-            .map_err(Error::Api)?;
+        Ok(Submission {
+            tx_hash: format!("{:?}", BlakeTwo256::hash_of(&tx)),
+            raw: tx.hex_encode(),
+            nonce,
+            block: u64::from(block),
+            batch_index: None,
+        })
+    }
+
+    /// Send empty remark, so nonce is used.
+    async fn fill_nonce(&self, nonce: u64) -> Result<(), Error> {
+        let submission = self
+            .sign_with_nonce(Call::System(frame_system::Call::remark(Vec::new())), nonce)
+            .await?;
 
-        self.check_extrinsic(hash)
+        self.send_to_blockchain(&submission)
     }
 
-    fn check_extrinsic(&self, block_hash: Option<Hash>) -> Result<(), Error> {
+    /// Extrinsic is only put to transaction pool, its result is found by tracker.
+    fn send_to_blockchain(&self, submission: &Submission) -> Result<(), Error> {
+        self.api
+            .send_extrinsic(submission.raw.clone(), XtStatus::Ready)
+            .map(|_| ())
+            .map_err(|error| Error::Unconfirmed(format!("{:?}", error)))
+    }
+
+    fn xt_hash(submission: &Submission) -> Result<Hash, Error> {
+        Hash::from_str(&submission.tx_hash).map_err(|error| Error::Custom(format!("{:?}", error)))
+    }
+
+    /// Results of tracked extrinsics included in given block,
+    /// with index of failed call if batch extrinsic was interrupted.
+    fn find_extrinsics(
+        &self,
+        block_hash: Hash,
+        tracked: &HashMap<Hash, Vec<Tracked>>,
+    ) -> Result<Vec<(Hash, Settlement, Option<u32>)>, Error> {
         let block = self
             .api
-            .get_block::<Block>(block_hash)
+            .get_block::<Block>(Some(block_hash))
             .map_err(Error::Api)?
             .ok_or_else(|| Error::Custom(String::from("Missing block!")))?;
 
+        #[allow(clippy::cast_possible_truncation)]
+        let included = block
+            .extrinsics
+            .iter()
+            .enumerate()
+            .map(|(index, xt)| (index as u32, BlakeTwo256::hash_of(xt)))
+            .filter(|(_, xt_hash)| tracked.contains_key(xt_hash))
+            .collect::<Vec<_>>();
+        if included.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let events = self
             .api
-            .get_storage_value::<Vec<EventRecord<RuntimeEvent, H256>>>("System", "Events", block_hash)
+            .get_storage_value::<Vec<EventRecord<RuntimeEvent, H256>>>("System", "Events", Some(block_hash))
             .map_err(Error::Api)?
             .ok_or_else(|| Error::Custom(String::from("Missing events!")))?;
+        let timestamp = self
+            .api
+            .get_storage_value::<u64>("Timestamp", "Now", Some(block_hash))
+            .map_err(Error::Api)?;
+        let account = self.signer.account_id();
 
-        for event in events {
-            if let RuntimeEvent::System(frame_system::Event::ExtrinsicSuccess(_)) = event.event {
-                if let Phase::ApplyExtrinsic(index) = event.phase {
-                    let xt = block.extrinsics.get(index as usize).unwrap();
-                    if xt.signature.is_some() {
-                        if let Address::Id(account_id) = &xt.signature.as_ref().unwrap().0 {
-                            if account_id.clone() == self.api.signer_account().unwrap() {
-                                return Ok(());
-                            }
-                        }
-                    }
-                }
-            }
+        included
+            .into_iter()
+            .map(|(index, xt_hash)| {
+                let events = events
+                    .iter()
+                    .filter(|event| {
+                        matches!(event.phase, Phase::ApplyExtrinsic(extrinsic_index) if extrinsic_index == index)
+                    })
+                    .collect::<Vec<_>>();
+                let interrupted = events.iter().find_map(|event| match &event.event {
+                    RuntimeEvent::Utility(pallet_utility::Event::BatchInterrupted(index, _)) => Some(*index),
+                    _ => None,
+                });
+
+                events
+                    .iter()
+                    .find_map(|event| match &event.event {
+                        RuntimeEvent::System(frame_system::Event::ExtrinsicSuccess(info)) => Some((true, info)),
+                        RuntimeEvent::System(frame_system::Event::ExtrinsicFailed(_, info)) => Some((false, info)),
+                        _ => None,
+                    })
+                    .map(|(success, info)| {
+                        let settlement = Settlement {
+                            tx_hash: format!("{:?}", xt_hash),
+                            block: u64::from(block.header.number),
+                            gas_used: Some(info.weight.to_string()),
+                            fee: Self::fee(&events, &account),
+                            timestamp,
+                            success,
+                        };
+                        (xt_hash, settlement, interrupted)
+                    })
+                    .ok_or_else(|| Error::Custom(String::from("Not confirmation found")))
+            })
+            .collect()
+    }
+
+    /// Fee paid by bridge account for extrinsic with given events. Transaction payment pallet
+    /// of runtime has no events, fee is withdrawn before dispatch and its unused part is deposited back.
+    fn fee(events: &[&EventRecord<RuntimeEvent, H256>], account: &AccountId32) -> Option<String> {
+        let withdrawn = events.iter().find_map(|event| match &event.event {
+            RuntimeEvent::Balances(BalancesEvent::Withdraw(who, amount)) if who == account => Some(*amount),
+            _ => None,
+        })?;
+
+        let fee = events
+            .iter()
+            .filter_map(|event| match &event.event {
+                RuntimeEvent::Balances(BalancesEvent::Deposit(who, amount)) if who == account => Some(*amount),
+                _ => None,
+            })
+            .fold(withdrawn, |fee, refund| fee.saturating_sub(refund));
+
+        Some(fee.to_string())
+    }
+
+    fn finalized_head(&self) -> Result<(Hash, u32), Error> {
+        let hash = self
+            .api
+            .get_finalized_head()
+            .map_err(Error::Api)?
+            .ok_or_else(|| Error::Custom(String::from("Missing finalized head!")))?;
+        let block = self
+            .api
+            .get_block::<Block>(Some(hash))
+            .map_err(Error::Api)?
+            .ok_or_else(|| Error::Custom(String::from("Missing block!")))?;
+
+        Ok((hash, block.header.number))
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn get_block_hash(&self, number: u64) -> Result<Hash, Error> {
+        self.api
+            .get_storage_map("System", "BlockHash", number as u32, None)
+            .map_err(Error::Api)?
+            .ok_or_else(|| Error::Custom(format!("Missing block {}!", number)))
+    }
+
+    /// Nonce of bridge account at given block, or at best block if `None`
+    fn get_nonce(&self, at: Option<Hash>) -> Result<u32, Error> {
+        self.api
+            .get_storage_map::<_, AccountInfo>("System", "Account", self.signer.account_id(), at)
+            .map_err(Error::Api)
+            .map(|info| info.map_or(0, |info| info.nonce))
+    }
+}
+
+/// Settlement of one call of batch extrinsic, `None` if call wasn't executed because
+/// call with `interrupted` index failed before it. Calls before failed one are executed.
+fn batch_outcome(
+    settlement: &Settlement,
+    batch_index: Option<u32>,
+    interrupted: Option<u32>,
+) -> Option<Settlement> {
+    match (batch_index, interrupted) {
+        (Some(index), Some(interrupted)) if index > interrupted => None,
+        (Some(index), Some(interrupted)) => Some(Settlement {
+            success: settlement.success && index < interrupted,
+            ..settlement.clone()
+        }),
+        _ => Some(settlement.clone()),
+    }
+}
+
+/// Nonce of Realis account is u32, nonce reserved above it would be signed truncated.
+fn account_index(nonce: u64) -> Result<u32, Error> {
+    u32::try_from(nonce).map_err(|_| Error::Custom(format!("Nonce {} overflows Realis account index", nonce)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submission(nonce: u64, block: u64) -> Submission {
+        Submission {
+            tx_hash: format!("{:?}", Hash::repeat_byte(1)),
+            raw: String::from("0x00"),
+            nonce,
+            block,
+            batch_index: None,
         }
-        Err(Error::Custom(String::from("Not confirmation found")))
+    }
+
+    #[test]
+    fn extrinsic_is_dropped_when_nonce_is_used_at_finalized_block() {
+        let tracked = Tracked {
+            id: String::from("0x01"),
+            rollback: false,
+            submission: submission(5, 100),
+        };
+
+        assert!(!tracked.is_dropped(5));
+        assert!(tracked.is_dropped(6));
+    }
+
+    #[test]
+    fn stuck_extrinsic_is_resent() {
+        let sent = submission(5, 100);
+
+        assert_eq!(Gap::of(Some(&sent), 100 + STUCK_BLOCKS, false), Gap::Wait);
+        assert_eq!(Gap::of(Some(&sent), 101 + STUCK_BLOCKS, false), Gap::Resend);
+        // Pool of node could be lost while bridge was down
+        assert_eq!(Gap::of(Some(&sent), 100, true), Gap::Resend);
+    }
+
+    #[test]
+    fn unused_nonce_is_filled() {
+        assert_eq!(Gap::of(None, 100, false), Gap::Fill);
+        assert_eq!(Gap::of(None, 100, true), Gap::Fill);
+    }
+
+    fn settlement(success: bool) -> Settlement {
+        Settlement {
+            tx_hash: format!("{:?}", Hash::repeat_byte(1)),
+            block: 100,
+            gas_used: None,
+            fee: None,
+            timestamp: None,
+            success,
+        }
+    }
+
+    #[test]
+    fn interrupted_batch_is_settled_up_to_failed_call() {
+        let included = settlement(true);
+
+        assert!(batch_outcome(&included, Some(0), Some(1)).unwrap().success);
+        assert!(!batch_outcome(&included, Some(1), Some(1)).unwrap().success);
+        // Call after failed one isn't executed, transfer goes back to queue
+        assert!(batch_outcome(&included, Some(2), Some(1)).is_none());
+    }
+
+    #[test]
+    fn single_extrinsic_and_completed_batch_keep_result() {
+        assert!(batch_outcome(&settlement(true), None, None).unwrap().success);
+        assert!(!batch_outcome(&settlement(false), None, None).unwrap().success);
+        assert!(batch_outcome(&settlement(true), Some(3), None).unwrap().success);
+        assert!(!batch_outcome(&settlement(false), Some(0), Some(1)).unwrap().success);
+    }
+
+    #[test]
+    fn batches_fit_into_limits() {
+        let limits = BatchLimits { weight: 100, length: 10 };
+        let split = |items: Vec<(u64, usize)>| {
+            limits
+                .split(items.into_iter().enumerate().collect(), |(_, size)| *size)
+                .into_iter()
+                .map(|batch| batch.into_iter().map(|(index, _)| index).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+
+        assert!(split(Vec::new()).is_empty());
+        assert_eq!(split(vec![(40, 2), (60, 2), (1, 1)]), vec![vec![0, 1], vec![2]]);
+        assert_eq!(split(vec![(10, 5), (10, 5), (10, 1)]), vec![vec![0, 1], vec![2]]);
+        // Call which alone exceeds limits is sent alone
+        assert_eq!(split(vec![(10, 1), (500, 1), (10, 1)]), vec![vec![0], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn nonce_above_account_index_is_rejected() {
+        assert_eq!(account_index(u64::from(u32::MAX)).unwrap(), u32::MAX);
+        assert!(matches!(account_index(u64::from(u32::MAX) + 1), Err(Error::Custom(_))));
     }
 }
@@ -0,0 +1,269 @@
+use async_trait::async_trait;
+use log::info;
+use primitives::{key_file, Error};
+use serde_json::{json, Value};
+use std::{convert::TryFrom, fs, path::Path};
+use substrate_api_client::{
+    sp_runtime::{
+        app_crypto::{
+            sp_core::crypto::Ss58Codec,
+            sr25519::{self, Public, Signature},
+        },
+        AccountId32,
+    },
+    Pair,
+};
+use xsalsa20poly1305::{
+    aead::{Aead, NewAead},
+    Key, Nonce, XSalsa20Poly1305,
+};
+
+/// Signs Realis extrinsics on behalf of bridge account
+#[async_trait]
+pub trait Signer: Send + Sync {
+    /// Account which pays for extrinsics
+    fn public(&self) -> Public;
+
+    fn account_id(&self) -> AccountId32 {
+        AccountId32::from(self.public().0)
+    }
+
+    /// Sign encoded payload of extrinsic (already hashed if it is longer than 256 bytes).
+    /// # Errors
+    async fn sign(&self, payload: &[u8]) -> Result<Signature, Error>;
+}
+
+/// Signs with key held in memory of bridge process
+pub struct PairSigner {
+    pair: sr25519::Pair,
+}
+
+impl PairSigner {
+    #[must_use]
+    pub fn new(pair: sr25519::Pair) -> Self {
+        Self { pair }
+    }
+
+    /// Load key from file with mnemonic, hex seed or secret URI,
+    /// file must not be accessible by group or others.
+    /// # Errors
+    pub fn from_seed_file(path: &Path) -> Result<Self, Error> {
+        info!("[Realis Adapter] - load master key from seed file {:?}", path);
+        key_file::check_permissions(path)?;
+        let seed =
+            fs::read_to_string(path).map_err(|error| Error::FileNotFound(format!("{:?}: {}", path, error)))?;
+
+        sr25519::Pair::from_string(seed.trim(), None)
+            .map(Self::new)
+            .map_err(|_| Error::Custom(format!("Invalid seed in {:?}", path)))
+    }
+
+    /// Load key from JSON exported by polkadot.js (`scrypt` + `xsalsa20-poly1305`, `pkcs8` encoded).
+    /// # Errors
+    pub fn from_keystore(path: &Path, password: &str) -> Result<Self, Error> {
+        info!("[Realis Adapter] - load master key from keystore {:?}", path);
+        let content =
+            fs::read_to_string(path).map_err(|error| Error::FileNotFound(format!("{:?}: {}", path, error)))?;
+        let keystore = serde_json::from_str::<Value>(&content).map_err(Error::SerdeJSON)?;
+
+        let encoding = &keystore["encoding"];
+        if encoding["content"] != json!(["pkcs8", "sr25519"])
+            || encoding["type"] != json!(["scrypt", "xsalsa20-poly1305"])
+        {
+            return Err(Error::Custom(format!("Unsupported keystore encoding: {}", encoding)));
+        }
+        let encoded = keystore["encoded"]
+            .as_str()
+            .ok_or_else(|| Error::Custom(String::from("Missing encoded key in keystore")))
+            .and_then(|encoded| base64::decode(encoded).map_err(|error| Error::Custom(format!("{:?}", error))))?;
+
+        let pkcs8 = decrypt(&encoded, password)?;
+        let secret = pkcs8
+            .get(PKCS8_HEADER.len()..PKCS8_HEADER.len() + 64)
+            .filter(|_| pkcs8.starts_with(&PKCS8_HEADER))
+            .ok_or_else(|| Error::Custom(String::from("Invalid pkcs8 key in keystore")))?;
+        // polkadot.js stores sr25519 secret in ed25519 expanded form
+        let secret = schnorrkel::SecretKey::from_ed25519_bytes(secret)
+            .map_err(|_| Error::Custom(String::from("Invalid sr25519 key in keystore")))?;
+
+        Ok(Self::new(sr25519::Pair::from(secret)))
+    }
+}
+
+#[async_trait]
+impl Signer for PairSigner {
+    fn public(&self) -> Public {
+        self.pair.public()
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Signature, Error> {
+        Ok(self.pair.sign(payload))
+    }
+}
+
+/// Asks external signing service, key never leaves it.
+/// Service gets `{ "account": <hex public key>, "payload": <hex> }`
+/// and responds with `{ "signature": <hex> }`.
+pub struct RemoteSigner {
+    url: String,
+    public: Public,
+    client: reqwest::Client,
+}
+
+impl RemoteSigner {
+    /// * `account` - SS58 address of account which key is held by service
+    /// # Errors
+    pub fn new(url: &str, account: &str) -> Result<Self, Error> {
+        let public = Public::from_ss58check(account)
+            .map_err(|error| Error::Custom(format!("Invalid account {}: {:?}", account, error)))?;
+
+        Ok(Self {
+            url: url.to_string(),
+            public,
+            client: reqwest::Client::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    fn public(&self) -> Public {
+        self.public
+    }
+
+    async fn sign(&self, payload: &[u8]) -> Result<Signature, Error> {
+        let response = self
+            .client
+            .post(&self.url)
+            .json(&json!({
+                "account": format!("0x{}", hex::encode(self.public.0)),
+                "payload": format!("0x{}", hex::encode(payload)),
+            }))
+            .send()
+            .await
+            .map_err(|error| Error::Custom(format!("Remote signer request failed: {}", error)))?
+            .error_for_status()
+            .map_err(|error| Error::Custom(format!("Remote signer refused: {}", error)))?
+            .json::<Value>()
+            .await
+            .map_err(|error| Error::Custom(format!("Remote signer response is not a json: {}", error)))?;
+
+        let signature = response["signature"]
+            .as_str()
+            .ok_or_else(|| Error::Custom(format!("Unexpected remote signer response: {}", response)))?;
+        let bytes = hex::decode(signature.trim_start_matches("0x"))
+            .map_err(|error| Error::Custom(format!("Remote signer returned invalid hex: {:?}", error)))?;
+        let signature = Signature::try_from(bytes.as_slice())
+            .map_err(|_| Error::Custom(String::from("Remote signer returned signature of wrong length")))?;
+
+        if sr25519::Pair::verify(&signature, payload, &self.public) {
+            Ok(signature)
+        } else {
+            Err(Error::Custom(String::from("Remote signer returned invalid signature")))
+        }
+    }
+}
+
+const PKCS8_HEADER: [u8; 16] = [48, 83, 2, 1, 1, 48, 5, 6, 3, 43, 101, 112, 4, 34, 4, 32];
+const SALT_LENGTH: usize = 32;
+const NONCE_LENGTH: usize = 24;
+
+/// Layout: salt | N | p | r (u32 little endian) | nonce | ciphertext
+fn decrypt(encoded: &[u8], password: &str) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::Custom(String::from("Invalid encrypted key in keystore"));
+
+    if encoded.len() < SALT_LENGTH + 12 + NONCE_LENGTH {
+        return Err(invalid());
+    }
+    let (salt, rest) = encoded.split_at(SALT_LENGTH);
+    let (params, rest) = rest.split_at(12);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+
+    let read = |index: usize| {
+        let mut bytes = [0_u8; 4];
+        bytes.copy_from_slice(&params[index * 4..index * 4 + 4]);
+        u32::from_le_bytes(bytes)
+    };
+    let (n, p, r) = (read(0), read(1), read(2));
+    if !n.is_power_of_two() {
+        return Err(invalid());
+    }
+    #[allow(clippy::cast_possible_truncation)]
+    let params = scrypt::Params::new(n.trailing_zeros() as u8, r, p).map_err(|_| invalid())?;
+
+    let mut key = [0_u8; 32];
+    scrypt::scrypt(password.as_bytes(), salt, &params, &mut key).map_err(|_| invalid())?;
+
+    XSalsa20Poly1305::new(Key::from_slice(&key))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::Custom(String::from("Wrong keystore password")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PASSWORD: &str = "correct horse";
+    /// Key encrypted by independent xsalsa20-poly1305 implementation, scrypt is weakened to N = 1024
+    const ENCODED: &str = "\
+        Y0ea1poJCyWCd+yPum+ZQZov+ySJgVEGV8lEzNEUjpcABAAAAQAAAAgAAAB4N3tSV1e0lEJ/iQFPl9eZKPOTjRTr\
+        UeJke5Tar1QF4nMShRHHcsU4asfDLFx+1xGHt0Y9onQsxvnJo0KQh9PqXjcieL49HS2bksM4b+3s1/0CoCl5PcR2\
+        QPX8b3NP++FuYSIqp9QneaPYjXWKixF03BR56Lzw3YWfneUPZmtoc77rowx+7RdYJ7JdsCiQo/lirf8rCDUA3YtA\
+        jhoX";
+    /// Secret in ed25519 expanded form, public part of pkcs8 is zeroed as it isn't read
+    const SECRET: &str = "507c3cd7b66db6264bef1b8425c0f445c25bd0b6395adcf103dbd27b57646048\
+        70ef200cc8b5b52958b8a9c9d80de7fcb27efb39500d1a3f8651a9ea086d4cde";
+
+    fn pkcs8() -> Vec<u8> {
+        let mut pkcs8 = PKCS8_HEADER.to_vec();
+        pkcs8.extend(hex::decode(SECRET).unwrap());
+        pkcs8.extend([161, 35, 3, 33, 0]);
+        pkcs8.extend([0; 32]);
+        pkcs8
+    }
+
+    fn temp_file(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("realis-adapter-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn known_key_is_decrypted() {
+        let encoded = base64::decode(ENCODED).unwrap();
+
+        assert_eq!(decrypt(&encoded, PASSWORD).unwrap(), pkcs8());
+    }
+
+    #[test]
+    fn wrong_password_and_broken_key_are_rejected() {
+        let encoded = base64::decode(ENCODED).unwrap();
+
+        assert!(matches!(decrypt(&encoded, "wrong"), Err(Error::Custom(message)) if message.contains("password")));
+        assert!(decrypt(&encoded[..60], PASSWORD).is_err());
+
+        // N must be power of two
+        let mut broken = encoded;
+        broken[SALT_LENGTH] = 3;
+        assert!(decrypt(&broken, PASSWORD).is_err());
+    }
+
+    #[test]
+    fn keystore_gives_pair_of_encrypted_secret() {
+        let keystore = json!({
+            "encoded": ENCODED,
+            "encoding": {
+                "content": ["pkcs8", "sr25519"],
+                "type": ["scrypt", "xsalsa20-poly1305"],
+                "version": "3"
+            },
+        });
+        let path = temp_file("keystore.json", &keystore.to_string());
+
+        let signer = PairSigner::from_keystore(&path, PASSWORD).unwrap();
+        let secret = schnorrkel::SecretKey::from_ed25519_bytes(&hex::decode(SECRET).unwrap()).unwrap();
+        assert_eq!(signer.public(), sr25519::Pair::from(secret).public());
+
+        fs::remove_file(path).unwrap();
+    }
+}
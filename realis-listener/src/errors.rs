@@ -3,4 +3,6 @@ pub enum RpcError {
     Api,
     BlockNotFound,
     EventsNotFound,
+    Database,
+    Attestation,
 }
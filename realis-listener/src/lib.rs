@@ -1,7 +1,7 @@
 mod errors;
 pub mod listener_builder;
 
-use db::Database;
+use db::{Database, UnitOfWork};
 use errors::RpcError;
 use frame_system::{EventRecord, Phase};
 use log::{error, info, warn};
@@ -10,7 +10,10 @@ use std::str::FromStr;
 use tokio::select;
 use web3::types::H160;
 
-use primitives::events::realis::{RealisEventType, TransferNftToBsc, TransferTokenToBsc};
+use primitives::{
+    attestation::Attestor,
+    events::realis::{RealisEventType, TransferNftToBsc, TransferTokenToBsc},
+};
 use runtime::{Block, Event};
 use std::sync::Arc;
 use substrate_api_client::{
@@ -18,7 +21,7 @@ use substrate_api_client::{
     sp_runtime::app_crypto::{sp_core::H256, sr25519},
     Api, Hash,
 };
-use tokio::sync::mpsc::{Sender, UnboundedReceiver, UnboundedSender};
+use tokio::sync::mpsc::{Sender, UnboundedReceiver};
 
 pub struct BlockListener {
     rx: UnboundedReceiver<Hash>,
@@ -26,6 +29,9 @@ pub struct BlockListener {
     api: Api<sr25519::Pair, WsRpcClient>,
     health_checker: HealthChecker,
     db: Arc<Database>,
+    last_processed: Option<u64>,
+    /// Signs every found transfer in multi-operator mode
+    attestor: Option<Arc<Attestor>>,
 }
 
 impl BlockListener {
@@ -44,100 +50,117 @@ impl BlockListener {
             api,
             health_checker,
             db,
+            last_processed: None,
+            attestor: None,
         }
     }
 
+    #[must_use]
+    pub fn with_attestor(mut self, attestor: Arc<Attestor>) -> Self {
+        self.attestor = Some(attestor);
+        self
+    }
+
     /// # Panics
-    #[allow(clippy::match_same_arms)]
     pub async fn listen(&mut self) {
         loop {
             let health_checker = self.health_checker.clone();
             select! {
                 () = health_checker.is_alive() => break,
-                result = self.execute() => {
-                    match result {
-                        Ok(block_number) => match &self.db.update_block_realis(block_number.into()).await {
-                            Ok(_) => info!("Success add realis block to database"),
-                            Err(error) => {
-                                error!("Can't add realis block to database with error: {:?}", error);
-                                self.health_checker.make_sick();
-                            },
-                        },
-                        Err(error) => error!("{:?}", error),
+                Some(hash) = self.rx.recv() => {
+                    match self.get_block(Some(hash)) {
+                        Ok(block) => self.process_blocks(u64::from(block.header.number)).await,
+                        Err(error) => error!("[Realis Listener] - can't get finalized head: {:?}", error),
                     }
                 }
             }
         }
     }
 
+    /// Continue processing from block next after `from`.
     /// # Errors
     /// # Panics
-    #[allow(clippy::match_same_arms)]
-    pub async fn listen_with_restore(&mut self, from: u64, tx: UnboundedSender<H256>) {
-        warn!("Start restore Realis!!!");
-        let hash = self.rx.recv().await;
-
-        match self.get_block(hash) {
-            Ok(block) => {
-                let block_number = u64::from(block.header.number);
-                for number in from..block_number {
-                    match self.api.get_storage_map("System", "BlockHash", number, None) {
-                        Ok(Some(hash)) => {
-                            info!("[Restore] - add to the queue - [{:^8}]", number);
-                            let _result = tx.send(hash);
-                        }
-                        Ok(None) => warn!("[Restore] - missing block - [{:^8}]", number),
-                        Err(error) => error!("[Restore] - {:?}]", error),
-                    }
-                    match self.execute().await {
-                        Ok(block_number) => match &self.db.update_block_realis(block_number.into()).await {
-                            Ok(_) => info!("Success add realis block to database"),
-                            Err(error) => {
-                                error!("Can't add realis block to database with error: {:?}", error);
-                                self.health_checker.make_sick();
-                            }
-                        },
-                        Err(error) => error!("{:?}", error),
-                    }
-                }
+    pub async fn listen_with_restore(&mut self, from: u64) {
+        warn!("Start restore Realis from block {}!!!", from);
+        self.last_processed = Some(from);
+        self.listen().await;
+    }
 
-                self.listen().await;
+    /// Process all blocks up to finalized `head` one by one,
+    /// if some block fail it will be retried with next head.
+    async fn process_blocks(&mut self, head: u64) {
+        let mut number = self.last_processed.map_or(head, |last| last + 1);
+
+        while number <= head {
+            match self.process_block(number).await {
+                Ok(()) => {
+                    info!("Success process realis block [{:^8}]", number);
+                    self.last_processed = Some(number);
+                    number += 1;
+                }
+                Err(error) => {
+                    error!("[Realis Listener] - can't process block [{:^8}]: {:?}", number, error);
+                    break;
+                }
             }
-            Err(error) => {
-                error!("Can't get block from hash: {:?}", error);
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    async fn process_block(&self, number: u64) -> Result<(), RpcError> {
+        let hash = self
+            .api
+            .get_storage_map::<_, H256>("System", "BlockHash", number as u32, None)
+            .map_err(|_| RpcError::Api)?
+            .ok_or(RpcError::BlockNotFound)?;
+
+        let mut work = UnitOfWork::new();
+        self.execute(hash, &mut work)?;
+        work.advance_cursor_realis(number, &format!("{:?}", hash));
+
+        let committed = self.db.commit(work).await.map_err(|error| {
+            error!("Can't add realis block to database with error: {:?}", error);
+            self.health_checker.make_sick();
+            RpcError::Database
+        })?;
+
+        // Transfers are sent only after block is stored with them,
+        // if sending fails they are picked up from database later
+        for event in committed.extrinsics_realis {
+            match self.tx.send(event).await {
+                Ok(()) => info!("Success send to Binance Handler!"),
+                Err(error) => {
+                    error!("Error transfer to Binance Handler {:?}", error);
+                    self.health_checker.make_sick();
+                }
             }
-        };
+        }
+
+        Ok(())
     }
 
-    async fn execute(&mut self) -> Result<u32, RpcError> {
-        let hash = self.rx.recv().await;
-        let block = self.get_block(hash)?;
+    fn execute(&self, hash: H256, work: &mut UnitOfWork) -> Result<(), RpcError> {
+        let block = self.get_block(Some(hash))?;
         let block_number = block.header.number;
-        let events = self.get_events(hash)?;
+        let events = self.get_events(Some(hash))?;
 
-        for event in events {
-            if let Phase::ApplyExtrinsic(_) = event.phase {
+        for (event_index, event) in events.into_iter().enumerate() {
+            #[allow(clippy::cast_possible_truncation)]
+            let event_index = event_index as u32;
+            if let Phase::ApplyExtrinsic(extrinsic_index) = event.phase {
                 match event.event {
                     Event::RealisBridge(realis_bridge::Event::SendTokensToBsc(from, to, value, _)) => {
                         match H160::from_str(&format!("{:?}", to)) {
                             Ok(to) => {
-                                match self
-                                    .tx
-                                    .send(RealisEventType::TransferTokenToBsc(TransferTokenToBsc {
-                                        block: u64::from(block_number),
-                                        hash: hash.unwrap(),
-                                        from,
-                                        to,
-                                        amount: value,
-                                    }))
-                                    .await
-                                {
-                                    Ok(()) => info!("Success send to Binance Handler!"),
-                                    Err(error) => {
-                                        error!("Error transfer to Binance Handler {:?}", error);
-                                        self.health_checker.make_sick();
-                                    }
-                                }
+                                self.add_event(work, RealisEventType::TransferTokenToBsc(TransferTokenToBsc {
+                                    block: u64::from(block_number),
+                                    hash,
+                                    extrinsic_index,
+                                    event_index,
+                                    from,
+                                    to,
+                                    amount: value,
+                                }))?;
                             }
                             Err(error) => error!("Cannot parse account: {:?}", error),
                         }
@@ -145,23 +168,15 @@ impl BlockListener {
                     Event::RealisBridge(realis_bridge::Event::TransferNftToBSC(from, to, token_id)) => {
                         match H160::from_str(&format!("{:?}", to)) {
                             Ok(dest) => {
-                                match self
-                                    .tx
-                                    .send(RealisEventType::TransferNftToBsc(TransferNftToBsc {
-                                        block: u64::from(block_number),
-                                        hash: hash.unwrap(),
-                                        from,
-                                        dest,
-                                        token_id,
-                                    }))
-                                    .await
-                                {
-                                    Ok(()) => info!("Success send to Binance Handler!"),
-                                    Err(error) => {
-                                        error!("Error transfer to Binance Handler {:?}", error);
-                                        self.health_checker.make_sick();
-                                    }
-                                }
+                                self.add_event(work, RealisEventType::TransferNftToBsc(TransferNftToBsc {
+                                    block: u64::from(block_number),
+                                    hash,
+                                    extrinsic_index,
+                                    event_index,
+                                    from,
+                                    dest,
+                                    token_id,
+                                }))?;
                             }
                             Err(error) => error!("Cannot parse account: {:?}", error),
                         }
@@ -171,7 +186,23 @@ impl BlockListener {
             }
         }
 
-        Ok(block_number)
+        Ok(())
+    }
+
+    fn add_event(&self, work: &mut UnitOfWork, event: RealisEventType) -> Result<(), RpcError> {
+        if let Some(attestor) = &self.attestor {
+            match attestor.attest_realis(&event) {
+                Ok(Some(attestation)) => work.add_attestation(attestation),
+                Ok(None) => {}
+                Err(error) => {
+                    error!("Can't attest realis event: {:?}", error);
+                    return Err(RpcError::Attestation);
+                }
+            }
+        }
+        work.add_extrinsic_realis(event);
+
+        Ok(())
     }
 
     fn get_block(&self, hash: Option<H256>) -> Result<Block, RpcError> {
@@ -9,8 +9,7 @@ use substrate_api_client::{rpc::WsRpcClient, sp_runtime::app_crypto::sr25519, Ap
 use log::error;
 use primitives::events::realis::RealisEventType;
 use rust_lib::healthchecker::HealthChecker;
-use substrate_api_client::sp_runtime::app_crypto::sp_core::H256;
-use tokio::sync::mpsc::{unbounded_channel, Sender, UnboundedSender};
+use tokio::sync::mpsc::{unbounded_channel, Sender};
 
 #[allow(clippy::module_name_repetitions)]
 pub struct BlockListenerBuilder {
@@ -33,13 +32,12 @@ impl BlockListenerBuilder {
 
     /// # Panics
     #[must_use]
-    pub fn build(self) -> (BlockListener, UnboundedSender<H256>) {
+    pub fn build(self) -> BlockListener {
         let client = WsRpcClient::new(&self.url);
         let api = Api::<sr25519::Pair, WsRpcClient>::new(client).unwrap();
         let (async_tx, async_rx) = unbounded_channel();
 
         std::thread::spawn({
-            let async_tx = async_tx.clone();
             let api = api.clone();
             let health_checker = self.health_checker.clone();
             move || {
@@ -76,9 +74,6 @@ impl BlockListenerBuilder {
             }
         });
 
-        (
-            BlockListener::new(async_rx, self.tx, api, self.health_checker, self.db),
-            async_tx,
-        )
+        BlockListener::new(async_rx, self.tx, api, self.health_checker, self.db)
     }
 }